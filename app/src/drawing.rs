@@ -9,11 +9,15 @@ pub const COLOR_TILE_DARK: Color = Color { r: 0.247, g: 0.247, b: 0.247, a: 1.0
 pub const COLOR_TILE_HOVER: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 0.2 };
 pub const COLOR_TILE_SELECTED: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 0.4 };
 pub const COLOR_TILE_MOVE: Color = Color { r: 1.0, g: 0.4, b: 0.08, a: 0.2 };
+pub const COLOR_CLOCK: Color = Color { r: 0.835, g: 0.835, b: 0.835, a: 1.0 };
+pub const COLOR_CLOCK_RUNNING: Color = Color { r: 1.0, g: 0.4, b: 0.08, a: 1.0 };
+pub const COLOR_CLOCK_FLAGGED: Color = Color { r: 0.8, g: 0.1, b: 0.1, a: 1.0 };
 
 pub const BOARD_TILE_SIZE: f32 = 64.0;
 pub const INFO_WIDTH: f32 = 256.0;
 pub const SCREEN_WIDTH: f32 = BOARD_TILE_SIZE * 8.0 + INFO_WIDTH;
 pub const SCREEN_HEIGHT: f32 = BOARD_TILE_SIZE * 8.0;
+pub const CLOCK_HEIGHT: f32 = 16.0;
 
 
 pub enum TileOverlayType {
@@ -103,6 +107,31 @@ fn draw_board(gfx: &mut Graphics, context: &mut DrawContext, board: &Board, pers
 
 }
 
+/// Draws the time left of each side as a bar in the info panel, the
+/// opponent's at the top and the player's at the bottom.
+pub fn draw_clocks(draw: &mut Draw, clock: &Clock, perspective: PieceColor) {
+    let total = clock.remaining(PieceColor::White) + clock.remaining(PieceColor::Black);
+    let x = BOARD_TILE_SIZE * 8.0;
+
+    for (color, y) in [(perspective.opposite(), 0.0), (perspective, SCREEN_HEIGHT - CLOCK_HEIGHT)] {
+        let fraction = if total.is_zero() {
+            0.0
+        } else {
+            clock.remaining(color).as_secs_f32() / total.as_secs_f32()
+        };
+        let bar_color = if clock.flagged() == Some(color) {
+            COLOR_CLOCK_FLAGGED
+        } else if clock.running() == Some(color) {
+            COLOR_CLOCK_RUNNING
+        } else {
+            COLOR_CLOCK
+        };
+
+        draw.rect((x, y), (INFO_WIDTH * fraction, CLOCK_HEIGHT))
+            .color(bar_color);
+    }
+}
+
 
 
 
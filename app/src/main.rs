@@ -1,7 +1,9 @@
 use notan::prelude::*;
 use notan::draw::*;
 
+use ruschess_app::drawing::*;
 use ruschess_app::state::*;
+use ruschess_core::*;
 
 #[notan_main]
 fn main() -> Result<(), String> {
@@ -11,6 +13,7 @@ fn main() -> Result<(), String> {
         .set_vsync(true);
     
     notan::init_with(setup)
+        .update(update)
         .draw(draw)
         .add_config(DrawConfig)        
         .add_config(window_config)
@@ -22,10 +25,21 @@ fn setup(gfx: &mut Graphics) -> State {
     State::new(gfx)
 }
 
+fn update(app: &mut App, state: &mut State) {
+    if app.mouse.left_was_pressed() {
+        let (x, y) = app.mouse.position();
+        if let Some(position) = screen_to_board(state.user_color, x, y) {
+            state.click(position);
+        }
+    }
+    state.update();
+}
+
 fn draw(gfx: &mut Graphics, state: &mut State) {
     let mut draw = gfx.create_draw();
     draw.clear(Color::BLACK);
     
     draw.triangle((400.0, 100.0), (100.0, 500.0), (700.0, 500.0));
+    draw_clocks(&mut draw, &state.clock, state.user_color);
     gfx.render(&draw);
 }
\ No newline at end of file
@@ -1,10 +1,32 @@
 use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Duration;
 
 use notan::prelude::*;
+use ruschess_core::*;
+
+/// The engine the user plays against, waiting for its turn or thinking on
+/// a thread of its own, which hands it back along with its move.
+enum Engine {
+    Idle(EnginePlayer),
+    Thinking(Receiver<(EnginePlayer, PlayerAction)>),
+}
 
 #[derive(AppState)]
 pub struct State {    
     pub draw_context: Box<DrawContext>,
+    pub game: GameState,
+    /// The positions since the last capture or pawn move, for repetitions.
+    positions: Vec<u64>,
+    /// Started by the first move, and pressed by every move after it.
+    pub clock: Clock,
+    /// The side the user plays, the engine playing the other one.
+    pub user_color: PieceColor,
+    /// The square of the user's piece about to move.
+    pub selected: Option<Position>,
+    pub outcome: Option<Outcome>,
+    engine: Option<Engine>,
 }
 
 impl State {
@@ -13,9 +35,123 @@ impl State {
             textures: TextureTable::new(gfx),
         };
 
+        let game = GameState::default();
+        let user_color = PieceColor::White;
+        // without limits of its own, the engine budgets its time from the clock
+        let mut engine = EnginePlayer::new(SearchLimits::infinite());
+        engine.on_game_start(user_color.opposite(), &game);
+
         Self {
             draw_context: Box::new(draw_context),
+            positions: vec![game.hash()],
+            game,
+            clock: Clock::sudden_death(Duration::from_secs(5 * 60), Timing::Fischer(Duration::from_secs(3))),
+            user_color,
+            selected: None,
+            outcome: None,
+            engine: Some(Engine::Idle(engine)),
+        }
+    }
+
+    /// Plays `m`, which must be legal, and hands the turn over on the clock.
+    pub fn play_move(&mut self, m: Move) {
+        self.game.apply_move(m);
+        if self.game.halfmove_clock == 0 {
+            self.positions.clear();
         }
+        self.positions.push(self.game.hash());
+
+        // the first move is free, and starts the clock of the side to reply
+        match self.clock.running() {
+            Some(_) => {
+                self.clock.press();
+            }
+            None => self.clock.start(self.game.turn),
+        }
+        self.outcome = adjudicate(&self.game, &self.positions);
+        if self.outcome.is_some() {
+            self.clock.stop();
+        }
+    }
+
+    /// Handles a click of the user on `position`: selects one of their pieces
+    /// that can move, or moves the selected piece there, promoting to a queen.
+    pub fn click(&mut self, position: Position) {
+        if self.outcome.is_some() || self.game.turn != self.user_color {
+            return;
+        }
+        let chosen = self.selected.and_then(|from| {
+            self.game
+                .find_move(from, position, None)
+                .or_else(|| self.game.find_move(from, position, Some(PieceKind::Queen)))
+        });
+        match chosen {
+            Some(m) => {
+                self.selected = None;
+                self.play_move(m);
+            }
+            None => {
+                let movable = self.game.legal_moves().iter().any(|m| m.from() == position);
+                self.selected = movable.then_some(position);
+            }
+        }
+    }
+
+    /// Called every frame: ends the game when a flag falls, and lets the
+    /// engine think on its turn, telling it the time left on the clock.
+    pub fn update(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        if let Some(loser) = self.clock.flagged() {
+            self.clock.stop();
+            self.outcome = Some(if self.game.has_insufficient_material(loser.opposite()) {
+                Outcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+            } else {
+                Outcome::Win {
+                    winner: loser.opposite(),
+                    reason: WinReason::Timeout,
+                }
+            });
+            return;
+        }
+
+        self.engine = match self.engine.take() {
+            Some(Engine::Idle(mut engine)) if self.game.turn != self.user_color => {
+                engine.on_clock(self.clock.time_control(PieceColor::White), self.clock.time_control(PieceColor::Black));
+                let state = self.game.clone();
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let action = engine.get_move(&state);
+                    let _ = sender.send((engine, action));
+                });
+                Some(Engine::Thinking(receiver))
+            }
+            Some(Engine::Thinking(receiver)) => match receiver.try_recv() {
+                Ok((engine, action)) => {
+                    match action.played_move() {
+                        Some(m) => self.play_move(m),
+                        None => self.resign_engine(),
+                    }
+                    Some(Engine::Idle(engine))
+                }
+                Err(TryRecvError::Empty) => Some(Engine::Thinking(receiver)),
+                // the engine panicked, and is gone
+                Err(TryRecvError::Disconnected) => {
+                    self.resign_engine();
+                    None
+                }
+            },
+            engine => engine,
+        };
+    }
+
+    fn resign_engine(&mut self) {
+        self.clock.stop();
+        self.outcome = Some(Outcome::Win {
+            winner: self.user_color,
+            reason: WinReason::Resignation,
+        });
     }
 }
 
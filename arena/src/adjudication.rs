@@ -0,0 +1,170 @@
+use ruschess_core::*;
+
+/// Scores a game as drawn once both sides' scores have stayed within
+/// `score` centipawns of equality for `plies` plies in a row, from move
+/// `after_move` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawAdjudication {
+    pub after_move: usize,
+    pub plies: usize,
+    pub score: i32,
+}
+
+/// Scores a game as won once one side has been ahead by at least `score`
+/// centipawns for `plies` plies in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResignAdjudication {
+    pub plies: usize,
+    pub score: i32,
+}
+
+/// When to stop a game early rather than play it out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AdjudicationRules {
+    /// Scores a game as drawn once this many moves have been played in it.
+    pub max_moves: Option<usize>,
+    pub draw: Option<DrawAdjudication>,
+    pub resign: Option<ResignAdjudication>,
+}
+
+/// Applies `AdjudicationRules` to a game as it is played, judging it by the
+/// scores the players reported for their moves, see `Player::last_score`.
+/// A move without a score, e.g. by a bot, breaks the streaks of both rules.
+pub struct Adjudicator {
+    rules: AdjudicationRules,
+    plies: usize,
+    draw_streak: usize,
+    /// The side that has been winning, and for how many plies.
+    resign_streak: Option<(PieceColor, usize)>,
+}
+
+impl Adjudicator {
+    pub fn new(rules: AdjudicationRules) -> Self {
+        Self {
+            rules,
+            plies: 0,
+            draw_streak: 0,
+            resign_streak: None,
+        }
+    }
+
+    /// Judges `state`, the next position of the game, where it is about to be
+    /// somebody's turn, given the score of the move that led to it from
+    /// White's point of view, as passed by `GameRunner::with_adjudicator`.
+    pub fn check(&mut self, state: &GameState, white_score: Option<i32>) -> Option<Outcome> {
+        let plies = self.plies;
+        self.plies += 1;
+        if self.rules.max_moves.is_some_and(|moves| plies >= 2 * moves) {
+            return Some(Outcome::Draw(DrawReason::Adjudication));
+        }
+        if self.rules.draw.is_none() && self.rules.resign.is_none() {
+            return None;
+        }
+
+        if let Some(rule) = self.rules.resign {
+            let leader = match white_score {
+                Some(score) if score >= rule.score => Some(PieceColor::White),
+                Some(score) if score <= -rule.score => Some(PieceColor::Black),
+                _ => None,
+            };
+            self.resign_streak = match (leader, self.resign_streak) {
+                (Some(leader), Some((previous, streak))) if leader == previous => Some((leader, streak + 1)),
+                (Some(leader), _) => Some((leader, 1)),
+                (None, _) => None,
+            };
+            if let Some((winner, streak)) = self.resign_streak {
+                if streak >= rule.plies {
+                    return Some(Outcome::Win {
+                        winner,
+                        reason: WinReason::Adjudication,
+                    });
+                }
+            }
+        }
+
+        if let Some(rule) = self.rules.draw {
+            let level = white_score.is_some_and(|score| score.abs() <= rule.score);
+            if state.fullmove_number >= rule.after_move && level {
+                self.draw_streak += 1;
+            } else {
+                self.draw_streak = 0;
+            }
+            if self.draw_streak >= rule.plies {
+                return Some(Outcome::Draw(DrawReason::Adjudication));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the position of `fen` with each of `scores` in turn, and
+    /// returns the last verdict.
+    fn check(rules: AdjudicationRules, fen: &str, scores: &[Option<i32>]) -> Option<Outcome> {
+        let state = GameState::from_fen(fen).unwrap();
+        let mut adjudicator = Adjudicator::new(rules);
+        let mut verdict = None;
+        for score in scores {
+            verdict = adjudicator.check(&state, *score);
+        }
+        verdict
+    }
+
+    const LATE: &str = "4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 30";
+    const EARLY: &str = "4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 10";
+    const QUEEN_UP: Option<i32> = Some(900);
+    const EQUAL: Option<i32> = Some(0);
+
+    #[test]
+    fn test_no_rules() {
+        assert_eq!(check(AdjudicationRules::default(), LATE, &[QUEEN_UP; 100]), None);
+    }
+
+    #[test]
+    fn test_max_moves() {
+        let rules = AdjudicationRules {
+            max_moves: Some(2),
+            ..AdjudicationRules::default()
+        };
+        assert_eq!(check(rules, LATE, &[EQUAL; 4]), None);
+        assert_eq!(check(rules, LATE, &[EQUAL; 5]), Some(Outcome::Draw(DrawReason::Adjudication)));
+    }
+
+    #[test]
+    fn test_resign_adjudication() {
+        let rules = AdjudicationRules {
+            resign: Some(ResignAdjudication { plies: 3, score: 500 }),
+            ..AdjudicationRules::default()
+        };
+        assert_eq!(check(rules, LATE, &[QUEEN_UP; 2]), None);
+        let win = |winner| Outcome::Win {
+            winner,
+            reason: WinReason::Adjudication,
+        };
+        assert_eq!(check(rules, LATE, &[QUEEN_UP; 3]), Some(win(PieceColor::White)));
+        assert_eq!(check(rules, LATE, &[Some(-900); 3]), Some(win(PieceColor::Black)));
+        assert_eq!(check(rules, LATE, &[EQUAL; 10]), None);
+        // a move without a score starts the count again
+        assert_eq!(check(rules, LATE, &[QUEEN_UP, QUEEN_UP, None, QUEEN_UP, QUEEN_UP]), None);
+    }
+
+    #[test]
+    fn test_draw_adjudication() {
+        let rules = AdjudicationRules {
+            draw: Some(DrawAdjudication {
+                after_move: 20,
+                plies: 4,
+                score: 50,
+            }),
+            ..AdjudicationRules::default()
+        };
+        assert_eq!(check(rules, LATE, &[EQUAL; 3]), None);
+        assert_eq!(check(rules, LATE, &[EQUAL; 4]), Some(Outcome::Draw(DrawReason::Adjudication)));
+        assert_eq!(check(rules, LATE, &[QUEEN_UP; 10]), None);
+        assert_eq!(check(rules, LATE, &[None; 10]), None);
+        assert_eq!(check(rules, EARLY, &[EQUAL; 10]), None);
+    }
+}
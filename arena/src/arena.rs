@@ -0,0 +1,241 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use ruschess_core::*;
+
+use crate::{
+    adjudication::{AdjudicationRules, Adjudicator},
+    players::PlayerSpec,
+    stats::{Sprt, Tally},
+};
+
+/// A match between two players.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaConfig {
+    pub players: [PlayerSpec; 2],
+    pub games: usize,
+    /// How many games to play at the same time.
+    pub concurrency: usize,
+    /// The positions to start from, each played twice with colors reversed.
+    /// Games start from the standard position if there are none.
+    pub openings: Vec<GameState>,
+    /// The time control in the format of `Clock::parse`, or `None` to play
+    /// without clocks, with the players thinking as long as they are set to.
+    /// Engines learn the time on their clocks before each move.
+    pub time_control: Option<String>,
+    pub adjudication: AdjudicationRules,
+    /// Stops the match early once the test reaches a verdict.
+    pub sprt: Option<Sprt>,
+    /// Seeds the random choices of the players.
+    pub seed: u64,
+}
+
+impl ArenaConfig {
+    pub fn new(first: PlayerSpec, second: PlayerSpec) -> Self {
+        Self {
+            players: [first, second],
+            games: 2,
+            concurrency: 1,
+            openings: Vec::new(),
+            time_control: None,
+            adjudication: AdjudicationRules::default(),
+            sprt: None,
+            seed: 0,
+        }
+    }
+}
+
+/// A finished game of a match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaGame {
+    /// The number of the game in the match, from 0.
+    pub index: usize,
+    /// Which of the players had white, 0 for the first one.
+    pub white: usize,
+    pub record: GameRecord,
+}
+
+impl ArenaGame {
+    /// The color the first player had.
+    pub fn first_player_color(&self) -> PieceColor {
+        match self.white {
+            0 => PieceColor::White,
+            _ => PieceColor::Black,
+        }
+    }
+}
+
+impl Tally {
+    pub fn add(&mut self, game: &ArenaGame) {
+        match game.record.outcome.winner() {
+            Some(winner) if winner == game.first_player_color() => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+/// Plays one game of the match.
+pub fn play_game(config: &ArenaConfig, index: usize) -> ArenaGame {
+    // each opening is played by both players with each color in turn
+    let white = index % 2;
+    let start = match config.openings.len() {
+        0 => GameState::default(),
+        count => config.openings[index / 2 % count].clone(),
+    };
+    let seed = config.seed.wrapping_add(index as u64 * 2);
+    let clock = config.time_control.as_deref().and_then(Clock::parse);
+    let mut first = config.players[0].build(seed, clock.is_some());
+    let mut second = config.players[1].build(seed.wrapping_add(1), clock.is_some());
+    let (white_player, black_player) = match white {
+        0 => (&mut *first, &mut *second),
+        _ => (&mut *second, &mut *first),
+    };
+
+    let mut adjudicator = Adjudicator::new(config.adjudication);
+    let mut runner = GameRunner::new(white_player, black_player)
+        .with_start(start)
+        .with_adjudicator(move |state, score| adjudicator.check(state, score));
+    if let Some(clock) = clock {
+        runner = runner.with_clock(clock);
+    }
+    ArenaGame {
+        index,
+        white,
+        record: runner.run(),
+    }
+}
+
+/// Plays the match, calling `on_game` as each game finishes with the game
+/// and the standing after it, and returns the final standing. With an
+/// SPRT, no more games are started once it reaches a verdict; the games
+/// still running are finished and counted.
+pub fn run_arena(config: &ArenaConfig, mut on_game: impl FnMut(&ArenaGame, &Tally)) -> Tally {
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut tally = Tally::default();
+
+    thread::scope(|scope| {
+        for _ in 0..config.concurrency.clamp(1, config.games.max(1)) {
+            let sender = sender.clone();
+            let (next, stop) = (&next, &stop);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= config.games {
+                        break;
+                    }
+                    if sender.send(play_game(config, index)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for game in receiver {
+            tally.add(&game);
+            on_game(&game, &tally);
+            if config.sprt.is_some_and(|sprt| sprt.verdict(&tally).is_some()) {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+    tally
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(first: &str, second: &str, games: usize) -> ArenaConfig {
+        let mut config = ArenaConfig::new(PlayerSpec::parse(first).unwrap(), PlayerSpec::parse(second).unwrap());
+        config.games = games;
+        config
+    }
+
+    #[test]
+    fn test_play_game_alternates_colors_and_openings() {
+        let mut config = config("random", "greedy", 4);
+        config.openings = vec![
+            GameState::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap(),
+            GameState::from_fen("4k3/8/8/8/8/8/8/4KB2 w - - 0 1").unwrap(),
+        ];
+        let games: Vec<_> = (0..4).map(|index| play_game(&config, index)).collect();
+        assert_eq!(games.iter().map(|game| game.white).collect::<Vec<_>>(), vec![0, 1, 0, 1]);
+        assert_eq!(games[0].record.start, config.openings[0]);
+        assert_eq!(games[1].record.start, config.openings[0]);
+        assert_eq!(games[2].record.start, config.openings[1]);
+        // a lone bishop cannot mate
+        assert_eq!(games[2].record.outcome, Outcome::Draw(DrawReason::InsufficientMaterial));
+        assert_eq!(games[1].first_player_color(), PieceColor::Black);
+    }
+
+    #[test]
+    fn test_play_game_is_reproducible() {
+        let config = config("random", "random", 1);
+        assert_eq!(play_game(&config, 0).record.moves, play_game(&config, 0).record.moves);
+    }
+
+    #[test]
+    fn test_play_game_with_largest_seed() {
+        let mut config = config("random", "random", 2);
+        config.seed = u64::MAX;
+        config.adjudication.max_moves = Some(2);
+        assert_eq!(play_game(&config, 1).record.moves.len(), 4);
+    }
+
+    #[test]
+    fn test_play_game_with_adjudication() {
+        let mut config = config("random", "random", 1);
+        config.adjudication.max_moves = Some(5);
+        let game = play_game(&config, 0);
+        assert_eq!(game.record.outcome, Outcome::Draw(DrawReason::Adjudication));
+        assert_eq!(game.record.moves.len(), 10);
+    }
+
+    #[test]
+    fn test_play_game_with_clock() {
+        let mut config = config("engine", "engine", 1);
+        config.time_control = Some("1+0.05".to_string());
+        config.adjudication.max_moves = Some(5);
+        // unlimited engines budget their time from the clock
+        let game = play_game(&config, 0);
+        assert_eq!(game.record.outcome, Outcome::Draw(DrawReason::Adjudication));
+    }
+
+    #[test]
+    fn test_run_arena() {
+        let mut config = config("cautious", "random", 6);
+        config.concurrency = 3;
+        config.adjudication.max_moves = Some(60);
+        let mut seen = Vec::new();
+        let tally = run_arena(&config, |game, tally| {
+            seen.push(game.index);
+            assert_eq!(tally.games() as usize, seen.len());
+        });
+        seen.sort();
+        assert_eq!(seen, (0..6).collect::<Vec<_>>());
+        assert_eq!(tally.games(), 6);
+        // random moves do not get far against a player that mates in one
+        assert!(tally.losses <= 1, "{:?}", tally);
+    }
+
+    #[test]
+    fn test_run_arena_stops_at_sprt_verdict() {
+        let mut config = config("random", "random", 1000);
+        config.adjudication.max_moves = Some(30);
+        config.adjudication.resign = Some(crate::ResignAdjudication { plies: 2, score: 300 });
+        config.sprt = Some(Sprt {
+            elo0: 0.0,
+            elo1: 200.0,
+            alpha: 0.1,
+            beta: 0.1,
+        });
+        let tally = run_arena(&config, |_, _| {});
+        assert!(tally.games() < 200, "{:?}", tally);
+        assert_eq!(config.sprt.unwrap().verdict(&tally), Some(crate::SprtVerdict::AcceptH0));
+    }
+}
@@ -0,0 +1,13 @@
+mod adjudication;
+mod arena;
+mod openings;
+mod pgn;
+mod players;
+mod stats;
+
+pub use adjudication::*;
+pub use arena::*;
+pub use openings::*;
+pub use pgn::*;
+pub use players::*;
+pub use stats::*;
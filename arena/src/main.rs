@@ -0,0 +1,199 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use ruschess_arena::*;
+use ruschess_core::*;
+
+const USAGE: &str = "\
+usage: ruschess-arena --player1 <spec> --player2 <spec> [options]
+
+players:
+  random, greedy, cautious      the built-in bots
+  engine[:depth=N,nodes=N,movetime=MS,skill=N,elo=N]
+  uci:<command>[,depth=N,nodes=N,movetime=MS,option.<name>=<value>]
+  any player also takes name=<name>
+
+options:
+  --games N                     number of games, default 2
+  --concurrency N               games played at once, default 1
+  --openings FILE               EPD or PGN file of starting positions
+  --tc CONTROL                  PGN time control, e.g. 40/60+1 or 10+0.1
+  --pgn FILE                    write the games to FILE
+  --seed N                      seed for the players' random choices
+  --max-moves N                 adjudicate a draw after N moves
+  --draw-adjudication after=N,plies=N,score=CP
+  --resign-adjudication plies=N,score=CP
+  --sprt elo0=E,elo1=E,alpha=A,beta=B
+";
+
+/// Splits `key=value,...` settings, checking that each key is one of `keys`.
+fn settings<'a>(value: &'a str, keys: &[&str]) -> Result<Vec<(&'a str, &'a str)>, String> {
+    value
+        .split(',')
+        .map(|setting| match setting.split_once('=') {
+            Some((key, value)) if keys.contains(&key) => Ok((key, value)),
+            _ => Err(format!("expected one of {} as key=value: {}", keys.join(", "), setting)),
+        })
+        .collect()
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {}", name, value))
+}
+
+struct Options {
+    config: ArenaConfig,
+    pgn: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut players = [None, None];
+    let mut config = ArenaConfig::new(PlayerSpec::parse("random")?, PlayerSpec::parse("random")?);
+    let mut pgn = None;
+
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            print!("{}", USAGE);
+            process::exit(0);
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--player1" => players[0] = Some(PlayerSpec::parse(&value)?),
+            "--player2" => players[1] = Some(PlayerSpec::parse(&value)?),
+            "--games" => config.games = number(&flag, &value)?,
+            "--concurrency" => config.concurrency = number(&flag, &value)?,
+            "--openings" => config.openings = load_openings(Path::new(&value))?,
+            "--tc" => {
+                if Clock::parse(&value).is_none() {
+                    return Err(format!("invalid time control: {}", value));
+                }
+                config.time_control = Some(value);
+            }
+            "--pgn" => pgn = Some(value),
+            "--seed" => config.seed = number(&flag, &value)?,
+            "--max-moves" => config.adjudication.max_moves = Some(number(&flag, &value)?),
+            "--draw-adjudication" => {
+                let mut rule = DrawAdjudication {
+                    after_move: 40,
+                    plies: 8,
+                    score: 10,
+                };
+                for (key, value) in settings(&value, &["after", "plies", "score"])? {
+                    match key {
+                        "after" => rule.after_move = number(key, value)?,
+                        "plies" => rule.plies = number(key, value)?,
+                        _ => rule.score = number(key, value)?,
+                    }
+                }
+                config.adjudication.draw = Some(rule);
+            }
+            "--resign-adjudication" => {
+                let mut rule = ResignAdjudication { plies: 6, score: 1000 };
+                for (key, value) in settings(&value, &["plies", "score"])? {
+                    match key {
+                        "plies" => rule.plies = number(key, value)?,
+                        _ => rule.score = number(key, value)?,
+                    }
+                }
+                config.adjudication.resign = Some(rule);
+            }
+            "--sprt" => {
+                let mut sprt = Sprt::default();
+                for (key, value) in settings(&value, &["elo0", "elo1", "alpha", "beta"])? {
+                    match key {
+                        "elo0" => sprt.elo0 = number(key, value)?,
+                        "elo1" => sprt.elo1 = number(key, value)?,
+                        "alpha" => sprt.alpha = number(key, value)?,
+                        _ => sprt.beta = number(key, value)?,
+                    }
+                }
+                config.sprt = Some(sprt);
+            }
+            _ => return Err(format!("unknown option: {}", flag)),
+        }
+    }
+
+    let [Some(first), Some(second)] = players else {
+        return Err("both --player1 and --player2 are needed".to_string());
+    };
+    config.players = [first, second];
+    Ok(Options { config, pgn })
+}
+
+fn main() {
+    let Options { config, pgn } = parse_args(env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, USAGE);
+        process::exit(2);
+    });
+    let mut pgn = pgn.map(|path| {
+        File::create(&path).unwrap_or_else(|error| {
+            eprintln!("cannot create {}: {}", path, error);
+            process::exit(1);
+        })
+    });
+
+    let names = [&config.players[0].name, &config.players[1].name];
+    let event = format!("{} vs {}", names[0], names[1]);
+    let date = pgn_date();
+    let tally = run_arena(&config, |game, tally| {
+        let (white, black) = (names[game.white], names[1 - game.white]);
+        println!(
+            "Finished game {} ({} vs {}): {} {{{}}}",
+            game.index + 1,
+            white,
+            black,
+            game.record.outcome.result(),
+            describe(game.record.outcome)
+        );
+        println!(
+            "Score of {}: {} - {} - {} [{:.3}] {}",
+            event,
+            tally.wins,
+            tally.losses,
+            tally.draws,
+            tally.score().unwrap_or(0.5),
+            tally.games()
+        );
+        if let Some(file) = &mut pgn {
+            let tags = [
+                ("Event", event.clone()),
+                ("Site", "ruschess-arena".to_string()),
+                ("Date", date.clone()),
+                ("Round", (game.index + 1).to_string()),
+                ("White", white.clone()),
+                ("Black", black.clone()),
+            ];
+            let written = write!(file, "{}", write_pgn(&game.record, &tags)).and_then(|_| file.flush());
+            if let Err(error) = written {
+                eprintln!("cannot write the game: {}", error);
+            }
+        }
+    });
+
+    let mut stdout = io::stdout().lock();
+    if let Some(elo) = tally.elo() {
+        writeln!(stdout, "Elo difference: {}", elo).unwrap();
+    }
+    if let Some(sprt) = config.sprt {
+        let (lower, upper) = sprt.bounds();
+        let verdict = match sprt.verdict(&tally) {
+            Some(SprtVerdict::AcceptH0) => "H0 accepted",
+            Some(SprtVerdict::AcceptH1) => "H1 accepted",
+            None => "no verdict",
+        };
+        writeln!(
+            stdout,
+            "SPRT: llr {:.2} ({:.2}, {:.2}) [{:.1}, {:.1}]: {}",
+            sprt.llr(&tally),
+            lower,
+            upper,
+            sprt.elo0,
+            sprt.elo1,
+            verdict
+        )
+        .unwrap();
+    }
+}
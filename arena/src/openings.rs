@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+use ruschess_core::*;
+
+use crate::pgn::parse_pgn;
+
+/// Reads positions from EPD, one per line. The operations after the four
+/// position fields are ignored, unless they are the two move counters of a
+/// full FEN.
+pub fn parse_epd(text: &str) -> Result<Vec<GameState>, String> {
+    let mut positions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(format!("line {}: not a position: {}", number + 1, line));
+        }
+        let counters = match fields.get(4..6) {
+            Some([halfmove, fullmove]) if halfmove.parse::<u32>().is_ok() && fullmove.parse::<u32>().is_ok() => {
+                format!("{} {}", halfmove, fullmove)
+            }
+            _ => "0 1".to_string(),
+        };
+        let fen = format!("{} {}", fields[..4].join(" "), counters);
+        let state = GameState::from_fen(&fen).map_err(|error| format!("line {}: {:?}", number + 1, error))?;
+        positions.push(state);
+    }
+    Ok(positions)
+}
+
+/// Reads opening positions from an EPD file, or from a PGN file, where each
+/// game gives the position after its moves. The format is told by the
+/// extension, `.pgn` for PGN and anything else for EPD.
+pub fn load_openings(path: &Path) -> Result<Vec<GameState>, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("cannot read {}: {}", path.display(), error))?;
+    let is_pgn = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pgn"));
+    let openings = if is_pgn {
+        parse_pgn(&text)?.iter().map(|game| game.final_state()).collect()
+    } else {
+        parse_epd(&text)?
+    };
+    if openings.is_empty() {
+        return Err(format!("no openings in {}", path.display()));
+    }
+    Ok(openings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_parse_epd() {
+        let text = "\
+# a comment
+rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 id \"king's pawn\";
+
+rnbqkb1r/pppppppp/5n2/8/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 1 2
+";
+        let positions = parse_epd(text).unwrap();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert_eq!(positions[1].fullmove_number, 2);
+    }
+
+    #[test]
+    fn test_parse_epd_invalid() {
+        assert_eq!(parse_epd("\nrnbqkbnr w").unwrap_err(), "line 2: not a position: rnbqkbnr w");
+        assert!(parse_epd("rnbqkbnr/pppppppp w KQkq -").is_err());
+    }
+
+    #[test]
+    fn test_load_openings() {
+        let directory = env::temp_dir();
+        let pgn = directory.join(format!("ruschess-openings-{}.pgn", std::process::id()));
+        fs::write(&pgn, "1. d4 d5 *\n\n1. e4 c5 *\n").unwrap();
+        let openings = load_openings(&pgn).unwrap();
+        fs::remove_file(&pgn).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[1].to_fen(), "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2");
+
+        let epd = directory.join(format!("ruschess-openings-{}.epd", std::process::id()));
+        fs::write(&epd, "\n").unwrap();
+        let error = load_openings(&epd).unwrap_err();
+        fs::remove_file(&epd).unwrap();
+        assert!(error.starts_with("no openings in "));
+        assert!(load_openings(Path::new("/nonexistent/openings.epd")).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ruschess_core::*;
+
+/// The PGN movetext is wrapped at this width.
+const LINE_WIDTH: usize = 79;
+
+/// A game read from PGN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: GameState,
+    pub moves: Vec<Move>,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+    /// The position after all the moves.
+    pub fn final_state(&self) -> GameState {
+        let mut state = self.start.clone();
+        for m in &self.moves {
+            state.apply_move(*m);
+        }
+        state
+    }
+}
+
+/// The value of the PGN `Termination` tag for `outcome`.
+pub fn termination(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Win { reason: WinReason::Timeout, .. } => "time forfeit",
+        Outcome::Win { reason: WinReason::IllegalMove, .. } => "rules infraction",
+        Outcome::Win { reason: WinReason::Adjudication, .. } | Outcome::Draw(DrawReason::Adjudication) => {
+            "adjudication"
+        }
+        _ => "normal",
+    }
+}
+
+/// A short description of how the game ended, written as a comment after the moves.
+pub fn describe(outcome: Outcome) -> String {
+    match outcome {
+        Outcome::Win { winner, reason } => {
+            let winner = match winner {
+                PieceColor::White => "White",
+                PieceColor::Black => "Black",
+            };
+            let reason = match reason {
+                WinReason::Checkmate => "mates",
+                WinReason::Resignation => "wins by resignation",
+                WinReason::Timeout => "wins on time",
+                WinReason::IllegalMove => "wins by an illegal move",
+                WinReason::Adjudication => "wins by adjudication",
+            };
+            format!("{} {}", winner, reason)
+        }
+        Outcome::Draw(reason) => match reason {
+            DrawReason::Stalemate => "Draw by stalemate",
+            DrawReason::Agreement => "Draw by agreement",
+            DrawReason::Repetition => "Draw by repetition",
+            DrawReason::FiftyMoveRule => "Draw by the fifty move rule",
+            DrawReason::InsufficientMaterial => "Draw by insufficient material",
+            DrawReason::TimeoutVsInsufficientMaterial => "Draw by timeout vs insufficient material",
+            DrawReason::Adjudication => "Draw by adjudication",
+        }
+        .to_string(),
+    }
+}
+
+/// Writes `record` as PGN, with `tags` first and then the `Result` and,
+/// for games not from the standard start position, the `FEN` tags.
+pub fn write_pgn(record: &GameRecord, tags: &[(&str, String)]) -> String {
+    let mut pgn = String::new();
+    for (name, value) in tags {
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+    let result = record.outcome.result();
+    pgn.push_str(&format!("[Result \"{}\"]\n", result));
+    let fen = record.start.to_fen();
+    if fen != GameState::default().to_fen() {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+    }
+    pgn.push_str(&format!("[Termination \"{}\"]\n\n", termination(record.outcome)));
+
+    let mut tokens = Vec::new();
+    let mut state = record.start.clone();
+    for (index, m) in record.moves.iter().enumerate() {
+        if state.turn == PieceColor::White {
+            tokens.push(format!("{}.", state.fullmove_number));
+        } else if index == 0 {
+            tokens.push(format!("{}...", state.fullmove_number));
+        }
+        tokens.push(to_san(&state, *m));
+        state.apply_move(*m);
+    }
+    tokens.push(format!("{{{}}}", describe(record.outcome)));
+    tokens.push(result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    pgn
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Today's date as written in the PGN `Date` tag, e.g. `2024.03.09`.
+pub fn pgn_date() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() / 86400) as i64;
+    // the civil date from days since 1970-01-01, after Howard Hinnant's algorithm
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Reads all games from PGN text. Comments, variations, move numbers and
+/// annotations are skipped.
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, String> {
+    let mut games = Vec::new();
+    let mut tags = Vec::new();
+    let mut movetext = String::new();
+    let mut in_movetext = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('%') {
+            continue;
+        }
+        if line.starts_with('[') && !in_comment(&movetext) {
+            if in_movetext {
+                games.push(parse_game(std::mem::take(&mut tags), &movetext)?);
+                movetext.clear();
+                in_movetext = false;
+            }
+            tags.push(parse_tag(line)?);
+        } else if !line.is_empty() || in_movetext {
+            in_movetext = true;
+            movetext.push_str(line);
+            movetext.push('\n');
+            // games without tags are only told apart by their results
+            if !in_comment(&movetext) && line.split_whitespace().last().is_some_and(is_result) {
+                games.push(parse_game(std::mem::take(&mut tags), &movetext)?);
+                movetext.clear();
+                in_movetext = false;
+            }
+        }
+    }
+    if in_movetext || !tags.is_empty() {
+        games.push(parse_game(tags, &movetext)?);
+    }
+    Ok(games)
+}
+
+fn is_result(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+/// Whether `movetext` ends inside a `{` comment.
+fn in_comment(movetext: &str) -> bool {
+    movetext.rfind('{') > movetext.rfind('}')
+}
+
+fn parse_tag(line: &str) -> Result<(String, String), String> {
+    let invalid = || format!("invalid tag: {}", line);
+    let inner = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')).ok_or_else(invalid)?;
+    let (name, value) = inner.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"')).ok_or_else(invalid)?;
+    Ok((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+fn parse_game(tags: Vec<(String, String)>, movetext: &str) -> Result<PgnGame, String> {
+    let start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => GameState::from_fen(fen).map_err(|error| format!("invalid FEN {}: {:?}", fen, error))?,
+        None => GameState::default(),
+    };
+
+    // drop comments and variations, which may nest
+    let mut plain = String::new();
+    let mut depth = 0;
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if depth == 0 => {
+                chars.by_ref().find(|c| *c == '}');
+                plain.push(' ');
+            }
+            ';' if depth == 0 => {
+                chars.by_ref().find(|c| *c == '\n');
+                plain.push(' ');
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => plain.push(c),
+            _ => {}
+        }
+    }
+
+    let mut state = start.clone();
+    let mut moves = Vec::new();
+    for token in plain.split_whitespace() {
+        if token.starts_with('$') || is_result(token) {
+            continue;
+        }
+        // move numbers may be glued to the move, as in `1.e4`
+        let token = match token.find(|c: char| !c.is_ascii_digit()) {
+            Some(index) if index > 0 && token[index..].starts_with('.') => token[index..].trim_start_matches('.'),
+            _ => token,
+        };
+        if token.is_empty() {
+            continue;
+        }
+        let m = parse_san(&state, token).ok_or_else(|| format!("illegal move: {}", token))?;
+        state.apply_move(m);
+        moves.push(m);
+    }
+    Ok(PgnGame { tags, start, moves })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(fen: Option<&str>, moves: &[&str], outcome: Outcome) -> GameRecord {
+        let start = fen.map_or_else(GameState::default, |fen| GameState::from_fen(fen).unwrap());
+        let mut state = start.clone();
+        let moves = moves
+            .iter()
+            .map(|uci| {
+                let m = parse_uci(&state, uci).unwrap();
+                state.apply_move(m);
+                m
+            })
+            .collect::<Vec<_>>();
+        GameRecord {
+            start,
+            move_times: vec![Default::default(); moves.len()],
+            draw_offers: vec![false; moves.len()],
+            moves,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_write_pgn() {
+        let mate = Outcome::Win {
+            winner: PieceColor::Black,
+            reason: WinReason::Checkmate,
+        };
+        let game = record(None, &["f2f3", "e7e5", "g2g4", "d8h4"], mate);
+        let pgn = write_pgn(&game, &[("White", "a \"b\"".to_string())]);
+        assert_eq!(
+            pgn,
+            "[White \"a \\\"b\\\"\"]\n[Result \"0-1\"]\n[Termination \"normal\"]\n\n\
+             1. f3 e5 2. g4 Qh4# {Black mates} 0-1\n\n"
+        );
+    }
+
+    #[test]
+    fn test_write_pgn_from_position() {
+        let game = record(
+            Some("4k3/8/8/8/8/8/8/R3K3 b - - 3 40"),
+            &["e8d7", "a1a7"],
+            Outcome::Draw(DrawReason::Adjudication),
+        );
+        let pgn = write_pgn(&game, &[]);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 3 40\"]\n"));
+        assert!(pgn.contains("[Termination \"adjudication\"]"));
+        assert!(pgn.ends_with("40... Kd7 41. Ra7+ {Draw by adjudication} 1/2-1/2\n\n"));
+    }
+
+    #[test]
+    fn test_write_pgn_wraps_lines() {
+        let mut moves = Vec::new();
+        for _ in 0..10 {
+            moves.extend(["g1f3", "g8f6", "f3g1", "f6g8"]);
+        }
+        let game = record(None, &moves[..38], Outcome::Draw(DrawReason::Agreement));
+        let pgn = write_pgn(&game, &[]);
+        assert!(pgn.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert_eq!(parse_pgn(&pgn).unwrap()[0].moves, game.moves);
+    }
+
+    #[test]
+    fn test_parse_pgn() {
+        let text = r#"
+[Event "Test"]
+[White "Someone"]
+
+1. e4 {best by test} e5 2.Nf3 (2. f4 exf4) Nc6! $1 ; the main line
+3. Bb5 a6 1/2-1/2
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 b - - 0 1"]
+
+1... Kd7 *
+"#;
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("Someone"));
+        assert_eq!(games[0].moves.len(), 6);
+        assert_eq!(
+            games[0].final_state().to_fen(),
+            "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4"
+        );
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(games[1].final_state().to_fen(), "8/3k4/8/8/8/8/8/R3K3 w - - 1 2");
+    }
+
+    #[test]
+    fn test_parse_pgn_illegal_move() {
+        assert_eq!(parse_pgn("1. e4 e4").unwrap_err(), "illegal move: e4");
+    }
+
+    #[test]
+    fn test_pgn_date() {
+        let date = pgn_date();
+        assert_eq!(date.len(), 10);
+        assert!(date.as_str() > "2020.01.01");
+    }
+}
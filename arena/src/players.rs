@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ruschess_core::*;
+use ruschess_uci::{Go, UciPlayer};
+
+/// How long engines think per move when given no limits and no clock.
+const DEFAULT_MOVETIME_MS: u64 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerKind {
+    Random,
+    Greedy,
+    Cautious,
+    /// The built-in engine.
+    Engine { limits: SearchLimits, skill: Skill },
+    /// An external engine speaking UCI.
+    Uci {
+        command: PathBuf,
+        options: Vec<(String, String)>,
+        go: Go,
+    },
+}
+
+/// A player taking part in a match, as given on the command line: a kind,
+/// followed by a colon and comma separated `key=value` settings, e.g.
+/// `engine:depth=6,skill=10`, `uci:/usr/bin/stockfish,movetime=200,option.Hash=64`
+/// or `random:name=Monkey`. The settings are `name` for all players, `depth`,
+/// `nodes` and `movetime` in milliseconds for engines, `skill` and `elo` for
+/// the built-in engine, and `option.<name>` for UCI engines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerSpec {
+    pub name: String,
+    pub kind: PlayerKind,
+}
+
+impl PlayerSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (kind, settings) = spec.split_once(':').unwrap_or((spec, ""));
+        let mut settings = settings.split(',').filter(|setting| !setting.is_empty());
+        let mut kind = match kind {
+            "random" => PlayerKind::Random,
+            "greedy" => PlayerKind::Greedy,
+            "cautious" => PlayerKind::Cautious,
+            "engine" => PlayerKind::Engine {
+                limits: SearchLimits::default(),
+                skill: Skill::full(),
+            },
+            "uci" => PlayerKind::Uci {
+                command: settings.next().ok_or("uci players need an engine command")?.into(),
+                options: Vec::new(),
+                go: Go::default(),
+            },
+            _ => return Err(format!("unknown player: {}", kind)),
+        };
+        let mut name = match &kind {
+            PlayerKind::Uci { command, .. } => command
+                .file_stem()
+                .map_or_else(|| "uci".to_string(), |stem| stem.to_string_lossy().into_owned()),
+            _ => spec.split(':').next().unwrap_or(spec).to_string(),
+        };
+
+        for setting in settings {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value: {}", setting))?;
+            let invalid = || format!("invalid value for {}: {}", key, value);
+            let number = || value.parse::<u64>().map_err(|_| invalid());
+            match (&mut kind, key) {
+                (_, "name") => name = value.to_string(),
+                (PlayerKind::Engine { limits, .. }, "depth") => limits.depth = Some(number()? as usize),
+                (PlayerKind::Engine { limits, .. }, "nodes") => limits.nodes = Some(number()?),
+                (PlayerKind::Engine { limits, .. }, "movetime") => {
+                    limits.time = Some(Duration::from_millis(number()?))
+                }
+                (PlayerKind::Engine { skill, .. }, "skill") => *skill = Skill::new(number()?.min(255) as u8),
+                (PlayerKind::Engine { skill, .. }, "elo") => *skill = Skill::from_elo(number()?.min(u32::MAX as u64) as u32),
+                (PlayerKind::Uci { go, .. }, "depth") => go.depth = Some(number()? as usize),
+                (PlayerKind::Uci { go, .. }, "nodes") => go.nodes = Some(number()?),
+                (PlayerKind::Uci { go, .. }, "movetime") => go.movetime = Some(Duration::from_millis(number()?)),
+                (PlayerKind::Uci { options, .. }, key) if key.starts_with("option.") => {
+                    options.push((key["option.".len()..].to_string(), value.to_string()))
+                }
+                _ => return Err(format!("unknown setting for {}: {}", name, key)),
+            }
+        }
+        Ok(Self { name, kind })
+    }
+
+    /// Creates the player, seeding its random choices with `seed`. Engines
+    /// without any limits think for `DEFAULT_MOVETIME_MS` per move, unless
+    /// the game is `clocked`, in which case they budget their time from it.
+    pub fn build(&self, seed: u64, clocked: bool) -> Box<dyn Player + Send> {
+        let default_time = (!clocked).then(|| Duration::from_millis(DEFAULT_MOVETIME_MS));
+        match &self.kind {
+            PlayerKind::Random => Box::new(RandomPlayer::new(seed)),
+            PlayerKind::Greedy => Box::new(GreedyPlayer::new(seed)),
+            PlayerKind::Cautious => Box::new(CautiousPlayer::new(seed)),
+            PlayerKind::Engine { limits, skill } => {
+                let mut limits = *limits;
+                if limits == SearchLimits::infinite() {
+                    limits.time = default_time;
+                }
+                Box::new(EnginePlayer::new(limits).with_skill(*skill).with_seed(seed))
+            }
+            PlayerKind::Uci { command, options, go } => {
+                let mut go = go.clone();
+                if go.depth.is_none() && go.nodes.is_none() && go.movetime.is_none() {
+                    go.movetime = default_time;
+                }
+                let mut player = UciPlayer::new(command.clone()).with_go(go);
+                for (name, value) in options {
+                    player = player.with_option(name, value);
+                }
+                Box::new(player)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn test_parse_bots() {
+        assert_eq!(PlayerSpec::parse("random").unwrap().kind, PlayerKind::Random);
+        let greedy = PlayerSpec::parse("greedy:name=Grabby").unwrap();
+        assert_eq!(greedy.name, "Grabby");
+        assert_eq!(greedy.kind, PlayerKind::Greedy);
+        assert_eq!(PlayerSpec::parse("cautious").unwrap().name, "cautious");
+    }
+
+    #[test]
+    fn test_parse_engine() {
+        let spec = PlayerSpec::parse("engine:depth=6,nodes=1000,skill=5").unwrap();
+        let PlayerKind::Engine { limits, skill } = spec.kind else {
+            panic!("not an engine: {:?}", spec.kind);
+        };
+        assert_eq!(limits.depth, Some(6));
+        assert_eq!(limits.nodes, Some(1000));
+        assert_eq!(limits.time, None);
+        assert_eq!(skill, Skill::new(5));
+
+        let spec = PlayerSpec::parse("engine:elo=1700").unwrap();
+        let PlayerKind::Engine { limits, skill } = spec.kind else {
+            panic!("not an engine: {:?}", spec.kind);
+        };
+        assert_eq!(limits, SearchLimits::infinite());
+        assert_eq!(skill, Skill::from_elo(1700));
+    }
+
+    #[test]
+    fn test_parse_uci() {
+        let spec = PlayerSpec::parse("uci:/opt/engines/fish,depth=4,option.Hash=64,option.Threads=2").unwrap();
+        assert_eq!(spec.name, "fish");
+        let PlayerKind::Uci { command, options, go } = spec.kind else {
+            panic!("not a UCI engine: {:?}", spec.kind);
+        };
+        assert_eq!(command, PathBuf::from("/opt/engines/fish"));
+        assert_eq!(options, vec![("Hash".into(), "64".into()), ("Threads".into(), "2".into())]);
+        assert_eq!(go.depth, Some(4));
+        assert_eq!(go.movetime, None);
+    }
+
+    #[test_case("wizard", "unknown player: wizard" ; "unknown kind")]
+    #[test_case("uci", "uci players need an engine command" ; "uci without command")]
+    #[test_case("engine:depth", "expected key=value: depth" ; "no value")]
+    #[test_case("engine:depth=deep", "invalid value for depth: deep" ; "bad number")]
+    #[test_case("random:depth=3", "unknown setting for random: depth" ; "setting for another kind")]
+    fn test_parse_invalid(spec: &str, error: &str) {
+        assert_eq!(PlayerSpec::parse(spec).unwrap_err(), error);
+    }
+
+    #[test]
+    fn test_build() {
+        let state = GameState::default();
+        for spec in ["random", "greedy", "cautious", "engine:depth=1"] {
+            let mut player = PlayerSpec::parse(spec).unwrap().build(1, false);
+            let m = player.get_move(&state).played_move().unwrap();
+            assert!(state.legal_moves().contains(&m));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+/// The z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.959964;
+
+/// The results of a match, from the first player's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tally {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Tally {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+    /// The points scored per game, from 0 to 1, or `None` before the first game.
+    pub fn score(&self) -> Option<f64> {
+        let games = self.games();
+        (games > 0).then(|| (self.wins as f64 + self.draws as f64 / 2.0) / games as f64)
+    }
+    /// The variance of the points scored in a single game.
+    fn variance(&self) -> Option<f64> {
+        let score = self.score()?;
+        let games = self.games() as f64;
+        let deviations = self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2);
+        Some(deviations / games)
+    }
+
+    /// The Elo difference the score suggests, with its 95% confidence interval.
+    pub fn elo(&self) -> Option<EloEstimate> {
+        let score = self.score()?;
+        let variance = self.variance()?;
+        // results that never vary, such as all wins, give no interval at all
+        let error = (variance > 0.0).then(|| {
+            let error = Z_95 * (variance / self.games() as f64).sqrt();
+            (elo_from_score(score + error) - elo_from_score(score - error)) / 2.0
+        });
+        Some(EloEstimate {
+            elo: elo_from_score(score),
+            error,
+        })
+    }
+}
+
+/// An Elo difference, give or take `error`, which is `None` while the
+/// results are all the same.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub elo: f64,
+    pub error: Option<f64>,
+}
+
+impl std::fmt::Display for EloEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.error {
+            Some(error) => write!(f, "{:.1} +/- {:.1}", self.elo, error),
+            None => write!(f, "{:.1} (no error bar yet)", self.elo),
+        }
+    }
+}
+
+/// The Elo difference at which the stronger side scores `score` per game
+/// on average. Infinite for a score of 0 or 1.
+pub fn elo_from_score(score: f64) -> f64 {
+    let score = score.clamp(0.0, 1.0);
+    // written this way round to give 0 rather than -0 for even scores
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+/// The score per game that an Elo difference of `elo` suggests.
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// A sequential probability ratio test, deciding between the hypotheses
+/// that the first player is `elo0` stronger (H0) or `elo1` stronger (H1),
+/// with error rates `alpha` and `beta`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtVerdict {
+    AcceptH0,
+    AcceptH1,
+}
+
+impl Sprt {
+    /// The log-likelihood ratio of H1 over H0, using the normal
+    /// approximation of the score distribution.
+    pub fn llr(&self, tally: &Tally) -> f64 {
+        let (Some(score), Some(variance)) = (tally.score(), tally.variance()) else {
+            return 0.0;
+        };
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let score0 = score_from_elo(self.elo0);
+        let score1 = score_from_elo(self.elo1);
+        let variance = variance / tally.games() as f64;
+        (score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance)
+    }
+    /// The ratios at which H0 and H1 are accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+    pub fn verdict(&self, tally: &Tally) -> Option<SprtVerdict> {
+        let llr = self.llr(tally);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(SprtVerdict::AcceptH1)
+        } else if llr <= lower {
+            Some(SprtVerdict::AcceptH0)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn tally(wins: u32, draws: u32, losses: u32) -> Tally {
+        Tally { wins, draws, losses }
+    }
+
+    #[test_case(0.5, 0.0)]
+    #[test_case(0.75, 190.8)]
+    #[test_case(0.25, -190.8)]
+    fn test_elo_from_score(score: f64, elo: f64) {
+        assert!((elo_from_score(score) - elo).abs() < 0.1);
+        assert!((score_from_elo(elo) - score).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_tally_elo() {
+        assert_eq!(tally(0, 0, 0).elo(), None);
+        let even = tally(30, 40, 30).elo().unwrap();
+        assert_eq!(even.elo, 0.0);
+        let error = even.error.unwrap();
+        assert!(error > 30.0 && error < 60.0, "{}", even);
+
+        let more_games = tally(300, 400, 300).elo().unwrap();
+        assert!(more_games.error.unwrap() < error / 3.0);
+        assert!(tally(60, 20, 20).elo().unwrap().elo > 100.0);
+    }
+
+    #[test_case(tally(5, 0, 0), f64::INFINITY, "inf (no error bar yet)"; "all wins")]
+    #[test_case(tally(0, 0, 5), f64::NEG_INFINITY, "-inf (no error bar yet)"; "all losses")]
+    #[test_case(tally(0, 5, 0), 0.0, "0.0 (no error bar yet)"; "all draws")]
+    fn test_tally_elo_without_variance(tally: Tally, elo: f64, shown: &str) {
+        let estimate = tally.elo().unwrap();
+        assert_eq!(estimate.elo, elo);
+        assert_eq!(estimate.error, None);
+        assert_eq!(estimate.to_string(), shown);
+    }
+
+    #[test]
+    fn test_sprt_bounds() {
+        let (lower, upper) = Sprt::default().bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_sprt_verdict() {
+        let sprt = Sprt::default();
+        assert_eq!(sprt.llr(&tally(0, 0, 0)), 0.0);
+        assert_eq!(sprt.llr(&tally(0, 10, 0)), 0.0);
+        assert_eq!(sprt.verdict(&tally(10, 10, 10)), None);
+        assert_eq!(sprt.verdict(&tally(3000, 4000, 2000)), Some(SprtVerdict::AcceptH1));
+        assert_eq!(sprt.verdict(&tally(2000, 4000, 3000)), Some(SprtVerdict::AcceptH0));
+        assert!(sprt.llr(&tally(110, 100, 90)) > sprt.llr(&tally(100, 100, 100)));
+    }
+}
@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use ruschess_arena::parse_pgn;
+
+fn arena(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ruschess-arena")).args(args).output().unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_match() {
+    let pgn = env::temp_dir().join(format!("ruschess-arena-{}.pgn", std::process::id()));
+    let (success, stdout, stderr) = arena(&[
+        "--player1",
+        "cautious:name=Careful",
+        "--player2",
+        "engine:depth=1",
+        "--games",
+        "4",
+        "--concurrency",
+        "2",
+        "--max-moves",
+        "20",
+        "--resign-adjudication",
+        "plies=4,score=600",
+        "--pgn",
+        pgn.to_str().unwrap(),
+    ]);
+    assert!(success, "{}", stderr);
+    assert_eq!(stdout.lines().filter(|line| line.starts_with("Finished game ")).count(), 4);
+    let last_score = stdout.lines().rfind(|line| line.starts_with("Score of Careful vs engine: "));
+    assert!(last_score.unwrap().ends_with("] 4"), "{}", stdout);
+
+    let games = parse_pgn(&fs::read_to_string(&pgn).unwrap()).unwrap();
+    fs::remove_file(&pgn).unwrap();
+    assert_eq!(games.len(), 4);
+    let mut rounds: Vec<_> = games.iter().map(|game| game.tag("Round").unwrap().to_string()).collect();
+    rounds.sort();
+    assert_eq!(rounds, ["1", "2", "3", "4"]);
+    for game in &games {
+        assert!(game.moves.len() <= 40);
+        assert!(["Careful", "engine"].contains(&game.tag("White").unwrap()));
+    }
+}
+
+#[test]
+fn test_sprt_report() {
+    let (success, stdout, stderr) = arena(&[
+        "--player1",
+        "random",
+        "--player2",
+        "random",
+        "--games",
+        "2",
+        "--max-moves",
+        "10",
+        "--sprt",
+        "elo0=0,elo1=10",
+    ]);
+    assert!(success, "{}", stderr);
+    assert!(stdout.lines().any(|line| line.starts_with("SPRT: llr ")), "{}", stdout);
+}
+
+#[test]
+fn test_invalid_arguments() {
+    let (success, _, stderr) = arena(&["--player1", "random"]);
+    assert!(!success);
+    assert!(stderr.starts_with("both --player1 and --player2 are needed"), "{}", stderr);
+
+    let (success, _, stderr) = arena(&["--player1", "random", "--player2", "random", "--tc", "soon"]);
+    assert!(!success);
+    assert!(stderr.starts_with("invalid time control: soon"), "{}", stderr);
+}
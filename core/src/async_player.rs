@@ -0,0 +1,296 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::future::{AbortHandle, Abortable, BoxFuture, FutureExt};
+
+use crate::{
+    moves::Move,
+    piece::PieceColor,
+    player::{Outcome, Player, PlayerAction},
+    search::StopSignal,
+    state::GameState,
+    time_manager::TimeControl,
+};
+
+/// A `Player` whose moves take a while to arrive, such as a human at a GUI,
+/// a network opponent or an engine in another process. Instead of blocking,
+/// `get_move` returns a future, which works with any executor. Dropping the
+/// future abandons the move; see `cancellable` to do that from elsewhere.
+pub trait AsyncPlayer: Send {
+    fn get_move<'a>(&'a mut self, state: &'a GameState) -> BoxFuture<'a, PlayerAction>;
+
+    fn on_game_start(&mut self, _color: PieceColor, _start: &GameState) {}
+    fn on_opponent_move(&mut self, _m: Move) {}
+    fn on_draw_offer(&mut self) {}
+    fn on_draw_rejected(&mut self) {}
+    fn on_clock(&mut self, _white: TimeControl, _black: TimeControl) {}
+    fn on_game_end(&mut self, _outcome: Outcome) {}
+}
+
+/// Cancels the future it was created with, see `cancellable`.
+#[derive(Debug, Clone)]
+pub struct CancelHandle(AbortHandle);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.abort();
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_aborted()
+    }
+}
+
+/// Wraps `future` so that it can be cancelled through the returned handle,
+/// e.g. when a flag falls or the game is aborted. The wrapped future
+/// resolves to `None` once cancelled.
+pub fn cancellable<F: Future>(future: F) -> (impl Future<Output = Option<F::Output>>, CancelHandle) {
+    let (handle, registration) = AbortHandle::new_pair();
+    let future = Abortable::new(future, registration).map(Result::ok);
+    (future, CancelHandle(handle))
+}
+
+/// Runs a blocking `Player` on a thread of its own, to use it as an
+/// `AsyncPlayer`. Notifications are queued for that thread, so they never
+/// wait for a move in progress. Cancelling a move stops the player through
+/// its `Player::stop_signal`; a player without one finishes the move in the
+/// background, and the next move waits for it. A player that panics resigns.
+pub struct ThreadedPlayer<P> {
+    jobs: mpsc::Sender<Job<P>>,
+    stop: Option<StopSignal>,
+}
+
+type Job<P> = Box<dyn FnOnce(&mut P) + Send>;
+
+impl<P: Player + Send + 'static> ThreadedPlayer<P> {
+    pub fn new(mut player: P) -> Self {
+        let stop = player.stop_signal();
+        let (jobs, receiver) = mpsc::channel::<Job<P>>();
+        thread::spawn(move || {
+            for job in receiver {
+                job(&mut player);
+            }
+        });
+        Self { jobs, stop }
+    }
+
+    fn queue(&self, job: impl FnOnce(&mut P) + Send + 'static) {
+        // the thread is only gone if the player panicked, and then there is nobody to tell
+        let _ = self.jobs.send(Box::new(job));
+    }
+}
+
+/// A move of a `ThreadedPlayer`, which is abandoned if dropped before it is done.
+struct PendingMove {
+    cancelled: Arc<AtomicBool>,
+    done: Arc<AtomicBool>,
+    stop: Option<StopSignal>,
+}
+
+impl Drop for PendingMove {
+    fn drop(&mut self) {
+        if !self.done.load(Ordering::SeqCst) {
+            self.cancelled.store(true, Ordering::SeqCst);
+            if let Some(stop) = &self.stop {
+                stop.stop();
+            }
+        }
+    }
+}
+
+impl<P: Player + Send + 'static> AsyncPlayer for ThreadedPlayer<P> {
+    fn get_move<'a>(&'a mut self, state: &'a GameState) -> BoxFuture<'a, PlayerAction> {
+        let (sender, receiver) = oneshot::channel();
+        let pending = PendingMove {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            done: Arc::new(AtomicBool::new(false)),
+            stop: self.stop.clone(),
+        };
+        let (cancelled, done, stop) = (Arc::clone(&pending.cancelled), Arc::clone(&pending.done), self.stop.clone());
+        let state = state.clone();
+        self.queue(move |player| {
+            // reset before checking, so that a cancel in between still stops the move
+            if let Some(stop) = &stop {
+                stop.reset();
+            }
+            if cancelled.load(Ordering::SeqCst) {
+                return;
+            }
+            let action = player.get_move(&state);
+            done.store(true, Ordering::SeqCst);
+            let _ = sender.send(action);
+        });
+        async move {
+            let action = receiver.await;
+            drop(pending);
+            // the answer only goes missing if the player panicked
+            action.unwrap_or(PlayerAction::Resign)
+        }
+        .boxed()
+    }
+    fn on_game_start(&mut self, color: PieceColor, start: &GameState) {
+        let start = start.clone();
+        self.queue(move |player| player.on_game_start(color, &start));
+    }
+    fn on_opponent_move(&mut self, m: Move) {
+        self.queue(move |player| player.on_opponent_move(m));
+    }
+    fn on_draw_offer(&mut self) {
+        self.queue(|player| player.on_draw_offer());
+    }
+    fn on_draw_rejected(&mut self) {
+        self.queue(|player| player.on_draw_rejected());
+    }
+    fn on_clock(&mut self, white: TimeControl, black: TimeControl) {
+        self.queue(move |player| player.on_clock(white, black));
+    }
+    fn on_game_end(&mut self, outcome: Outcome) {
+        self.queue(move |player| player.on_game_end(outcome));
+    }
+}
+
+/// Waits for the moves of an `AsyncPlayer`, to use it as a blocking `Player`.
+pub struct BlockingPlayer<P> {
+    player: P,
+}
+
+impl<P: AsyncPlayer> BlockingPlayer<P> {
+    pub fn new(player: P) -> Self {
+        Self { player }
+    }
+    pub fn into_inner(self) -> P {
+        self.player
+    }
+}
+
+impl<P: AsyncPlayer> Player for BlockingPlayer<P> {
+    fn get_move(&mut self, state: &GameState) -> PlayerAction {
+        block_on(self.player.get_move(state))
+    }
+    fn on_game_start(&mut self, color: PieceColor, start: &GameState) {
+        self.player.on_game_start(color, start);
+    }
+    fn on_opponent_move(&mut self, m: Move) {
+        self.player.on_opponent_move(m);
+    }
+    fn on_draw_offer(&mut self) {
+        self.player.on_draw_offer();
+    }
+    fn on_draw_rejected(&mut self) {
+        self.player.on_draw_rejected();
+    }
+    fn on_clock(&mut self, white: TimeControl, black: TimeControl) {
+        self.player.on_clock(white, black);
+    }
+    fn on_game_end(&mut self, outcome: Outcome) {
+        self.player.on_game_end(outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{player::DrawReason, test_player::Scripted};
+    use futures::future;
+    use std::time::Duration;
+
+    /// Plays the moves sent to it, like a human at a GUI would.
+    struct Remote {
+        moves: Vec<oneshot::Receiver<PlayerAction>>,
+    }
+
+    impl AsyncPlayer for Remote {
+        fn get_move<'a>(&'a mut self, _state: &'a GameState) -> BoxFuture<'a, PlayerAction> {
+            let receiver = self.moves.remove(0);
+            async move { receiver.await.unwrap_or(PlayerAction::Resign) }.boxed()
+        }
+    }
+
+    #[test]
+    fn test_threaded_player() {
+        let state = GameState::default();
+        let scripted = Scripted::new(&["first"]);
+        let notes = Arc::clone(&scripted.notes);
+        let mut player = ThreadedPlayer::new(scripted);
+        player.on_opponent_move(state.legal_moves()[1]);
+        let action = block_on(player.get_move(&state));
+        assert_eq!(action, PlayerAction::Move(state.legal_moves()[0]));
+        assert_eq!(notes.lock().unwrap().opponent_moves, [state.legal_moves()[1]]);
+    }
+
+    #[test]
+    fn test_threaded_player_cancel_stops_player() {
+        let state = GameState::default();
+        let mut player = ThreadedPlayer::new(Scripted::new(&["think", "first"]));
+        let (future, handle) = cancellable(player.get_move(&state));
+        let canceller = handle.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            canceller.cancel();
+        });
+        assert_eq!(block_on(future), None);
+        // neither notifications nor the next move wait for the abandoned one
+        player.on_opponent_move(state.legal_moves()[0]);
+        assert_eq!(block_on(player.get_move(&state)), PlayerAction::Move(state.legal_moves()[0]));
+    }
+
+    #[test]
+    fn test_threaded_player_resigns_after_panic() {
+        let state = GameState::default();
+        let mut player = ThreadedPlayer::new(Scripted::new(&["panic", "first"]));
+        assert_eq!(block_on(player.get_move(&state)), PlayerAction::Resign);
+        // a player that is gone neither blocks notifications nor moves again
+        player.on_draw_offer();
+        player.on_game_end(Outcome::Draw(DrawReason::Agreement));
+        assert_eq!(block_on(player.get_move(&state)), PlayerAction::Resign);
+    }
+
+    #[test]
+    fn test_blocking_player() {
+        let state = GameState::default();
+        let (sender, receiver) = oneshot::channel();
+        let mut player = BlockingPlayer::new(Remote { moves: vec![receiver] });
+        let m = state.legal_moves()[3];
+        thread::spawn(move || sender.send(PlayerAction::Move(m)).unwrap());
+        assert_eq!(player.get_move(&state), PlayerAction::Move(m));
+    }
+
+    #[test]
+    fn test_adapters_round_trip() {
+        let state = GameState::default();
+        let scripted = Scripted::new(&["first", "first"]);
+        let notes = Arc::clone(&scripted.notes);
+        let mut player = BlockingPlayer::new(ThreadedPlayer::new(scripted));
+        assert_eq!(player.get_move(&state), PlayerAction::Move(state.legal_moves()[0]));
+        player.on_opponent_move(state.legal_moves()[0]);
+        // the notification is handled before the next move
+        player.get_move(&state);
+        assert_eq!(notes.lock().unwrap().opponent_moves.len(), 1);
+    }
+
+    #[test]
+    fn test_cancellable() {
+        let (future, handle) = cancellable(future::pending::<PlayerAction>());
+        assert!(!handle.is_cancelled());
+        handle.cancel();
+        assert!(handle.is_cancelled());
+        assert_eq!(block_on(future), None);
+
+        let (future, _handle) = cancellable(future::ready(PlayerAction::Resign));
+        assert_eq!(block_on(future), Some(PlayerAction::Resign));
+    }
+
+    #[test]
+    fn test_cancel_waiting_move() {
+        let state = GameState::default();
+        let (_sender, receiver) = oneshot::channel();
+        let mut player = Remote { moves: vec![receiver] };
+        let (future, handle) = cancellable(player.get_move(&state));
+        let canceller = handle.clone();
+        thread::spawn(move || canceller.cancel());
+        assert_eq!(block_on(future), None);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    board::Board,
+    piece::{PieceColor, PieceKind},
+    position::Position,
+};
+
+pub const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (-2, -1), (-2, 1), (-1, -2), (-1, 2),
+    (1, -2), (1, 2), (2, -1), (2, 1),
+];
+pub const KING_OFFSETS: [(i8, i8); 8] = [
+    (-1, -1), (-1, 0), (-1, 1), (0, -1),
+    (0, 1), (1, -1), (1, 0), (1, 1),
+];
+pub const DIAGONAL_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+pub const ORTHOGONAL_DIRECTIONS: [(i8, i8); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// Walks from `target` in `direction` and returns the first occupied position, if any.
+pub fn first_piece_in_direction(
+    board: &Board,
+    target: Position,
+    direction: (i8, i8),
+) -> Option<Position> {
+    let mut current = target;
+    while let Some(next) = current.offset(direction.0, direction.1) {
+        if board.get(next).is_some() {
+            return Some(next);
+        }
+        current = next;
+    }
+    None
+}
+
+/// Returns the positions of all pieces of color `attacker` that attack `target`.
+pub fn attackers_to(board: &Board, target: Position, attacker: PieceColor) -> Vec<Position> {
+    let mut result = Vec::new();
+    let is_attacker = |position: Position, kinds: &[PieceKind]| {
+        board
+            .get(position)
+            .is_some_and(|piece| piece.color() == attacker && kinds.contains(&piece.kind()))
+    };
+
+    // a pawn attacks `target` if it sits diagonally behind it, seen from its own side
+    let pawn_rank_offset = match attacker {
+        PieceColor::White => -1,
+        PieceColor::Black => 1,
+    };
+    for file_offset in [-1, 1] {
+        if let Some(position) = target.offset(pawn_rank_offset, file_offset) {
+            if is_attacker(position, &[PieceKind::Pawn]) {
+                result.push(position);
+            }
+        }
+    }
+    for (rank_offset, file_offset) in KNIGHT_OFFSETS {
+        if let Some(position) = target.offset(rank_offset, file_offset) {
+            if is_attacker(position, &[PieceKind::Knight]) {
+                result.push(position);
+            }
+        }
+    }
+    for (rank_offset, file_offset) in KING_OFFSETS {
+        if let Some(position) = target.offset(rank_offset, file_offset) {
+            if is_attacker(position, &[PieceKind::King]) {
+                result.push(position);
+            }
+        }
+    }
+    for direction in DIAGONAL_DIRECTIONS {
+        if let Some(position) = first_piece_in_direction(board, target, direction) {
+            if is_attacker(position, &[PieceKind::Bishop, PieceKind::Queen]) {
+                result.push(position);
+            }
+        }
+    }
+    for direction in ORTHOGONAL_DIRECTIONS {
+        if let Some(position) = first_piece_in_direction(board, target, direction) {
+            if is_attacker(position, &[PieceKind::Rook, PieceKind::Queen]) {
+                result.push(position);
+            }
+        }
+    }
+    result
+}
+
+/// Returns whether any piece of color `attacker` attacks `target`.
+pub fn is_square_attacked(board: &Board, target: Position, attacker: PieceColor) -> bool {
+    !attackers_to(board, target, attacker).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::Piece;
+
+    #[test]
+    fn test_attackers_to_all_piece_kinds() {
+        let mut board = Board::empty();
+        let target = Position::try_from("d4").unwrap();
+        board.set(Position::try_from("c3").unwrap(), Some(piece!(Pawn, White)));
+        board.set(Position::try_from("b5").unwrap(), Some(piece!(Knight, White)));
+        board.set(Position::try_from("g7").unwrap(), Some(piece!(Bishop, White)));
+        board.set(Position::try_from("d1").unwrap(), Some(piece!(Rook, White)));
+        board.set(Position::try_from("h4").unwrap(), Some(piece!(Queen, White)));
+        board.set(Position::try_from("e5").unwrap(), Some(piece!(King, White)));
+
+        let attackers = attackers_to(&board, target, PieceColor::White);
+        assert_eq!(attackers.len(), 5);
+        // the bishop on g7 is blocked by the king on e5
+        assert!(!attackers.contains(&Position::try_from("g7").unwrap()));
+        assert!(attackers_to(&board, target, PieceColor::Black).is_empty());
+    }
+
+    #[test]
+    fn test_is_square_attacked_pawn_direction() {
+        let mut board = Board::empty();
+        board.set(Position::try_from("e4").unwrap(), Some(piece!(Pawn, White)));
+        board.set(Position::try_from("e6").unwrap(), Some(piece!(Pawn, Black)));
+
+        assert!(is_square_attacked(&board, Position::try_from("d5").unwrap(), PieceColor::White));
+        assert!(!is_square_attacked(&board, Position::try_from("d3").unwrap(), PieceColor::White));
+        assert!(is_square_attacked(&board, Position::try_from("f5").unwrap(), PieceColor::Black));
+        assert!(!is_square_attacked(&board, Position::try_from("f7").unwrap(), PieceColor::Black));
+    }
+
+    #[test]
+    fn test_is_square_attacked_slider_blocked() {
+        let mut board = Board::empty();
+        board.set(Position::try_from("a1").unwrap(), Some(piece!(Rook, Black)));
+        board.set(Position::try_from("c1").unwrap(), Some(piece!(Knight, White)));
+
+        assert!(is_square_attacked(&board, Position::try_from("b1").unwrap(), PieceColor::Black));
+        assert!(is_square_attacked(&board, Position::try_from("c1").unwrap(), PieceColor::Black));
+        assert!(!is_square_attacked(&board, Position::try_from("d1").unwrap(), PieceColor::Black));
+    }
+}
@@ -36,8 +36,8 @@ impl Board {
         }
 
         let mut result = [None; 8];
-        for rank in 0..8 {
-            result[rank] = self.squares[rank * 8 + file];
+        for (rank, square) in result.iter_mut().enumerate() {
+            *square = self.squares[rank * 8 + file];
         }
         result
     }
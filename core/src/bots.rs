@@ -0,0 +1,222 @@
+use crate::{
+    attacks::{attackers_to, is_square_attacked},
+    moves::Move,
+    piece::{PieceColor, PieceKind},
+    player::{Player, PlayerAction},
+    position::Position,
+    rng::Rng,
+    see::SEE_VALUES,
+    state::GameState,
+};
+
+#[inline]
+fn value(kind: PieceKind) -> i32 {
+    SEE_VALUES[kind as usize]
+}
+
+/// The material `m` wins at once: the piece it captures, and what a pawn
+/// gains by promoting.
+pub fn material_gain(m: Move) -> i32 {
+    let captured = match m {
+        Move::Capture { captured, .. } | Move::PromotionCapture { captured, .. } => value(captured.kind()),
+        Move::EnPassant { .. } => value(PieceKind::Pawn),
+        _ => 0,
+    };
+    let promoted = m.promotion().map_or(0, |piece| value(piece.kind()) - value(PieceKind::Pawn));
+    captured + promoted
+}
+
+/// Returns the total value of the pieces of `color` that the opponent could
+/// take for free: pieces that are attacked and undefended, or attacked by a
+/// less valuable piece. Pins are not taken into account.
+pub fn hanging_material(state: &GameState, color: PieceColor) -> i32 {
+    let board = &state.board;
+    let mut total = 0;
+    for (index, square) in board.squares().iter().enumerate() {
+        let Some(piece) = square else {
+            continue;
+        };
+        if piece.color() != color || piece.kind() == PieceKind::King {
+            continue;
+        }
+        let position = Position::from(index);
+        let cheapest_attacker = attackers_to(board, position, color.opposite())
+            .into_iter()
+            .filter_map(|attacker| board.get(attacker))
+            .map(|attacker| value(attacker.kind()))
+            .min();
+        let Some(cheapest_attacker) = cheapest_attacker else {
+            continue;
+        };
+        if cheapest_attacker < value(piece.kind()) || !is_square_attacked(board, position, color) {
+            total += value(piece.kind());
+        }
+    }
+    total
+}
+
+/// The moves in `moves` with the highest `score`.
+fn best_moves(moves: &[Move], mut score: impl FnMut(Move) -> i32) -> Vec<Move> {
+    let scores: Vec<i32> = moves.iter().map(|m| score(*m)).collect();
+    let Some(best) = scores.iter().max() else {
+        return Vec::new();
+    };
+    moves.iter().zip(&scores).filter(|(_, score)| *score == best).map(|(m, _)| *m).collect()
+}
+
+/// Plays a random legal move.
+#[derive(Debug, Clone, Default)]
+pub struct RandomPlayer {
+    rng: Rng,
+}
+
+impl RandomPlayer {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::new(seed) }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn get_move(&mut self, state: &GameState) -> PlayerAction {
+        match self.rng.choose(&state.legal_moves()) {
+            Some(m) => PlayerAction::Move(*m),
+            None => PlayerAction::Resign,
+        }
+    }
+}
+
+/// Plays the move that wins the most material at once, picking randomly
+/// between equally good moves, and without looking at the reply.
+#[derive(Debug, Clone, Default)]
+pub struct GreedyPlayer {
+    rng: Rng,
+}
+
+impl GreedyPlayer {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::new(seed) }
+    }
+}
+
+impl Player for GreedyPlayer {
+    fn get_move(&mut self, state: &GameState) -> PlayerAction {
+        let moves = best_moves(&state.legal_moves(), material_gain);
+        match self.rng.choose(&moves) {
+            Some(m) => PlayerAction::Move(*m),
+            None => PlayerAction::Resign,
+        }
+    }
+}
+
+/// Mates in one when it can. Otherwise plays greedily, but only moves that
+/// leave none of its pieces hanging, or as few as it can.
+#[derive(Debug, Clone, Default)]
+pub struct CautiousPlayer {
+    rng: Rng,
+}
+
+impl CautiousPlayer {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::new(seed) }
+    }
+}
+
+impl Player for CautiousPlayer {
+    fn get_move(&mut self, state: &GameState) -> PlayerAction {
+        let color = state.turn;
+        let moves = state.legal_moves();
+        let mut after = Vec::with_capacity(moves.len());
+        for m in &moves {
+            let mut next = state.clone();
+            next.apply_move(*m);
+            if next.is_checkmate() {
+                return PlayerAction::Move(*m);
+            }
+            after.push(next);
+        }
+
+        let mut index = 0;
+        let moves = best_moves(&moves, |m| {
+            let hanging = hanging_material(&after[index], color);
+            index += 1;
+            // material won matters less than not losing more in return
+            material_gain(m) - 2 * hanging
+        });
+        match self.rng.choose(&moves) {
+            Some(m) => PlayerAction::Move(*m),
+            None => PlayerAction::Resign,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{parse_uci, to_uci};
+    use test_case::test_case;
+
+    fn played(player: &mut dyn Player, state: &GameState) -> String {
+        let m = player.get_move(state).played_move().unwrap();
+        assert!(state.legal_moves().contains(&m));
+        to_uci(state, m)
+    }
+
+    #[test]
+    fn test_random_player_is_seedable() {
+        let state = GameState::default();
+        let moves: Vec<_> = (0..5).map(|_| played(&mut RandomPlayer::new(3), &state)).collect();
+        assert!(moves.iter().all(|m| *m == moves[0]));
+
+        let mut player = RandomPlayer::new(3);
+        let moves: Vec<_> = (0..20).map(|_| played(&mut player, &state)).collect();
+        assert!(moves.iter().any(|m| *m != moves[0]));
+    }
+
+    #[test]
+    fn test_bots_resign_without_moves() {
+        let state = GameState::from_fen("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(RandomPlayer::new(0).get_move(&state), PlayerAction::Resign);
+        assert_eq!(GreedyPlayer::new(0).get_move(&state), PlayerAction::Resign);
+        assert_eq!(CautiousPlayer::new(0).get_move(&state), PlayerAction::Resign);
+    }
+
+    #[test_case("e2e4", 0 ; "quiet")]
+    #[test_case("d4e5", 100 ; "pawn capture")]
+    #[test_case("c7c8q", 800 ; "promotion")]
+    #[test_case("c7b8q", 1120 ; "promotion capture")]
+    fn test_material_gain(uci: &str, gain: i32) {
+        let state = GameState::from_fen("1n2k3/2P5/8/4p3/3P4/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(material_gain(parse_uci(&state, uci).unwrap()), gain);
+    }
+
+    #[test_case("4k3/8/8/3q4/8/8/8/4K3 b - - 0 1", 0 ; "nothing attacked")]
+    #[test_case("4k3/8/8/3q4/8/8/8/3RK3 b - - 0 1", 900 ; "attacked by a cheaper piece")]
+    #[test_case("4k3/8/2p5/3r4/8/8/8/3QK3 b - - 0 1", 0 ; "attacked and defended")]
+    #[test_case("4k3/8/8/3n4/8/8/8/3QK3 b - - 0 1", 320 ; "attacked and undefended")]
+    fn test_hanging_material(fen: &str, hanging: i32) {
+        let state = GameState::from_fen(fen).unwrap();
+        assert_eq!(hanging_material(&state, PieceColor::Black), hanging);
+    }
+
+    #[test]
+    fn test_greedy_player_takes_the_queen() {
+        let state = GameState::from_fen("4k3/8/8/1q1r4/2P5/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(played(&mut GreedyPlayer::new(0), &state), "c4b5");
+    }
+
+    #[test]
+    fn test_cautious_player_mates_in_one() {
+        let state = GameState::from_fen("6k1/5ppp/8/7q/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        assert_eq!(played(&mut CautiousPlayer::new(0), &state), "a1a8");
+    }
+
+    #[test]
+    fn test_cautious_player_avoids_hanging_pieces() {
+        // taking the pawn loses the queen to the bishop
+        let state = GameState::from_fen("4k3/8/8/1b6/8/3p4/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(played(&mut GreedyPlayer::new(0), &state), "d1d3");
+        for seed in 0..10 {
+            assert_ne!(played(&mut CautiousPlayer::new(seed), &state), "d1d3");
+        }
+    }
+}
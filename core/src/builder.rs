@@ -0,0 +1,440 @@
+use crate::{
+    board::Board,
+    piece::{Piece, PieceColor, PieceKind},
+    position::Position,
+    state::{CastleSide, CastlingRights, GameState},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionBuilderError {
+    InvalidSquare(String),
+    InvalidCastle(char),
+    /// A color does not have exactly one king; holds the number found.
+    KingCount(PieceColor, usize),
+    PawnOnBackRank(Position),
+    /// The player who just moved has left their king in check.
+    OpponentInCheck,
+    /// The castling right has no king and rook on their home squares to back it.
+    InvalidCastlingRight(PieceColor, CastleSide),
+    InvalidEnPassant(Position),
+    InvalidFullmoveNumber,
+}
+
+/// Builds a `GameState` square by square, validating the result in `build`.
+#[derive(Debug, Clone)]
+pub struct PositionBuilder {
+    board: Board,
+    turn: PieceColor,
+    castling_rights: CastlingRights,
+    castling: Option<String>,
+    en_passant: Option<Position>,
+    halfmove_clock: usize,
+    fullmove_number: usize,
+    error: Option<PositionBuilderError>,
+}
+
+impl Default for PositionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PositionBuilder {
+    /// Starts from an empty board with White to move and no castling rights.
+    pub fn new() -> Self {
+        Self {
+            board: Board::empty(),
+            turn: PieceColor::White,
+            castling_rights: CastlingRights::none(),
+            castling: None,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            error: None,
+        }
+    }
+    /// Starts from an existing position.
+    pub fn from_state(state: &GameState) -> Self {
+        Self {
+            board: state.board,
+            turn: state.turn,
+            castling_rights: state.castling_rights,
+            castling: None,
+            en_passant: state.en_passant,
+            halfmove_clock: state.halfmove_clock,
+            fullmove_number: state.fullmove_number,
+            error: None,
+        }
+    }
+
+    fn parse_square(&mut self, square: &str) -> Option<Position> {
+        let position = Position::try_from(square).ok();
+        if position.is_none() && self.error.is_none() {
+            self.error = Some(PositionBuilderError::InvalidSquare(square.to_string()));
+        }
+        position
+    }
+
+    /// Places `piece` on the square with the given name, e.g. `"e4"`,
+    /// replacing whatever stood there.
+    pub fn place(mut self, square: &str, piece: Piece) -> Self {
+        if let Some(position) = self.parse_square(square) {
+            self.board.set(position, Some(piece));
+        }
+        self
+    }
+    pub fn remove(mut self, square: &str) -> Self {
+        if let Some(position) = self.parse_square(square) {
+            self.board.set(position, None);
+        }
+        self
+    }
+    pub fn clear(mut self) -> Self {
+        self.board = Board::empty();
+        self
+    }
+    /// Removes every piece on the given zero-based rank.
+    pub fn clear_rank(mut self, rank: u8) -> Self {
+        for file in 0..8 {
+            self.board.set(Position::new(rank, file), None);
+        }
+        self
+    }
+    pub fn turn(mut self, turn: PieceColor) -> Self {
+        self.turn = turn;
+        self
+    }
+    /// Sets the castling rights by name, in any notation `GameState::from_fen`
+    /// understands: `"KQkq"`, `"-"`, or Shredder-FEN rook files like `"HAha"`.
+    /// Rights are resolved against the pieces on the board when building.
+    pub fn castling(mut self, rights: &str) -> Self {
+        self.castling_rights = CastlingRights::none();
+        self.castling = Some(rights.trim_start_matches('-').to_string());
+        self
+    }
+    pub fn en_passant(mut self, square: &str) -> Self {
+        self.en_passant = self.parse_square(square);
+        self
+    }
+    pub fn no_en_passant(mut self) -> Self {
+        self.en_passant = None;
+        self
+    }
+    pub fn halfmove_clock(mut self, halfmove_clock: usize) -> Self {
+        self.halfmove_clock = halfmove_clock;
+        self
+    }
+    pub fn fullmove_number(mut self, fullmove_number: usize) -> Self {
+        self.fullmove_number = fullmove_number;
+        self
+    }
+
+    /// Flips the board vertically and swaps the colors of all pieces, the side
+    /// to move and the castling rights, giving the same position from the
+    /// other player's point of view.
+    pub fn mirror(mut self) -> Self {
+        let mut board = Board::empty();
+        for (index, square) in self.board.squares().iter().enumerate() {
+            let position = Position::from(index);
+            let mirrored = Position::new(7 - position.rank(), position.file());
+            board.set(mirrored, square.map(|piece| Piece::new(piece.kind(), piece.color().opposite())));
+        }
+        self.board = board;
+        self.turn = self.turn.opposite();
+
+        let mut castling_rights = CastlingRights::none();
+        for color in [PieceColor::White, PieceColor::Black] {
+            for side in [CastleSide::KingSide, CastleSide::QueenSide] {
+                if let Some(file) = self.castling_rights.get(color, side) {
+                    castling_rights.set(color.opposite(), side, file);
+                }
+            }
+        }
+        self.castling_rights = castling_rights;
+        self.castling = self.castling.map(|rights| {
+            rights
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        });
+        self.en_passant = self
+            .en_passant
+            .map(|position| Position::new(7 - position.rank(), position.file()));
+        self
+    }
+
+    /// Mirrors the board from left to right, swapping the a- and h-files.
+    /// Castling rights follow their rooks to the other side of the board.
+    pub fn flip_horizontal(mut self) -> Self {
+        let mut board = Board::empty();
+        for (index, square) in self.board.squares().iter().enumerate() {
+            let position = Position::from(index);
+            board.set(Position::new(position.rank(), 7 - position.file()), *square);
+        }
+        self.board = board;
+
+        let mut castling_rights = CastlingRights::none();
+        for color in [PieceColor::White, PieceColor::Black] {
+            for (side, flipped) in [
+                (CastleSide::KingSide, CastleSide::QueenSide),
+                (CastleSide::QueenSide, CastleSide::KingSide),
+            ] {
+                if let Some(file) = self.castling_rights.get(color, side) {
+                    castling_rights.set(color, flipped, 7 - file);
+                }
+            }
+        }
+        self.castling_rights = castling_rights;
+        self.castling = self.castling.map(|rights| {
+            rights
+                .chars()
+                .map(|c| match c {
+                    'K' => 'Q',
+                    'Q' => 'K',
+                    'k' => 'q',
+                    'q' => 'k',
+                    'A'..='H' => (b'H' - (c as u8 - b'A')) as char,
+                    'a'..='h' => (b'h' - (c as u8 - b'a')) as char,
+                    _ => c,
+                })
+                .collect()
+        });
+        self.en_passant = self
+            .en_passant
+            .map(|position| Position::new(position.rank(), 7 - position.file()));
+        self
+    }
+
+    fn validate_castling_rights(state: &GameState) -> Result<(), PositionBuilderError> {
+        for color in [PieceColor::White, PieceColor::Black] {
+            let rank = color.back_rank();
+            let king = state.board.find_king(color).filter(|king| king.rank() == rank);
+            for side in [CastleSide::KingSide, CastleSide::QueenSide] {
+                let Some(rook_file) = state.castling_rights.get(color, side) else {
+                    continue;
+                };
+                let rook = state.board.get(Position::new(rank, rook_file));
+                let valid = match king {
+                    Some(king) => {
+                        rook == Some(Piece::new(PieceKind::Rook, color))
+                            && match side {
+                                CastleSide::KingSide => rook_file > king.file(),
+                                CastleSide::QueenSide => rook_file < king.file(),
+                            }
+                    }
+                    None => false,
+                };
+                if !valid {
+                    return Err(PositionBuilderError::InvalidCastlingRight(color, side));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_en_passant(state: &GameState) -> Result<(), PositionBuilderError> {
+        let Some(en_passant) = state.en_passant else {
+            return Ok(());
+        };
+        // the pawn that just double-pushed stands one rank past the en passant square
+        let (rank, pawn_rank_offset) = match state.turn {
+            PieceColor::White => (5, -1),
+            PieceColor::Black => (2, 1),
+        };
+        let pawn = en_passant
+            .offset(pawn_rank_offset, 0)
+            .and_then(|position| state.board.get(position));
+        let valid = en_passant.rank() == rank
+            && state.board.get(en_passant).is_none()
+            && pawn == Some(Piece::new(PieceKind::Pawn, state.turn.opposite()));
+        if valid {
+            Ok(())
+        } else {
+            Err(PositionBuilderError::InvalidEnPassant(en_passant))
+        }
+    }
+
+    /// Builds the position, checking that it could occur in a game: one king
+    /// per side, no pawns on the back ranks, the side not to move is not in
+    /// check, and castling rights and the en passant square are consistent
+    /// with the board.
+    pub fn build(self) -> Result<GameState, PositionBuilderError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.fullmove_number == 0 {
+            return Err(PositionBuilderError::InvalidFullmoveNumber);
+        }
+
+        let mut state = GameState::new(
+            self.board,
+            self.turn,
+            self.castling_rights,
+            self.en_passant,
+            self.halfmove_clock,
+            self.fullmove_number,
+        );
+        if let Some(castling) = &self.castling {
+            for c in castling.chars() {
+                state
+                    .parse_fen_castle(c)
+                    .map_err(|_| PositionBuilderError::InvalidCastle(c))?;
+            }
+        }
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            let kings = state
+                .board
+                .squares()
+                .iter()
+                .filter(|square| **square == Some(Piece::new(PieceKind::King, color)))
+                .count();
+            if kings != 1 {
+                return Err(PositionBuilderError::KingCount(color, kings));
+            }
+        }
+        for (index, square) in state.board.squares().iter().enumerate() {
+            let position = Position::from(index);
+            let on_back_rank = position.rank() == 0 || position.rank() == 7;
+            if on_back_rank && square.is_some_and(|piece| piece.kind() == PieceKind::Pawn) {
+                return Err(PositionBuilderError::PawnOnBackRank(position));
+            }
+        }
+        if state.is_king_attacked(state.turn.opposite()) {
+            return Err(PositionBuilderError::OpponentInCheck);
+        }
+        Self::validate_castling_rights(&state)?;
+        Self::validate_en_passant(&state)?;
+
+        // rights on anything but the standard king and rook files need Chess960 castling
+        for color in [PieceColor::White, PieceColor::Black] {
+            let king_file = state.board.find_king(color).map(|king| king.file());
+            for side in [CastleSide::KingSide, CastleSide::QueenSide] {
+                if let Some(rook_file) = state.castling_rights.get(color, side) {
+                    if king_file != Some(4) || (rook_file != 0 && rook_file != 7) {
+                        state.chess960 = true;
+                    }
+                }
+            }
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kings() -> PositionBuilder {
+        PositionBuilder::new()
+            .place("e1", piece!(King, White))
+            .place("e8", piece!(King, Black))
+    }
+
+    #[test]
+    fn test_position_builder_matches_fen() {
+        let state = kings()
+            .place("a1", piece!(Rook, White))
+            .place("h8", piece!(Rook, Black))
+            .place("d5", piece!(Pawn, Black))
+            .place("e5", piece!(Pawn, White))
+            .turn(PieceColor::White)
+            .castling("Qk")
+            .en_passant("d6")
+            .halfmove_clock(0)
+            .fullmove_number(30)
+            .build()
+            .unwrap();
+
+        assert_eq!(state, GameState::from_fen("4k2r/8/8/3pP3/8/8/8/R3K3 w Qk d6 0 30").unwrap());
+        assert!(!state.chess960);
+    }
+
+    #[test]
+    fn test_position_builder_from_state_round_trip() {
+        let state = GameState::default();
+        assert_eq!(PositionBuilder::from_state(&state).build().unwrap(), state);
+    }
+
+    #[test]
+    fn test_position_builder_remove_and_clear_rank() {
+        let state = PositionBuilder::from_state(&GameState::default())
+            .clear_rank(1)
+            .clear_rank(6)
+            .remove("d1")
+            .build()
+            .unwrap();
+        assert_eq!(state.to_fen(), "rnbqkbnr/8/8/8/8/8/8/RNB1KBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn test_position_builder_mirror() {
+        let state = GameState::from_fen("4k2r/8/8/3pP3/8/8/8/R3K3 w Qk d6 0 30").unwrap();
+        let mirrored = PositionBuilder::from_state(&state).mirror().build().unwrap();
+        assert_eq!(mirrored.to_fen(), "r3k3/8/8/8/3Pp3/8/8/4K2R b Kq d3 0 30");
+
+        let twice = PositionBuilder::from_state(&mirrored).mirror().build().unwrap();
+        assert_eq!(twice, state);
+    }
+
+    #[test]
+    fn test_position_builder_flip_horizontal() {
+        let state = GameState::from_fen("4k3/8/8/8/8/2N5/8/R3K3 w Q - 0 1").unwrap();
+        let flipped = PositionBuilder::from_state(&state).flip_horizontal().build().unwrap();
+
+        assert_eq!(flipped.to_fen(), "3k4/8/8/8/8/5N2/8/3K3R w K - 0 1");
+        assert!(flipped.chess960);
+        assert_eq!(flipped.castling_rights.get(PieceColor::White, CastleSide::KingSide), Some(7));
+    }
+
+    #[test]
+    fn test_position_builder_err_invalid_square() {
+        let result = kings().place("z9", piece!(Queen, White)).build();
+        assert_eq!(result, Err(PositionBuilderError::InvalidSquare("z9".to_string())));
+    }
+
+    #[test]
+    fn test_position_builder_err_king_count() {
+        let result = PositionBuilder::new().place("e1", piece!(King, White)).build();
+        assert_eq!(result, Err(PositionBuilderError::KingCount(PieceColor::Black, 0)));
+    }
+
+    #[test]
+    fn test_position_builder_err_pawn_on_back_rank() {
+        let result = kings().place("a8", piece!(Pawn, White)).build();
+        assert_eq!(result, Err(PositionBuilderError::PawnOnBackRank(Position::new(7, 0))));
+    }
+
+    #[test]
+    fn test_position_builder_err_opponent_in_check() {
+        let result = kings().place("e4", piece!(Rook, White)).turn(PieceColor::White).build();
+        assert_eq!(result, Err(PositionBuilderError::OpponentInCheck));
+    }
+
+    #[test]
+    fn test_position_builder_err_castling_without_rook() {
+        let result = kings().castling("K").build();
+        assert_eq!(
+            result,
+            Err(PositionBuilderError::InvalidCastlingRight(PieceColor::White, CastleSide::KingSide))
+        );
+    }
+
+    #[test]
+    fn test_position_builder_err_en_passant_without_pawn() {
+        let result = kings().en_passant("d6").build();
+        assert_eq!(result, Err(PositionBuilderError::InvalidEnPassant(Position::new(5, 3))));
+    }
+
+    #[test]
+    fn test_position_builder_err_invalid_castle() {
+        let result = kings().castling("X").build();
+        assert_eq!(result, Err(PositionBuilderError::InvalidCastle('X')));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    board::Board,
+    piece::{Piece, PieceColor, PieceKind},
+    position::Position,
+    state::{CastleSide, CastlingRights, GameState},
+};
+
+/// Knight placements over the five squares left after placing the bishops
+/// and the queen, indexed as in Scharnagl's numbering scheme.
+const KNIGHT_TABLE: [(usize, usize); 10] = [
+    (0, 1), (0, 2), (0, 3), (0, 4), (1, 2),
+    (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+];
+
+/// Returns the back rank (a-file to h-file) of the Chess960 start position
+/// with the given Scharnagl index, or `None` if the index is not in `0..960`.
+pub fn chess960_back_rank(index: u16) -> Option<[PieceKind; 8]> {
+    if index >= 960 {
+        return None;
+    }
+    let mut rank: [Option<PieceKind>; 8] = [None; 8];
+    let mut n = index as usize;
+
+    // light-squared bishop on b, d, f or h, dark-squared bishop on a, c, e or g
+    rank[n % 4 * 2 + 1] = Some(PieceKind::Bishop);
+    n /= 4;
+    rank[n % 4 * 2] = Some(PieceKind::Bishop);
+    n /= 4;
+
+    let place_on_nth_empty = |rank: &mut [Option<PieceKind>; 8], nth: usize, kind| {
+        let file = (0..8).filter(|file| rank[*file].is_none()).nth(nth).unwrap();
+        rank[file] = Some(kind);
+    };
+    place_on_nth_empty(&mut rank, n % 6, PieceKind::Queen);
+    n /= 6;
+
+    // placing the second knight shifts the empty squares by one
+    let (first, second) = KNIGHT_TABLE[n];
+    place_on_nth_empty(&mut rank, first, PieceKind::Knight);
+    place_on_nth_empty(&mut rank, second - 1, PieceKind::Knight);
+
+    // the king always ends up between the two rooks
+    for kind in [PieceKind::Rook, PieceKind::King, PieceKind::Rook] {
+        place_on_nth_empty(&mut rank, 0, kind);
+    }
+
+    Some(rank.map(Option::unwrap))
+}
+
+/// Builds the Chess960 start position with the given Scharnagl index, with
+/// full castling rights on both rooks. Index 518 is the standard start position.
+pub fn chess960_start_position(index: u16) -> Option<GameState> {
+    let back_rank = chess960_back_rank(index)?;
+    let mut board = Board::empty();
+    let mut castling_rights = CastlingRights::none();
+
+    for color in [PieceColor::White, PieceColor::Black] {
+        let rank = color.back_rank();
+        let pawn_rank = match color {
+            PieceColor::White => 1,
+            PieceColor::Black => 6,
+        };
+        let king_file = back_rank.iter().position(|kind| *kind == PieceKind::King).unwrap() as u8;
+
+        for (file, kind) in back_rank.iter().enumerate() {
+            let file = file as u8;
+            board.set(Position::new(rank, file), Some(Piece::new(*kind, color)));
+            board.set(Position::new(pawn_rank, file), Some(Piece::new(PieceKind::Pawn, color)));
+
+            if *kind == PieceKind::Rook {
+                let side = if file > king_file {
+                    CastleSide::KingSide
+                } else {
+                    CastleSide::QueenSide
+                };
+                castling_rights.set(color, side, file);
+            }
+        }
+    }
+
+    let mut state = GameState::new(board, PieceColor::White, castling_rights, None, 0, 1);
+    state.chess960 = true;
+    Some(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(0, "bbqnnrkr")]
+    #[test_case(518, "rnbqkbnr")]
+    #[test_case(959, "rkrnnqbb")]
+    #[test_case(100, "qbbnrnkr")]
+    fn test_chess960_back_rank(index: u16, expected: &str) {
+        let back_rank = chess960_back_rank(index).unwrap();
+        let letters: String = back_rank
+            .iter()
+            .map(|kind| match kind {
+                PieceKind::Pawn => 'p',
+                PieceKind::Knight => 'n',
+                PieceKind::Bishop => 'b',
+                PieceKind::Rook => 'r',
+                PieceKind::Queen => 'q',
+                PieceKind::King => 'k',
+            })
+            .collect();
+        assert_eq!(letters, expected);
+    }
+
+    #[test]
+    fn test_chess960_back_rank_out_of_range() {
+        assert!(chess960_back_rank(960).is_none());
+    }
+
+    #[test]
+    fn test_chess960_back_rank_all_valid() {
+        for index in 0..960 {
+            let back_rank = chess960_back_rank(index).unwrap();
+            let files_of = |kind| (0..8).filter(move |file| back_rank[*file] == kind);
+            let bishops: Vec<usize> = files_of(PieceKind::Bishop).collect();
+            let rooks: Vec<usize> = files_of(PieceKind::Rook).collect();
+            let king = files_of(PieceKind::King).next().unwrap();
+
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "index {}", index);
+            assert!(rooks[0] < king && king < rooks[1], "index {}", index);
+        }
+    }
+
+    #[test]
+    fn test_chess960_start_position_standard() {
+        let state = chess960_start_position(518).unwrap();
+        assert_eq!(state.board, GameState::default().board);
+        assert_eq!(state.castling_rights, CastlingRights::all());
+        assert!(state.chess960);
+    }
+
+    #[test]
+    fn test_chess960_start_position_fen() {
+        let state = chess960_start_position(0).unwrap();
+        assert_eq!(
+            state.to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert_eq!(
+            state.to_shredder_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"
+        );
+    }
+}
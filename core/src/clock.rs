@@ -0,0 +1,425 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{piece::PieceColor, time_manager::TimeControl};
+
+/// Where a `Clock` reads the time from.
+pub trait TimeSource: Send {
+    /// The time passed since some fixed point, such as the creation of the source.
+    fn now(&self) -> Duration;
+}
+
+/// The time of the system's monotonic clock.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemTimeSource(Instant);
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// A time source that only moves when told to, for tests. Clones share the
+/// same time, so one can be handed to a `Clock` and another kept to advance it.
+#[derive(Debug, Clone, Default)]
+pub struct MockTimeSource(Arc<Mutex<Duration>>);
+
+impl MockTimeSource {
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+}
+
+impl TimeSource for MockTimeSource {
+    fn now(&self) -> Duration {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// How the time a player spent on a move is charged to its clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing {
+    /// All of it is charged.
+    Plain,
+    /// All of it is charged, and the increment added after the move.
+    Fischer(Duration),
+    /// All of it is charged, and up to the delay given back after the move.
+    Bronstein(Duration),
+    /// The clock only starts running once the delay has passed.
+    UsDelay(Duration),
+    /// All of it is charged, and given to the opponent.
+    Hourglass,
+}
+
+/// A period of the game, with the time for it, such as 40 moves in 90 minutes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    /// The moves to play in this stage, `None` for the rest of the game.
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub timing: Timing,
+}
+
+impl Stage {
+    pub fn sudden_death(time: Duration, timing: Timing) -> Self {
+        Self {
+            moves: None,
+            time,
+            timing,
+        }
+    }
+}
+
+/// A chess clock for both sides. Once a side has played the moves of a
+/// stage, the time of the next one is added to its clock; after the last
+/// stage, that stage repeats. A flag falls as soon as a side's time runs out.
+pub struct Clock {
+    stages: Vec<Stage>,
+    source: Box<dyn TimeSource>,
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    moves_in_stage: [u32; 2],
+    /// The side whose clock runs, and since when.
+    running: Option<(PieceColor, Duration)>,
+    flagged: Option<PieceColor>,
+}
+
+impl Clock {
+    /// A clock running on the system time. Panics if there are no stages.
+    pub fn new(stages: Vec<Stage>) -> Self {
+        assert!(!stages.is_empty(), "a clock needs at least one stage");
+        let time = stages[0].time;
+        Self {
+            stages,
+            source: Box::new(SystemTimeSource::default()),
+            remaining: [time; 2],
+            stage: [0; 2],
+            moves_in_stage: [0; 2],
+            running: None,
+            flagged: None,
+        }
+    }
+    pub fn sudden_death(time: Duration, timing: Timing) -> Self {
+        Self::new(vec![Stage::sudden_death(time, timing)])
+    }
+    /// Reads the time from `source` instead, which must only be done before the clock is started.
+    pub fn with_time_source(mut self, source: impl TimeSource + 'static) -> Self {
+        self.source = Box::new(source);
+        self
+    }
+
+    /// Parses a time control as in the PGN `TimeControl` tag, in seconds:
+    /// stages separated by `:`, each either `moves/time`, `time` or
+    /// `time+increment`, or `*time` for an hourglass. For example,
+    /// `40/5400+30:1800+30` is 90 minutes for 40 moves, then 30 for the rest
+    /// of the game, with 30 seconds added per move.
+    pub fn parse(time_control: &str) -> Option<Self> {
+        if let Some(time) = time_control.strip_prefix('*') {
+            let time = parse_seconds(time)?;
+            return Some(Self::sudden_death(time, Timing::Hourglass));
+        }
+        let mut stages = Vec::new();
+        for stage in time_control.split(':') {
+            let (moves, stage) = match stage.split_once('/') {
+                Some((moves, stage)) => (Some(moves.parse().ok().filter(|moves| *moves > 0)?), stage),
+                None => (None, stage),
+            };
+            let (time, timing) = match stage.split_once('+') {
+                Some((time, increment)) => (time, Timing::Fischer(parse_seconds(increment)?)),
+                None => (stage, Timing::Plain),
+            };
+            stages.push(Stage {
+                moves,
+                time: parse_seconds(time)?,
+                timing,
+            });
+        }
+        Some(Self::new(stages))
+    }
+
+    fn stage_of(&self, color: PieceColor) -> &Stage {
+        &self.stages[self.stage[color_index(color)]]
+    }
+
+    /// The time charged so far for the running side's current move.
+    fn charge(&self, color: PieceColor, elapsed: Duration) -> Duration {
+        match self.stage_of(color).timing {
+            Timing::UsDelay(delay) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+
+    /// Starts the clock of `color`, stopping the other one.
+    pub fn start(&mut self, color: PieceColor) {
+        self.stop();
+        self.running = Some((color, self.source.now()));
+    }
+
+    /// Stops the running clock, charging the time of the move in progress.
+    /// Returns how long that side had been thinking.
+    pub fn stop(&mut self) -> Duration {
+        let Some((color, since)) = self.running.take() else {
+            return Duration::ZERO;
+        };
+        let elapsed = self.source.now().saturating_sub(since);
+        let charge = self.charge(color, elapsed);
+        self.charge_to(color, charge);
+        elapsed
+    }
+
+    fn charge_to(&mut self, color: PieceColor, charge: Duration) {
+        let remaining = &mut self.remaining[color_index(color)];
+        if charge >= *remaining {
+            *remaining = Duration::ZERO;
+            self.flagged.get_or_insert(color);
+        } else {
+            *remaining -= charge;
+        }
+    }
+
+    /// Ends the move of the running side, and starts the opponent's clock.
+    /// Returns how long the move took.
+    pub fn press(&mut self) -> Duration {
+        let Some((color, since)) = self.running else {
+            return Duration::ZERO;
+        };
+        let index = color_index(color);
+        let elapsed = self.source.now().saturating_sub(since);
+        let stage = *self.stage_of(color);
+        self.running = None;
+        self.charge_to(color, self.charge(color, elapsed));
+
+        if self.flagged.is_none() {
+            match stage.timing {
+                Timing::Fischer(increment) => self.remaining[index] += increment,
+                Timing::Bronstein(delay) => self.remaining[index] += elapsed.min(delay),
+                Timing::Hourglass => self.remaining[1 - index] += elapsed,
+                Timing::Plain | Timing::UsDelay(_) => {}
+            }
+            self.moves_in_stage[index] += 1;
+            if stage.moves == Some(self.moves_in_stage[index]) {
+                self.moves_in_stage[index] = 0;
+                self.stage[index] = (self.stage[index] + 1).min(self.stages.len() - 1);
+                self.remaining[index] += self.stage_of(color).time;
+            }
+        }
+        self.running = Some((color.opposite(), self.source.now()));
+        elapsed
+    }
+
+    /// The side whose clock is running, if any.
+    pub fn running(&self) -> Option<PieceColor> {
+        self.running.map(|(color, _)| color)
+    }
+
+    /// The time left on the clock of `color`, as a clock would show it.
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        let remaining = self.remaining[color_index(color)];
+        match self.running {
+            Some((running, since)) if running == color => {
+                remaining.saturating_sub(self.charge(color, self.source.now().saturating_sub(since)))
+            }
+            Some((running, since)) if self.stage_of(running).timing == Timing::Hourglass => {
+                remaining + self.source.now().saturating_sub(since)
+            }
+            _ => remaining,
+        }
+    }
+
+    /// How long the running side can still think before its flag falls.
+    fn time_until_flag(&self) -> Option<Duration> {
+        let (color, since) = self.running?;
+        let delay = match self.stage_of(color).timing {
+            Timing::UsDelay(delay) => delay,
+            _ => Duration::ZERO,
+        };
+        let elapsed = self.source.now().saturating_sub(since);
+        Some((self.remaining[color_index(color)] + delay).saturating_sub(elapsed))
+    }
+
+    /// The side whose flag fell, if any.
+    pub fn flagged(&self) -> Option<PieceColor> {
+        self.flagged.or_else(|| {
+            let color = self.running()?;
+            (self.time_until_flag() == Some(Duration::ZERO)).then_some(color)
+        })
+    }
+
+    /// The time situation of `color`, to decide how long to think.
+    pub fn time_control(&self, color: PieceColor) -> TimeControl {
+        let stage = self.stage_of(color);
+        let increment = match stage.timing {
+            Timing::Fischer(increment) => increment,
+            Timing::Bronstein(delay) | Timing::UsDelay(delay) => delay,
+            Timing::Plain | Timing::Hourglass => Duration::ZERO,
+        };
+        TimeControl {
+            remaining: self.remaining(color),
+            increment,
+            moves_to_go: stage.moves.map(|moves| moves - self.moves_in_stage[color_index(color)]),
+            ..TimeControl::default()
+        }
+    }
+}
+
+impl std::fmt::Debug for Clock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Clock")
+            .field("stages", &self.stages)
+            .field("white", &self.remaining(PieceColor::White))
+            .field("black", &self.remaining(PieceColor::Black))
+            .field("running", &self.running())
+            .field("flagged", &self.flagged())
+            .finish()
+    }
+}
+
+fn color_index(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+fn parse_seconds(seconds: &str) -> Option<Duration> {
+    let seconds: f64 = seconds.parse().ok()?;
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn secs(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    fn mock_clock(stages: Vec<Stage>) -> (Clock, MockTimeSource) {
+        let time = MockTimeSource::default();
+        let mut clock = Clock::new(stages).with_time_source(time.clone());
+        clock.start(PieceColor::White);
+        (clock, time)
+    }
+
+    /// Plays a move taking `seconds`.
+    fn play(clock: &mut Clock, time: &MockTimeSource, seconds: u64) {
+        time.advance(secs(seconds));
+        assert_eq!(clock.press(), secs(seconds));
+    }
+
+    #[test_case(Timing::Plain, 55, 60 ; "plain")]
+    #[test_case(Timing::Fischer(secs(2)), 57, 60 ; "fischer")]
+    #[test_case(Timing::Bronstein(secs(2)), 57, 60 ; "bronstein with a slow move")]
+    #[test_case(Timing::Bronstein(secs(10)), 60, 60 ; "bronstein with a fast move")]
+    #[test_case(Timing::UsDelay(secs(2)), 57, 60 ; "us delay")]
+    #[test_case(Timing::Hourglass, 55, 65 ; "hourglass")]
+    fn test_clock_timing(timing: Timing, white: u64, black: u64) {
+        let (mut clock, time) = mock_clock(vec![Stage::sudden_death(secs(60), timing)]);
+        play(&mut clock, &time, 5);
+        assert_eq!(clock.remaining(PieceColor::White), secs(white));
+        assert_eq!(clock.remaining(PieceColor::Black), secs(black));
+        assert_eq!(clock.running(), Some(PieceColor::Black));
+    }
+
+    #[test]
+    fn test_clock_running_side() {
+        let (clock, time) = mock_clock(vec![Stage::sudden_death(secs(60), Timing::Hourglass)]);
+        time.advance(secs(10));
+        assert_eq!(clock.remaining(PieceColor::White), secs(50));
+        assert_eq!(clock.remaining(PieceColor::Black), secs(70));
+        assert_eq!(clock.time_until_flag(), Some(secs(50)));
+
+        let (clock, time) = mock_clock(vec![Stage::sudden_death(secs(60), Timing::UsDelay(secs(5)))]);
+        time.advance(secs(3));
+        assert_eq!(clock.remaining(PieceColor::White), secs(60));
+        assert_eq!(clock.time_until_flag(), Some(secs(62)));
+    }
+
+    #[test]
+    fn test_clock_stages() {
+        let stages = vec![
+            Stage {
+                moves: Some(2),
+                time: secs(60),
+                timing: Timing::Plain,
+            },
+            Stage::sudden_death(secs(30), Timing::Fischer(secs(1))),
+        ];
+        let (mut clock, time) = mock_clock(stages);
+        play(&mut clock, &time, 10);
+        assert_eq!(clock.time_control(PieceColor::White).moves_to_go, Some(1));
+        play(&mut clock, &time, 0);
+        play(&mut clock, &time, 10);
+        assert_eq!(clock.remaining(PieceColor::White), secs(70));
+        let control = clock.time_control(PieceColor::White);
+        assert_eq!(control.moves_to_go, None);
+        assert_eq!(control.increment, secs(1));
+        play(&mut clock, &time, 0);
+        play(&mut clock, &time, 10);
+        assert_eq!(clock.remaining(PieceColor::White), secs(61));
+        assert_eq!(clock.remaining(PieceColor::Black), secs(90));
+    }
+
+    #[test]
+    fn test_clock_last_stage_repeats() {
+        let (mut clock, time) = mock_clock(vec![Stage {
+            moves: Some(1),
+            time: secs(10),
+            timing: Timing::Plain,
+        }]);
+        play(&mut clock, &time, 5);
+        assert_eq!(clock.remaining(PieceColor::White), secs(15));
+    }
+
+    #[test]
+    fn test_clock_flag() {
+        let (mut clock, time) = mock_clock(vec![Stage::sudden_death(secs(10), Timing::Fischer(secs(5)))]);
+        time.advance(secs(9));
+        assert_eq!(clock.flagged(), None);
+        time.advance(secs(1));
+        assert_eq!(clock.flagged(), Some(PieceColor::White));
+        time.advance(secs(1));
+        clock.press();
+        // the increment does not save a fallen flag
+        assert_eq!(clock.remaining(PieceColor::White), Duration::ZERO);
+        assert_eq!(clock.flagged(), Some(PieceColor::White));
+    }
+
+    #[test]
+    fn test_clock_stop() {
+        let (mut clock, time) = mock_clock(vec![Stage::sudden_death(secs(10), Timing::Fischer(secs(5)))]);
+        time.advance(secs(4));
+        assert_eq!(clock.stop(), secs(4));
+        time.advance(secs(4));
+        assert_eq!(clock.running(), None);
+        assert_eq!(clock.remaining(PieceColor::White), secs(6));
+        assert_eq!(clock.time_until_flag(), None);
+    }
+
+    #[test_case("300", vec![Stage::sudden_death(secs(300), Timing::Plain)] ; "sudden death")]
+    #[test_case("180+2", vec![Stage::sudden_death(secs(180), Timing::Fischer(secs(2)))] ; "increment")]
+    #[test_case("*60", vec![Stage::sudden_death(secs(60), Timing::Hourglass)] ; "hourglass")]
+    #[test_case("40/5400+30:1800+30", vec![
+        Stage { moves: Some(40), time: secs(5400), timing: Timing::Fischer(secs(30)) },
+        Stage::sudden_death(secs(1800), Timing::Fischer(secs(30))),
+    ] ; "stages")]
+    fn test_clock_parse(time_control: &str, stages: Vec<Stage>) {
+        assert_eq!(Clock::parse(time_control).unwrap().stages, stages);
+    }
+
+    #[test_case("" ; "empty")]
+    #[test_case("0/60" ; "no moves")]
+    #[test_case("60+" ; "no increment")]
+    #[test_case("-5" ; "negative")]
+    #[test_case("40/60:" ; "empty stage")]
+    fn test_clock_parse_invalid(time_control: &str) {
+        assert!(Clock::parse(time_control).is_none());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    board::Board,
+    piece::{PieceColor, PieceKind},
+    position::Position,
+    state::GameState,
+};
+
+/// The game phase of a board with all minor and major pieces still on it.
+pub const MAX_PHASE: i32 = 24;
+
+/// Evaluation weights, indexed by `PieceKind as usize`. Piece-square tables
+/// are written from White's point of view, with a8 first and h1 last, so
+/// they read like a board diagram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    pub material_mg: [i32; 6],
+    pub material_eg: [i32; 6],
+    pub psqt_mg: [[i32; 64]; 6],
+    pub psqt_eg: [[i32; 64]; 6],
+    /// How much each piece contributes to the game phase, see `MAX_PHASE`.
+    pub phase_weights: [i32; 6],
+    /// Bonus for having the move.
+    pub tempo: i32,
+}
+
+#[rustfmt::skip]
+const PAWN_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+#[rustfmt::skip]
+const PAWN_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     80,  80,  80,  80,  80,  80,  80,  80,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     30,  30,  30,  30,  30,  30,  30,  30,
+     20,  20,  20,  20,  20,  20,  20,  20,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+#[rustfmt::skip]
+const ROOK_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+#[rustfmt::skip]
+const ROOK_EG: [i32; 64] = [
+      5,   5,   5,   5,   5,   5,   5,   5,
+     10,  10,  10,  10,  10,  10,  10,  10,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+#[rustfmt::skip]
+const KING_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+#[rustfmt::skip]
+const KING_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+pub const DEFAULT_EVAL_PARAMS: EvalParams = EvalParams {
+    material_mg: [82, 337, 365, 477, 1025, 0],
+    material_eg: [94, 281, 297, 512, 936, 0],
+    psqt_mg: [PAWN_MG, KNIGHT, BISHOP, ROOK_MG, QUEEN, KING_MG],
+    psqt_eg: [PAWN_EG, KNIGHT, BISHOP, ROOK_EG, QUEEN, KING_EG],
+    phase_weights: [0, 1, 1, 2, 4, 0],
+    tempo: 10,
+};
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_EVAL_PARAMS
+    }
+}
+
+#[inline]
+fn psqt_index(position: Position, color: PieceColor) -> usize {
+    let rank = match color {
+        PieceColor::White => 7 - position.rank(),
+        PieceColor::Black => position.rank(),
+    };
+    (rank * 8 + position.file()) as usize
+}
+
+/// Returns the game phase of `board`, from `MAX_PHASE` in the opening down
+/// to 0 when only kings and pawns are left.
+pub fn game_phase(board: &Board, params: &EvalParams) -> i32 {
+    let phase: i32 = board
+        .squares()
+        .iter()
+        .flatten()
+        .map(|piece| params.phase_weights[piece.kind() as usize])
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+/// Returns the material value of `kind`, interpolated for the given game phase.
+pub fn piece_value(kind: PieceKind, phase: i32, params: &EvalParams) -> i32 {
+    let index = kind as usize;
+    (params.material_mg[index] * phase + params.material_eg[index] * (MAX_PHASE - phase))
+        / MAX_PHASE
+}
+
+/// Scores `state` in centipawns from the point of view of the side to move,
+/// using the given weights.
+pub fn evaluate_with(state: &GameState, params: &EvalParams) -> i32 {
+    let mut mg = 0;
+    let mut eg = 0;
+
+    for (index, square) in state.board.squares().iter().enumerate() {
+        let Some(piece) = square else {
+            continue;
+        };
+        let kind = piece.kind() as usize;
+        let psqt = psqt_index(Position::from(index), piece.color());
+        let piece_mg = params.material_mg[kind] + params.psqt_mg[kind][psqt];
+        let piece_eg = params.material_eg[kind] + params.psqt_eg[kind][psqt];
+
+        match piece.color() {
+            PieceColor::White => {
+                mg += piece_mg;
+                eg += piece_eg;
+            }
+            PieceColor::Black => {
+                mg -= piece_mg;
+                eg -= piece_eg;
+            }
+        }
+    }
+
+    let phase = game_phase(&state.board, params);
+    let score = (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE;
+    match state.turn {
+        PieceColor::White => score + params.tempo,
+        PieceColor::Black => -score + params.tempo,
+    }
+}
+
+/// Scores `state` in centipawns from the point of view of the side to move.
+pub fn evaluate(state: &GameState) -> i32 {
+    evaluate_with(state, &DEFAULT_EVAL_PARAMS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::PositionBuilder;
+    use test_case::test_case;
+
+    #[test]
+    fn test_evaluate_start_position_is_balanced() {
+        let state = GameState::default();
+        assert_eq!(evaluate(&state), DEFAULT_EVAL_PARAMS.tempo);
+    }
+
+    #[test_case("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"; "opening")]
+    #[test_case("8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b - - 99 50"; "pawn endgame")]
+    #[test_case("r4rk1/1b3ppp/pq2p3/1pn5/4PB2/2N2Q2/PPP2PPP/3R1RK1 w - - 0 17"; "middlegame")]
+    fn test_evaluate_mirror_symmetry(fen: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        let mirrored = PositionBuilder::from_state(&state).mirror().build().unwrap();
+        assert_eq!(evaluate(&state), evaluate(&mirrored));
+    }
+
+    #[test]
+    fn test_evaluate_side_to_move_perspective() {
+        let white = GameState::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let black = GameState::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(evaluate(&white) > 800);
+        assert!(evaluate(&black) < -800);
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", MAX_PHASE; "start position")]
+    #[test_case("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1", 0; "pawns only")]
+    #[test_case("3qk3/8/8/8/8/8/8/3QKR2 w - - 0 1", 10; "queens and a rook")]
+    fn test_game_phase(fen: &str, expected: i32) {
+        let state = GameState::from_fen(fen).unwrap();
+        assert_eq!(game_phase(&state.board, &DEFAULT_EVAL_PARAMS), expected);
+    }
+
+    #[test]
+    fn test_evaluate_king_centralization_in_endgame() {
+        let center = GameState::from_fen("4k3/8/8/8/3K4/8/8/8 w - - 0 1").unwrap();
+        let corner = GameState::from_fen("4k3/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert!(evaluate(&center) > evaluate(&corner));
+    }
+
+    #[test]
+    fn test_evaluate_with_custom_params() {
+        let state = GameState::from_fen("4k3/8/8/8/8/8/8/3NK3 w - - 0 1").unwrap();
+        let mut params = EvalParams::default();
+        params.material_mg[PieceKind::Knight as usize] += 100;
+        params.material_eg[PieceKind::Knight as usize] += 100;
+
+        assert_eq!(evaluate_with(&state, &params), evaluate(&state) + 100);
+    }
+}
@@ -7,10 +7,46 @@ mod state;
 mod moves;
 mod position;
 mod player;
+mod async_player;
+mod attacks;
+mod chess960;
+mod builder;
+mod eval;
+mod see;
+mod ordering;
+mod search;
+mod zobrist;
+mod transposition;
+mod notation;
+mod time_manager;
+mod clock;
+mod runner;
+mod rng;
+mod bots;
+mod skill;
+#[cfg(test)]
+mod test_player;
 
 pub use piece::*;
 pub use board::*;
 pub use state::*;
 pub use moves::*;
 pub use position::*;
-pub use player::*;
\ No newline at end of file
+pub use player::*;
+pub use async_player::*;
+pub use attacks::*;
+pub use chess960::*;
+pub use builder::*;
+pub use eval::*;
+pub use see::*;
+pub use ordering::*;
+pub use search::*;
+pub use zobrist::*;
+pub use transposition::*;
+pub use notation::*;
+pub use time_manager::*;
+pub use clock::*;
+pub use runner::*;
+pub use rng::*;
+pub use bots::*;
+pub use skill::*;
\ No newline at end of file
@@ -1,5 +1,6 @@
 use crate::{
-    board::{Board, Square},
+    attacks::{attackers_to, is_square_attacked},
+    board::Square,
     piece::{Piece, PieceColor, PieceKind},
     position::Position,
     state::{CastleSide, GameState},
@@ -45,6 +46,51 @@ pub enum Move {
     },
 }
 
+/// The reason a move cannot be applied to a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// There is no piece on the square the move starts from.
+    NoPieceOnSquare(Position),
+    /// The piece on the starting square belongs to the player not to move.
+    WrongColor,
+    /// The piece cannot move that way, whether by its movement rules or
+    /// because other pieces are in the way.
+    IllegalPieceMove,
+    /// The move would leave the mover's own king in check.
+    LeavesKingInCheck,
+    /// The king or the rook has moved, or the rook is gone.
+    CastlingRightLost(CastleSide),
+    /// A square between the king and the rook or their targets is occupied.
+    CastlingPathBlocked,
+    /// The king would castle out of, through or into check.
+    CastlingPathAttacked,
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::NoPieceOnSquare(position) => write!(f, "there is no piece on {}", position),
+            MoveError::WrongColor => write!(f, "that piece belongs to the other player"),
+            MoveError::IllegalPieceMove => write!(f, "that piece cannot move that way"),
+            MoveError::LeavesKingInCheck => write!(f, "that move would leave the king in check"),
+            MoveError::CastlingRightLost(CastleSide::KingSide) => {
+                write!(f, "the right to castle king side has been lost")
+            }
+            MoveError::CastlingRightLost(CastleSide::QueenSide) => {
+                write!(f, "the right to castle queen side has been lost")
+            }
+            MoveError::CastlingPathBlocked => {
+                write!(f, "there are pieces between the king and the rook")
+            }
+            MoveError::CastlingPathAttacked => {
+                write!(f, "the king cannot castle out of, through or into check")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
 pub struct PawnMoveInfo {
     pub color: PieceColor,
     pub start_rank: u8,
@@ -458,45 +504,81 @@ fn make_pawn_moves(center: Position, state: &GameState, info: &PawnMoveInfo) ->
     moves
 }
 
-fn make_castle_moves(state: &GameState, color: PieceColor) -> Vec<Move> {
-    let mut moves = Vec::new();
-    let king = if color == PieceColor::White {
-        Position::new(0, 4)
-    } else {
-        Position::new(7, 4)
-    };
+/// Returns the (king target, rook target) files for castling to `side`.
+fn castle_target_files(side: CastleSide) -> (u8, u8) {
+    match side {
+        CastleSide::KingSide => (6, 5),
+        CastleSide::QueenSide => (2, 3),
+    }
+}
 
-    if state.can_castle(color, CastleSide::KingSide) {
-        let rook = Position::new(0, 7);
-        let rook_target = Position::new(0, 5);
-        let king_target = Position::new(0, 6);
-
-        if state.board.get(rook_target).is_none() && state.board.get(king_target).is_none() {
-            moves.push(Move::Castle {
-                from: king,
-                to: king_target,
-                rook_from: rook,
-                rook_to: rook_target,
-            });
+/// Builds the castling move of `color` towards `side`, or the reason castling
+/// that way is not possible.
+pub fn make_castle_move(
+    state: &GameState,
+    color: PieceColor,
+    side: CastleSide,
+) -> Result<Move, MoveError> {
+    let home_rank = color.back_rank();
+    let rook_file = state
+        .castling_rights
+        .get(color, side)
+        .ok_or(MoveError::CastlingRightLost(side))?;
+    let king = state
+        .board
+        .find_king(color)
+        .filter(|king| king.rank() == home_rank)
+        .ok_or(MoveError::CastlingRightLost(side))?;
+    let rook = Position::new(home_rank, rook_file);
+    if state.board.get(rook) != Some(Piece::new(PieceKind::Rook, color)) {
+        return Err(MoveError::CastlingRightLost(side));
+    }
+
+    let (king_target_file, rook_target_file) = castle_target_files(side);
+    let king_target = Position::new(home_rank, king_target_file);
+    let rook_target = Position::new(home_rank, rook_target_file);
+
+    // every square the king or rook passes over or lands on must be empty,
+    // except for the squares the castling king and rook are standing on
+    let files = [king.file(), rook.file(), king_target_file, rook_target_file];
+    let min_file = *files.iter().min().unwrap();
+    let max_file = *files.iter().max().unwrap();
+    for file in min_file..=max_file {
+        let position = Position::new(home_rank, file);
+        if position != king && position != rook && state.board.get(position).is_some() {
+            return Err(MoveError::CastlingPathBlocked);
         }
     }
-    if state.can_castle(state.turn, CastleSide::QueenSide) {
-        let rook = Position::new(0, 0);
-        let rook_target = Position::new(0, 3);
-        let king_target = Position::new(0, 2);
-        if state.board.get(rook_target).is_none()
-            && state.board.get(king_target).is_none()
-            && state.board.get(Position::new(0, 1)).is_none()
-        {
-            moves.push(Move::Castle {
-                from: king,
-                to: king_target,
-                rook_from: rook,
-                rook_to: rook_target,
-            });
+
+    // the king may not pass through or land on an attacked square; the castling
+    // pieces are lifted so they cannot shield any of those squares
+    let mut board = state.board;
+    board.set(king, None);
+    board.set(rook, None);
+    let (path_start, path_end) = if king.file() < king_target_file {
+        (king.file(), king_target_file)
+    } else {
+        (king_target_file, king.file())
+    };
+    for file in path_start..=path_end {
+        if is_square_attacked(&board, Position::new(home_rank, file), color.opposite()) {
+            return Err(MoveError::CastlingPathAttacked);
         }
     }
-    moves
+
+    Ok(Move::Castle {
+        from: king,
+        to: king_target,
+        rook_from: rook,
+        rook_to: rook_target,
+    })
+}
+
+fn make_castle_moves(state: &GameState, color: PieceColor) -> Vec<Move> {
+    [CastleSide::KingSide, CastleSide::QueenSide]
+        .into_iter()
+        .filter_map(|side| make_castle_move(state, color, side).ok())
+        .collect()
 }
 pub fn get_moves_for_square(
     square: Square,
@@ -530,31 +612,76 @@ pub fn get_moves(state: &GameState, color: PieceColor) -> Vec<Move> {
     moves
 }
 
-pub fn prune_moves_into_check(mut moves: Vec<Move>, state: &GameState) -> Vec<Move> {
-    let mut i = 0;
-    while i < moves.len() {
-        if let Move::Castle { rook_to, .. } = moves[i] {
-            if state.is_in_check() || get_moves(state, state.turn.opposite())
-                                        .iter()
-                                        .any(|m| m.to() == rook_to)
-            {
-                moves.remove(i);
-                continue;
-            } else {
-                i += 1;
+/// Generates the captures and promotions of `color`, like `get_moves` but
+/// without generating any quiet moves of pieces other than pawns.
+pub fn get_tactical_moves(state: &GameState, color: PieceColor) -> Vec<Move> {
+    let mut moves = Vec::new();
+    let pawn_info = PawnMoveInfo::new(color);
+    for (index, square) in state.board.squares().iter().enumerate() {
+        let position = Position::from(index);
+        match square {
+            Some(piece) if piece.color() == color && piece.kind() == PieceKind::Pawn => {
+                let pawn_moves = make_pawn_moves(position, state, &pawn_info);
+                moves.extend(pawn_moves.into_iter().filter(|m| !m.is_quiet()));
             }
+            Some(piece) if piece.color() != color && piece.kind() != PieceKind::King => {
+                for from in attackers_to(&state.board, position, color) {
+                    if state.board.get(from).is_some_and(|attacker| attacker.kind() != PieceKind::Pawn) {
+                        moves.push(Move::Capture {
+                            from,
+                            to: position,
+                            captured: *piece,
+                        });
+                    }
+                }
+            }
+            _ => {}
         }
-        let mut new_state = (*state).clone();
-        new_state.apply_move(moves[i]);
-        if new_state.is_in_check() {
-            moves.remove(i);
-        } else {
-            i += 1;
-        }
     }
     moves
 }
 
+/// Generates the moves of `color` that neither capture nor promote.
+pub fn get_quiet_moves(state: &GameState, color: PieceColor) -> Vec<Move> {
+    let mut moves = get_moves(state, color);
+    moves.retain(Move::is_quiet);
+    moves
+}
+
+pub fn prune_moves_into_check(mut moves: Vec<Move>, state: &GameState) -> Vec<Move> {
+    // castling through or out of check is already rejected by `make_castle_moves`,
+    // so every move only needs to be checked for leaving the king attacked
+    let mut new_state = state.clone();
+    moves.retain(|m| {
+        let undo = new_state.make_move(*m);
+        let legal = !new_state.is_king_attacked(state.turn);
+        new_state.unmake_move(*m, undo);
+        legal
+    });
+    moves
+}
+
+/// Counts the leaf nodes of the legal move tree of the given depth.
+pub fn perft(state: &GameState, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = state.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut new_state = state.clone();
+    moves
+        .into_iter()
+        .map(|m| {
+            let undo = new_state.make_move(m);
+            let nodes = perft(&new_state, depth - 1);
+            new_state.unmake_move(m, undo);
+            nodes
+        })
+        .sum()
+}
+
 impl Move {
     #[cfg(not(tarpaulin_include))]
     pub fn from(&self) -> Position {
@@ -580,6 +707,30 @@ impl Move {
             Move::Castle { to, .. } => *to,
         }
     }
+    /// Returns the piece a pawn promotes to, if this is a promotion.
+    pub fn promotion(&self) -> Option<Piece> {
+        match self {
+            Move::Promotion { promoted, .. } => Some(*promoted),
+            Move::PromotionCapture { promoted, .. } => Some(*promoted),
+            _ => None,
+        }
+    }
+    /// Returns the kind of piece this move captures, if it is a capture.
+    pub fn captured_kind(&self) -> Option<PieceKind> {
+        match self {
+            Move::Capture { captured, .. } => Some(captured.kind()),
+            Move::PromotionCapture { captured, .. } => Some(captured.kind()),
+            Move::EnPassant { .. } => Some(PieceKind::Pawn),
+            _ => None,
+        }
+    }
+    pub fn is_capture(&self) -> bool {
+        self.captured_kind().is_some()
+    }
+    /// Returns whether this move neither captures nor promotes.
+    pub fn is_quiet(&self) -> bool {
+        !self.is_capture() && self.promotion().is_none()
+    }
 }
 
 #[cfg(test)]
@@ -1724,6 +1875,137 @@ mod tests {
     #[test]
     fn test_prune_moves_into_check_prevent_castle_into_check() {
         let mut board = Board::empty();
+        board.set(
+            Position::new(0, 4),
+            Some(Piece::new(PieceKind::King, PieceColor::White)),
+        );
+        board.set(
+            Position::new(0, 0),
+            Some(Piece::new(PieceKind::Rook, PieceColor::White)),
+        );
+        board.set(
+            Position::new(0, 7),
+            Some(Piece::new(PieceKind::Rook, PieceColor::White)),
+        );
+        board.set(
+            Position::new(7, 2),
+            Some(Piece::new(PieceKind::Rook, PieceColor::Black)),
+        );
+        board.set(
+            Position::new(7, 6),
+            Some(Piece::new(PieceKind::Rook, PieceColor::Black)),
+        );
+
+        let state = GameState::new(board, PieceColor::White, 0b1100, None, 0, 0);
+        let moves = get_moves(&state, PieceColor::White);
+        let moves = prune_moves_into_check(moves, &state);
+
+        moves.assert_forall(|m| !matches!(m, Move::Castle{..}));
+    }
+
+    #[test]
+    fn test_get_moves_castle_moves_black() {
+        let state = GameState::from_fen("r3k2r/8/8/8/8/8/8/4K3 b kq - 0 1").unwrap();
+        let castle_moves = state.legal_moves().into_iter()
+                            .filter(|m| matches!(m, Move::Castle{..}))
+                            .collect::<Vec<_>>();
+
+        castle_moves.assert_len(2);
+        assert!(castle_moves.contains(&Move::Castle {
+            from: Position::new(7, 4),
+            to: Position::new(7, 6),
+            rook_from: Position::new(7, 7),
+            rook_to: Position::new(7, 5)
+        }));
+        assert!(castle_moves.contains(&Move::Castle {
+            from: Position::new(7, 4),
+            to: Position::new(7, 2),
+            rook_from: Position::new(7, 0),
+            rook_to: Position::new(7, 3)
+        }));
+    }
+
+    #[test_case("r3k2r/8/8/8/8/8/8/4K3 b Kk - 0 1", 1; "only own rights count")]
+    #[test_case("r3k2r/8/8/8/8/8/8/4K1R1 b kq - 0 1", 1; "kingside target attacked")]
+    #[test_case("r3k2r/8/8/8/8/8/8/4KR2 b kq - 0 1", 1; "kingside path attacked")]
+    #[test_case("r3k2r/8/8/8/8/8/8/1R2K3 b kq - 0 1", 2; "queenside rook path attacked")]
+    #[test_case("r3k2r/8/8/8/8/8/8/3RK3 b kq - 0 1", 1; "queenside path attacked")]
+    #[test_case("r3k2r/8/8/8/8/8/8/4R1K1 b kq - 0 1", 0; "in check")]
+    #[test_case("r3k1nr/8/8/8/8/8/8/4K3 b kq - 0 1", 1; "kingside blocked")]
+    #[test_case("rn2k2r/8/8/8/8/8/8/4K3 b kq - 0 1", 1; "queenside blocked on b-file")]
+    #[test_case("r3k2r/8/8/8/8/8/8/4K3 b - - 0 1", 0; "no rights")]
+    #[test_case("4k2r/8/8/8/8/8/8/4K3 b kq - 0 1", 1; "queenside rook missing")]
+    fn test_get_moves_castle_moves_black_rules(fen: &str, expected: usize) {
+        let state = GameState::from_fen(fen).unwrap();
+        let castle_moves = state.legal_moves().into_iter()
+                            .filter(|m| matches!(m, Move::Castle{..}))
+                            .collect::<Vec<_>>();
+
+        castle_moves.assert_len(expected);
+    }
+
+    #[test]
+    fn test_apply_move_rook_capture_waives_castle_rights() {
+        let mut state = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        state.apply_move(Move::Capture {
+            from: Position::new(0, 0),
+            to: Position::new(7, 0),
+            captured: Piece::new(PieceKind::Rook, PieceColor::Black),
+        });
+
+        assert!(!state.can_castle(PieceColor::White, CastleSide::QueenSide));
+        assert!(!state.can_castle(PieceColor::Black, CastleSide::QueenSide));
+        assert!(state.can_castle(PieceColor::White, CastleSide::KingSide));
+        assert!(state.can_castle(PieceColor::Black, CastleSide::KingSide));
+    }
+
+    #[test]
+    fn test_apply_move_rook_off_home_rank_keeps_castle_rights() {
+        let mut state = GameState::from_fen("r3k2r/8/8/8/R7/8/8/4K2R w Kkq - 0 1").unwrap();
+        state.apply_move(Move::Normal {
+            from: Position::new(3, 0),
+            to: Position::new(3, 1),
+        });
+
+        assert!(state.can_castle(PieceColor::White, CastleSide::KingSide));
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902]; "start position")]
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]; "kiwipete")]
+    #[test_case("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]; "en passant pins")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]; "promotions and castling")]
+    #[test_case("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467]; "promotions and castling mirrored")]
+    #[test_case("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]; "discovered checks")]
+    #[test_case("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", &[21, 528, 12189]; "chess960 1")]
+    #[test_case("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", &[21, 807, 18002]; "chess960 2")]
+    #[test_case("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", &[20, 479, 10471]; "chess960 3")]
+    #[test_case("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", &[22, 593, 13440]; "chess960 4")]
+    #[test_case("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", &[28, 1120, 31058]; "chess960 5")]
+    fn test_perft(fen: &str, expected: &[u64]) {
+        let state = GameState::from_fen(fen).unwrap();
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(&state, depth + 1), *nodes, "depth {}", depth + 1);
+        }
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"; "kiwipete")]
+    #[test_case("8/2p5/3p4/KP5r/1R3p1k/4P3/6P1/8 b - - 0 1"; "en passant pins")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"; "promotions")]
+    #[test_case("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1"; "promotions black")]
+    #[test_case("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"; "en passant")]
+    fn test_get_tactical_and_quiet_moves_partition_moves(fen: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        let mut expected = get_moves(&state, state.turn);
+        expected.retain(|m| !m.is_quiet());
+        let tactical = get_tactical_moves(&state, state.turn);
+
+        assert_eq!(tactical.len(), expected.len());
+        expected.assert_forall(|m| tactical.contains(m));
+        get_quiet_moves(&state, state.turn).assert_forall(|m| m.is_quiet());
+        assert_eq!(
+            tactical.len() + get_quiet_moves(&state, state.turn).len(),
+            get_moves(&state, state.turn).len()
+        );
     }
 
 }
@@ -0,0 +1,236 @@
+use crate::{
+    moves::Move,
+    piece::{PieceColor, PieceKind},
+    position::Position,
+    state::GameState,
+};
+
+fn piece_letter(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+/// Formats `m`, a legal move in `state`, in standard algebraic notation,
+/// e.g. `Nbd7`, `exd5`, `e8=Q+`, `O-O-O` or `Qh4#`.
+pub fn to_san(state: &GameState, m: Move) -> String {
+    let mut san = String::new();
+    if let Move::Castle { from, rook_from, .. } = m {
+        if rook_from.file() > from.file() {
+            san.push_str("O-O");
+        } else {
+            san.push_str("O-O-O");
+        }
+    } else {
+        let kind = state.board.get(m.from()).map_or(PieceKind::Pawn, |piece| piece.kind());
+        if kind == PieceKind::Pawn {
+            if m.is_capture() {
+                san.push(m.from().to_string().remove(0));
+            }
+        } else {
+            san.push(piece_letter(kind));
+            // disambiguate by file if that is enough, then by rank, then by both
+            let others: Vec<_> = state
+                .legal_moves()
+                .into_iter()
+                .filter(|other| {
+                    other.to() == m.to()
+                        && other.from() != m.from()
+                        && !matches!(other, Move::Castle { .. })
+                        && state.board.get(other.from()).map(|piece| piece.kind()) == Some(kind)
+                })
+                .collect();
+            if !others.is_empty() {
+                let from = m.from().to_string();
+                if others.iter().all(|other| other.from().file() != m.from().file()) {
+                    san.push_str(&from[..1]);
+                } else if others.iter().all(|other| other.from().rank() != m.from().rank()) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+        }
+        if m.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&m.to().to_string());
+        if let Some(promoted) = m.promotion() {
+            san.push('=');
+            san.push(piece_letter(promoted.kind()));
+        }
+    }
+
+    let mut after = state.clone();
+    after.apply_move(m);
+    if after.is_checkmate() {
+        san.push('#');
+    } else if after.is_in_check() {
+        san.push('+');
+    }
+    san
+}
+
+/// Formats a line of moves played from `state` in standard algebraic
+/// notation with move numbers, e.g. `1. e4 e5 2. Nf3` or `3... Nc6 4. Bb5`.
+pub fn line_to_san(state: &GameState, moves: &[Move]) -> String {
+    let mut state = state.clone();
+    let mut parts = Vec::with_capacity(moves.len());
+    for (index, m) in moves.iter().enumerate() {
+        let number = state.fullmove_number;
+        match state.turn {
+            PieceColor::White => parts.push(format!("{}. {}", number, to_san(&state, *m))),
+            PieceColor::Black if index == 0 => parts.push(format!("{}... {}", number, to_san(&state, *m))),
+            PieceColor::Black => parts.push(to_san(&state, *m)),
+        }
+        state.apply_move(*m);
+    }
+    parts.join(" ")
+}
+
+/// Formats `m` in the long algebraic notation of the UCI protocol, e.g.
+/// `e2e4` or `e7e8q`. Castling is written as the king's move, or as the king
+/// capturing its own rook in Chess960.
+pub fn to_uci(state: &GameState, m: Move) -> String {
+    let to = match m {
+        Move::Castle { rook_from, .. } if state.chess960 => rook_from,
+        _ => m.to(),
+    };
+    let mut uci = format!("{}{}", m.from(), to);
+    if let Some(promoted) = m.promotion() {
+        uci.push(piece_letter(promoted.kind()).to_ascii_lowercase());
+    }
+    uci
+}
+
+/// Parses a move in UCI notation, returning `None` unless it is legal in `state`.
+pub fn parse_uci(state: &GameState, uci: &str) -> Option<Move> {
+    if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+        return None;
+    }
+    let from = Position::try_from(&uci[0..2]).ok()?;
+    let to = Position::try_from(&uci[2..4]).ok()?;
+    let promotion = match uci.as_bytes().get(4) {
+        None => None,
+        Some(b'n') => Some(PieceKind::Knight),
+        Some(b'b') => Some(PieceKind::Bishop),
+        Some(b'r') => Some(PieceKind::Rook),
+        Some(b'q') => Some(PieceKind::Queen),
+        Some(_) => return None,
+    };
+    state.find_move(from, to, promotion)
+}
+
+/// Parses a move in standard algebraic notation, returning `None` unless it
+/// is legal in `state`. Check marks, annotations like `!?` and the `=` of
+/// promotions are optional, and castling may be written with zeros.
+pub fn parse_san(state: &GameState, san: &str) -> Option<Move> {
+    let normalize = |san: &str| -> String {
+        san.trim_end_matches(['+', '#', '!', '?'])
+            .chars()
+            .filter(|c| *c != '=')
+            .map(|c| if c == '0' { 'O' } else { c })
+            .collect()
+    };
+    let san = normalize(san);
+    if san.is_empty() {
+        return None;
+    }
+    state.legal_moves().into_iter().find(|m| normalize(&to_san(state, *m)) == san)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e2", "e4", None, "e4"; "pawn push")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "g1", "f3", None, "Nf3"; "knight")]
+    #[test_case("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "e4", "d5", None, "exd5"; "pawn capture")]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5", "d6", None, "exd6"; "en passant")]
+    #[test_case("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7", "a8", Some(PieceKind::Queen), "bxa8=Q+"; "promotion capture with check")]
+    #[test_case("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "g1", None, "O-O"; "king side castle")]
+    #[test_case("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1", "c1", None, "O-O-O"; "queen side castle")]
+    #[test_case("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1", "d2", None, "Nbd2"; "disambiguate by file")]
+    #[test_case("4k3/8/8/8/R7/8/8/R3K3 w - - 0 1", "a1", "a2", None, "R1a2"; "disambiguate by rank")]
+    #[test_case("4k3/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1", "a4", "b3", None, "Qa4b3"; "disambiguate by square")]
+    #[test_case("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1", "a1", "a8", None, "Ra8#"; "checkmate")]
+    fn test_to_san(fen: &str, from: &str, to: &str, promotion: Option<PieceKind>, expected: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        let m = state
+            .find_move(Position::try_from(from).unwrap(), Position::try_from(to).unwrap(), promotion)
+            .unwrap();
+        assert_eq!(to_san(&state, m), expected);
+    }
+
+    #[test]
+    fn test_line_to_san() {
+        let mut state = GameState::default();
+        let mut moves = Vec::new();
+        for (from, to) in [("e2", "e4"), ("e7", "e5"), ("g1", "f3")] {
+            let m = state.find_move(Position::try_from(from).unwrap(), Position::try_from(to).unwrap(), None);
+            state.apply_move(m.unwrap());
+            moves.push(m.unwrap());
+        }
+        assert_eq!(line_to_san(&GameState::default(), &moves), "1. e4 e5 2. Nf3");
+        assert_eq!(line_to_san(&GameState::default(), &[]), "");
+
+        let mut black_to_move = GameState::default();
+        black_to_move.apply_move(moves[0]);
+        assert_eq!(line_to_san(&black_to_move, &moves[1..]), "1... e5 2. Nf3");
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e2e4"; "pawn push")]
+    #[test_case("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n"; "promotion")]
+    #[test_case("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "e1g1"; "castle")]
+    fn test_uci_round_trip(fen: &str, uci: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        let m = parse_uci(&state, uci).unwrap();
+        assert_eq!(to_uci(&state, m), uci);
+    }
+
+    #[test]
+    fn test_uci_chess960_castle() {
+        let mut state = GameState::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(parse_uci(&state, "e1h1"), parse_uci(&state, "e1g1"));
+        state.chess960 = true;
+        let m = parse_uci(&state, "e1h1").unwrap();
+        assert!(matches!(m, Move::Castle { .. }));
+        assert_eq!(to_uci(&state, m), "e1h1");
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "Nf3", "g1f3"; "knight")]
+    #[test_case("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "Ra8#", "a1a8"; "mate")]
+    #[test_case("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "Ra8!!", "a1a8"; "annotated")]
+    #[test_case("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8=N", "b7b8n"; "promotion")]
+    #[test_case("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8Q+", "b7b8q"; "promotion without equals")]
+    #[test_case("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "0-0-0", "e1c1"; "castle with zeros")]
+    #[test_case("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", "Rad1", "a1d1"; "disambiguated")]
+    fn test_parse_san(fen: &str, san: &str, uci: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        assert_eq!(parse_san(&state, san), parse_uci(&state, uci));
+        assert!(parse_san(&state, san).is_some());
+    }
+
+    #[test_case("e5"; "illegal")]
+    #[test_case(""; "empty")]
+    #[test_case("Rd1"; "ambiguous")]
+    fn test_parse_san_invalid(san: &str) {
+        let state = GameState::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+        assert_eq!(parse_san(&state, san), None);
+    }
+
+    #[test_case("e2e5"; "illegal")]
+    #[test_case("e2"; "too short")]
+    #[test_case("e7e8x"; "bad promotion")]
+    #[test_case("z2e4"; "bad square")]
+    #[test_case("é2e4"; "not ascii")]
+    fn test_parse_uci_invalid(uci: &str) {
+        assert_eq!(parse_uci(&GameState::default(), uci), None);
+    }
+}
@@ -0,0 +1,399 @@
+use crate::{
+    moves::{get_quiet_moves, get_tactical_moves, prune_moves_into_check, Move},
+    piece::{Piece, PieceColor},
+    search::MAX_PLY,
+    see::{see_ge, SEE_VALUES},
+    state::GameState,
+    transposition::CompactMove,
+};
+
+const MAX_HISTORY: i32 = 16384;
+
+#[inline]
+fn color_index(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+#[inline]
+fn piece_index(piece: Piece) -> usize {
+    color_index(piece.color()) * 6 + piece.kind() as usize
+}
+
+/// What the search has learned about quiet moves that caused cutoffs: two
+/// killer moves per ply, a history score per color and from/to squares, and
+/// the countermove that refuted each piece moving to each square.
+#[derive(Debug, Clone)]
+pub struct MoveOrdering {
+    killers: Vec<[Option<Move>; 2]>,
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    countermoves: Box<[[Option<Move>; 64]; 12]>,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        Self {
+            killers: vec![[None; 2]; MAX_PLY],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            countermoves: Box::new([[None; 64]; 12]),
+        }
+    }
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+    /// Forgets the killers, which belong to the plies of the last search, and
+    /// halves the history so that recent cutoffs weigh more.
+    pub fn new_search(&mut self) {
+        self.killers.fill([None; 2]);
+        for score in self.history.iter_mut().flatten().flatten() {
+            *score /= 2;
+        }
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers.get(ply).copied().unwrap_or_default()
+    }
+    pub fn history(&self, color: PieceColor, m: Move) -> i32 {
+        self.history[color_index(color)][usize::from(m.from())][usize::from(m.to())]
+    }
+    /// Returns the move that last refuted `previous`, the move that led to `state`.
+    pub fn countermove(&self, state: &GameState, previous: Option<Move>) -> Option<Move> {
+        let previous = previous?;
+        let piece = state.board.get(previous.to())?;
+        self.countermoves[piece_index(piece)][usize::from(previous.to())]
+    }
+
+    fn add_history(&mut self, color: PieceColor, m: Move, bonus: i32) {
+        let score = &mut self.history[color_index(color)][usize::from(m.from())][usize::from(m.to())];
+        // scales the bonus down as the score nears its bounds, so it never overflows
+        *score += bonus - *score * bonus.abs() / MAX_HISTORY;
+    }
+
+    /// Records that the quiet move `m` caused a beta cutoff in `state` at
+    /// `ply`, after the quiet moves in `tried` were searched without one.
+    pub fn update_quiet_cutoff(
+        &mut self,
+        state: &GameState,
+        m: Move,
+        previous: Option<Move>,
+        ply: usize,
+        depth: usize,
+        tried: &[Move],
+    ) {
+        if let Some(killers) = self.killers.get_mut(ply) {
+            if killers[0] != Some(m) {
+                killers[1] = killers[0];
+                killers[0] = Some(m);
+            }
+        }
+
+        let bonus = (depth * depth).min(MAX_HISTORY as usize) as i32;
+        self.add_history(state.turn, m, bonus);
+        for other in tried {
+            self.add_history(state.turn, *other, -bonus);
+        }
+
+        if let Some(previous) = previous {
+            if let Some(piece) = state.board.get(previous.to()) {
+                self.countermoves[piece_index(piece)][usize::from(previous.to())] = Some(m);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    HashMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    Countermove,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the legal moves of a position one at a time, most promising
+/// first: the hash move, captures and promotions that do not lose material
+/// by most valuable victim and least valuable attacker, the killer moves, the
+/// countermove, the remaining quiet moves by history score, and lastly the
+/// captures that lose material. Every stage is generated only once the
+/// previous ones are used up, so a cutoff early on skips the rest.
+pub struct MovePicker {
+    stage: Stage,
+    tactical_only: bool,
+    hash_move: Option<CompactMove>,
+    killers: [Option<Move>; 2],
+    killer_index: usize,
+    countermove: Option<Move>,
+    /// Moves handed out before their stage was generated.
+    picked_early: Vec<Move>,
+    scored: Vec<(Move, i32)>,
+    bad_captures: Vec<Move>,
+}
+
+impl MovePicker {
+    pub fn new(
+        state: &GameState,
+        ordering: &MoveOrdering,
+        hash_move: Option<CompactMove>,
+        ply: usize,
+        previous: Option<Move>,
+    ) -> Self {
+        Self {
+            stage: Stage::HashMove,
+            tactical_only: false,
+            hash_move,
+            killers: ordering.killers(ply),
+            killer_index: 0,
+            countermove: ordering.countermove(state, previous),
+            picked_early: Vec::new(),
+            scored: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+    /// A picker for the quiescence search, which only hands out the captures
+    /// and promotions that do not lose material.
+    pub fn new_tactical() -> Self {
+        Self {
+            stage: Stage::GenerateCaptures,
+            tactical_only: true,
+            hash_move: None,
+            killers: [None; 2],
+            killer_index: 0,
+            countermove: None,
+            picked_early: Vec::new(),
+            scored: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    fn mvv_lva(state: &GameState, m: Move) -> i32 {
+        let victim = m.captured_kind().map_or(0, |kind| SEE_VALUES[kind as usize]);
+        let promotion = m.promotion().map_or(0, |piece| SEE_VALUES[piece.kind() as usize]);
+        let attacker = state.board.get(m.from()).map_or(0, |piece| piece.kind() as i32);
+        16 * (victim + promotion) - attacker
+    }
+
+    fn pick_best(&mut self) -> Option<Move> {
+        let index = (0..self.scored.len()).max_by_key(|index| self.scored[*index].1)?;
+        Some(self.scored.swap_remove(index).0)
+    }
+
+    /// Picks `m` ahead of its stage if it is legal in `state` and has not been picked yet.
+    fn pick_early(&mut self, state: &GameState, m: Move) -> Option<Move> {
+        if self.picked_early.contains(&m) || !state.legal_moves_from(m.from()).contains(&m) {
+            return None;
+        }
+        self.picked_early.push(m);
+        Some(m)
+    }
+
+    /// Returns the next move to search, or `None` once all moves were handed out.
+    /// `state` and `ordering` must be the ones the picker was created with.
+    pub fn next(&mut self, state: &GameState, ordering: &MoveOrdering) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    let Some(hash_move) = self.hash_move else {
+                        continue;
+                    };
+                    if let Some(m) = hash_move.resolve(&state.legal_moves_from(hash_move.from())) {
+                        self.picked_early.push(m);
+                        return Some(m);
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    let moves = prune_moves_into_check(get_tactical_moves(state, state.turn), state);
+                    for m in moves {
+                        if self.picked_early.contains(&m) {
+                            continue;
+                        }
+                        if see_ge(&state.board, m, 0) {
+                            self.scored.push((m, Self::mvv_lva(state, m)));
+                        } else if !self.tactical_only {
+                            self.bad_captures.push(m);
+                        }
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    if let Some(m) = self.pick_best() {
+                        return Some(m);
+                    }
+                    self.stage = if self.tactical_only {
+                        Stage::Done
+                    } else {
+                        Stage::Killers
+                    };
+                }
+                Stage::Killers => {
+                    let Some(killer) = self.killers.get(self.killer_index).copied() else {
+                        self.stage = Stage::Countermove;
+                        continue;
+                    };
+                    self.killer_index += 1;
+                    if let Some(m) = killer.and_then(|m| self.pick_early(state, m)) {
+                        return Some(m);
+                    }
+                }
+                Stage::Countermove => {
+                    self.stage = Stage::GenerateQuiets;
+                    if let Some(m) = self.countermove.and_then(|m| self.pick_early(state, m)) {
+                        return Some(m);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    let moves = prune_moves_into_check(get_quiet_moves(state, state.turn), state);
+                    for m in moves {
+                        if !self.picked_early.contains(&m) {
+                            self.scored.push((m, ordering.history(state.turn, m)));
+                        }
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    if let Some(m) = self.pick_best() {
+                        return Some(m);
+                    }
+                    self.bad_captures.reverse();
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if let Some(m) = self.bad_captures.pop() {
+                        return Some(m);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+    use devutil::VecAsserts;
+    use test_case::test_case;
+
+    fn find(state: &GameState, from: &str, to: &str) -> Move {
+        state
+            .find_move(Position::try_from(from).unwrap(), Position::try_from(to).unwrap(), None)
+            .unwrap()
+    }
+
+    fn pick_all(state: &GameState, ordering: &MoveOrdering, mut picker: MovePicker) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(m) = picker.next(state, ordering) {
+            moves.push(m);
+        }
+        moves
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"; "kiwipete")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"; "promotions")]
+    #[test_case("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"; "pins")]
+    #[test_case("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"; "checkmate")]
+    fn test_move_picker_yields_every_legal_move_once(fen: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        let legal = state.legal_moves();
+        let mut ordering = MoveOrdering::new();
+        let quiets: Vec<Move> = legal.iter().copied().filter(Move::is_quiet).collect();
+        if let [first, second, ..] = quiets[..] {
+            let previous = Move::Normal {
+                from: Position::new(7, 0),
+                to: Position::new(6, 0),
+            };
+            ordering.update_quiet_cutoff(&state, first, Some(previous), 3, 4, &[]);
+            ordering.update_quiet_cutoff(&state, second, None, 3, 4, &[]);
+        }
+        // a killer from another position, which is not legal here
+        ordering.killers[3][1] = Some(Move::Normal {
+            from: Position::new(3, 3),
+            to: Position::new(4, 4),
+        });
+        let hash_move = legal.last().copied().map(CompactMove::new);
+
+        let picker = MovePicker::new(&state, &ordering, hash_move, 3, None);
+        let picked = pick_all(&state, &ordering, picker);
+        assert_eq!(picked.len(), legal.len());
+        legal.assert_forall(|m| picked.contains(m));
+        if let Some(hash_move) = hash_move {
+            assert!(hash_move.matches(&picked[0]));
+        }
+    }
+
+    #[test]
+    fn test_move_picker_stage_order() {
+        // Qxa7 wins a rook, Nxb5 and Qxd6 lose material to a pawn recapture
+        let state = GameState::from_fen("4k3/r1p5/p2p4/1p6/3Q4/2N5/8/4K3 w - - 0 1").unwrap();
+        let mut ordering = MoveOrdering::new();
+        let killer = find(&state, "e1", "f1");
+        let history = find(&state, "d4", "h4");
+        ordering.update_quiet_cutoff(&state, killer, None, 0, 1, &[]);
+        ordering.add_history(PieceColor::White, history, 500);
+
+        let picker = MovePicker::new(&state, &ordering, None, 0, None);
+        let picked = pick_all(&state, &ordering, picker);
+
+        assert_eq!(picked[0], find(&state, "d4", "a7"));
+        assert_eq!(picked[1], killer);
+        assert_eq!(picked[2], history);
+        picked[1..picked.len() - 2].to_vec().assert_forall(|m| m.is_quiet());
+        let bad_captures = picked[picked.len() - 2..].to_vec();
+        bad_captures.assert_eq(&[find(&state, "c3", "b5"), find(&state, "d4", "d6")]);
+    }
+
+    #[test]
+    fn test_move_picker_tactical_only() {
+        let state = GameState::from_fen("4k3/r1p5/p2p4/1p6/3Q4/2N5/8/4K3 w - - 0 1").unwrap();
+        let ordering = MoveOrdering::new();
+        let picked = pick_all(&state, &ordering, MovePicker::new_tactical());
+        assert_eq!(picked, vec![find(&state, "d4", "a7")]);
+    }
+
+    #[test]
+    fn test_move_picker_prefers_valuable_victims() {
+        // Rxd8 and Nxe5 both win material, the rook is the bigger prize
+        let state = GameState::from_fen("3rk3/8/8/4p3/2N5/8/8/3RK3 w - - 0 1").unwrap();
+        let ordering = MoveOrdering::new();
+        let picked = pick_all(&state, &ordering, MovePicker::new_tactical());
+        assert_eq!(picked, vec![find(&state, "d1", "d8"), find(&state, "c4", "e5")]);
+    }
+
+    #[test]
+    fn test_move_ordering_cutoff_updates() {
+        let state = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut ordering = MoveOrdering::new();
+        let first = find(&state, "a1", "a7");
+        let second = find(&state, "a1", "a2");
+        let failed = find(&state, "e1", "d1");
+        let mut after_black = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let previous = find(&after_black, "e8", "d8");
+        after_black.apply_move(previous);
+
+        ordering.update_quiet_cutoff(&after_black, first, Some(previous), 2, 3, &[failed]);
+        ordering.update_quiet_cutoff(&state, second, None, 2, 3, &[]);
+        assert_eq!(ordering.killers(2), [Some(second), Some(first)]);
+        assert_eq!(ordering.killers(3), [None, None]);
+        assert_eq!(ordering.history(PieceColor::White, first), 9);
+        assert!(ordering.history(PieceColor::White, failed) < 0);
+        assert_eq!(ordering.countermove(&after_black, Some(previous)), Some(first));
+
+        ordering.new_search();
+        assert_eq!(ordering.killers(2), [None, None]);
+        assert_eq!(ordering.history(PieceColor::White, first), 4);
+    }
+}
@@ -21,6 +21,13 @@ impl PieceColor {
             Self::White => Self::Black,
         }
     }
+    /// The rank the pieces of this color start on.
+    pub fn back_rank(&self) -> u8 {
+        match self {
+            Self::Black => 7,
+            Self::White => 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -52,4 +59,9 @@ mod tests {
         assert_eq!(PieceColor::Black.opposite(), PieceColor::White);
         assert_eq!(PieceColor::White.opposite(), PieceColor::Black);
     }
+    #[test]
+    fn test_piece_color_back_rank() {
+        assert_eq!(PieceColor::White.back_rank(), 0);
+        assert_eq!(PieceColor::Black.back_rank(), 7);
+    }
 }
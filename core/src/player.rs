@@ -1,5 +1,141 @@
 use crate::state::GameState;
 use crate::moves::Move;
+use crate::piece::PieceColor;
+use crate::search::StopSignal;
+use crate::time_manager::TimeControl;
+
+/// What a player does on its turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerAction {
+    Move(Move),
+    Resign,
+    /// Plays the move and offers a draw, which the opponent may accept on its turn.
+    OfferDraw(Move),
+    /// Accepts the draw the opponent offered with its last move.
+    AcceptDraw,
+    /// Claims a draw by threefold repetition or the fifty move rule.
+    ClaimDraw,
+}
+
+impl PlayerAction {
+    /// Returns the move played, if any.
+    pub fn played_move(&self) -> Option<Move> {
+        match self {
+            PlayerAction::Move(m) | PlayerAction::OfferDraw(m) => Some(*m),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinReason {
+    Checkmate,
+    Resignation,
+    Timeout,
+    /// The loser tried to play an illegal move.
+    IllegalMove,
+    /// The game was stopped and scored, e.g. by an arena, with the winner far ahead.
+    Adjudication,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    Agreement,
+    Repetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    /// The flag fell, but the opponent could not possibly have mated.
+    TimeoutVsInsufficientMaterial,
+    /// The game was stopped and scored, e.g. by an arena, as a dead draw.
+    Adjudication,
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win { winner: PieceColor, reason: WinReason },
+    Draw(DrawReason),
+}
+
+impl Outcome {
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            Outcome::Win { winner, .. } => Some(*winner),
+            Outcome::Draw(_) => None,
+        }
+    }
+    /// The result as written in PGN: `1-0`, `0-1` or `1/2-1/2`.
+    pub fn result(&self) -> &'static str {
+        match self.winner() {
+            Some(PieceColor::White) => "1-0",
+            Some(PieceColor::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+/// A participant in a game. Apart from `get_move`, the methods are
+/// notifications that players may use to keep track of the game.
 pub trait Player {
-    fn get_move(&self, state: &GameState) -> Move;    
-}
\ No newline at end of file
+    /// Decides what to do in `state`, where it is this player's turn.
+    fn get_move(&mut self, state: &GameState) -> PlayerAction;
+
+    /// A game starts from `start`, in which this player plays `color`.
+    fn on_game_start(&mut self, _color: PieceColor, _start: &GameState) {}
+    /// The opponent played `m`.
+    fn on_opponent_move(&mut self, _m: Move) {}
+    /// The opponent offered a draw, which can be accepted with `PlayerAction::AcceptDraw`.
+    fn on_draw_offer(&mut self) {}
+    /// The draw this player just claimed or accepted was not available, and
+    /// it is asked to move again.
+    fn on_draw_rejected(&mut self) {}
+    /// In a clocked game, called before each `get_move` with the time left
+    /// to both sides, for the player to budget its thinking time.
+    fn on_clock(&mut self, _white: TimeControl, _black: TimeControl) {}
+    fn on_game_end(&mut self, _outcome: Outcome) {}
+
+    /// The score this player gave the move it last played, in centipawns
+    /// from its own point of view, for players that evaluate positions.
+    fn last_score(&self) -> Option<i32> {
+        None
+    }
+    /// A signal that makes a running `get_move` return as soon as it can,
+    /// for players that think for a while. Whoever stops it resets it
+    /// before asking for the next move.
+    fn stop_signal(&self) -> Option<StopSignal> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+
+    #[test]
+    fn test_player_action_played_move() {
+        let m = Move::Normal {
+            from: Position::new(1, 4),
+            to: Position::new(2, 4),
+        };
+        assert_eq!(PlayerAction::Move(m).played_move(), Some(m));
+        assert_eq!(PlayerAction::OfferDraw(m).played_move(), Some(m));
+        assert_eq!(PlayerAction::Resign.played_move(), None);
+        assert_eq!(PlayerAction::AcceptDraw.played_move(), None);
+        assert_eq!(PlayerAction::ClaimDraw.played_move(), None);
+    }
+
+    #[test]
+    fn test_outcome_result() {
+        let win = Outcome::Win {
+            winner: PieceColor::Black,
+            reason: WinReason::Checkmate,
+        };
+        assert_eq!(win.winner(), Some(PieceColor::Black));
+        assert_eq!(win.result(), "0-1");
+        let draw = Outcome::Draw(DrawReason::Stalemate);
+        assert_eq!(draw.winner(), None);
+        assert_eq!(draw.result(), "1/2-1/2");
+    }
+}
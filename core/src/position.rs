@@ -1,5 +1,3 @@
-use std::ops::{Add, Sub};
-
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     data: u8,
@@ -27,6 +25,18 @@ impl Position {
     pub fn file(&self) -> u8 {
         self.data & 0b00000111
     }
+    /// Returns the position `rank_offset` ranks and `file_offset` files away,
+    /// or `None` if that would leave the board.
+    #[inline]
+    pub fn offset(&self, rank_offset: i8, file_offset: i8) -> Option<Self> {
+        let rank = self.rank() as i8 + rank_offset;
+        let file = self.file() as i8 + file_offset;
+        if (0..8).contains(&rank) && (0..8).contains(&file) {
+            Some(Self::new(rank as u8, file as u8))
+        } else {
+            None
+        }
+    }
 }
 impl From<Position> for (u8, u8) {
     fn from(position: Position) -> Self {
@@ -89,11 +99,11 @@ impl From<u8> for Position {
         Self { data: value }
     }
 }
-impl ToString for Position {
-    fn to_string(&self) -> String {        
-        let file = ((self.data & 0b00000111) + ('a' as u8)) as char;
-        let rank = ((self.rank() >> 3) + ('1' as u8)) as char;
-        format!("{}{}", file, rank)
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file = (self.file() + b'a') as char;
+        let rank = (self.rank() + b'1') as char;
+        write!(f, "{}{}", file, rank)
     }
 }
 impl std::fmt::Debug for Position {
@@ -176,6 +186,14 @@ mod tests {
         assert!(Position::try_from("a9").is_err());
     }
     #[test]
+    fn test_position_offset() {
+        let position = Position::new(3, 4);
+        assert_eq!(position.offset(1, -1), Some(Position::new(4, 3)));
+        assert_eq!(position.offset(-3, 3), Some(Position::new(0, 7)));
+        assert_eq!(position.offset(5, 0), None);
+        assert_eq!(position.offset(0, -5), None);
+    }
+    #[test]
     fn test_position_to_string_ok() {
         let position = Position::new(0, 0);
         assert_eq!(position.to_string(), "a1");
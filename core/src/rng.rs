@@ -0,0 +1,75 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+
+use crate::zobrist::split_mix;
+
+/// A small, fast random number generator (SplitMix64). Not suitable for
+/// anything but games: the same seed always gives the same numbers.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    /// A generator seeded differently on every call.
+    pub fn from_entropy() -> Self {
+        Self::new(RandomState::new().hash_one(0u64))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let output;
+        (self.state, output) = split_mix(self.state);
+        output
+    }
+    /// A number in `0..bound`, which must not be 0.
+    pub fn below(&mut self, bound: usize) -> usize {
+        // the bias is negligible for the small bounds used in games
+        (self.next_u64() % bound as u64) as usize
+    }
+    /// A number in `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// A random element of `items`, or `None` if it is empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.below(items.len())])
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn test_rng_ranges() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 6];
+        for _ in 0..1000 {
+            seen[rng.below(6)] = true;
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+        }
+        assert_eq!(seen, [true; 6]);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+        assert_eq!(rng.choose(&[3]), Some(&3));
+    }
+}
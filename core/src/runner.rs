@@ -0,0 +1,427 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    clock::Clock,
+    moves::Move,
+    piece::PieceColor,
+    player::{DrawReason, Outcome, Player, PlayerAction, WinReason},
+    state::GameState,
+};
+
+/// A finished game, as played by a `GameRunner`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub start: GameState,
+    pub moves: Vec<Move>,
+    /// The time each move took, in the order of `moves`.
+    pub move_times: Vec<Duration>,
+    /// Whether a draw was offered along with each move.
+    pub draw_offers: Vec<bool>,
+    pub outcome: Outcome,
+}
+
+impl GameRecord {
+    /// The position at the end of the game.
+    pub fn final_state(&self) -> GameState {
+        let mut state = self.start.clone();
+        for m in &self.moves {
+            state.apply_move(*m);
+        }
+        state
+    }
+}
+
+/// Decides from the position, and the score its last move was given, whether
+/// to end a game early, see `GameRunner::with_adjudicator`.
+pub type Adjudicator<'a> = dyn FnMut(&GameState, Option<i32>) -> Option<Outcome> + 'a;
+
+/// Plays a game between two players, checking their moves and deciding
+/// when and how it ends. Threefold repetition and the fifty move rule end
+/// the game without a claim, so a player claiming a draw on its turn is
+/// never entitled to it. Such a claim, like accepting a draw that was not
+/// offered, is rejected, and the player is asked again while its clock
+/// keeps running.
+pub struct GameRunner<'a> {
+    white: &'a mut dyn Player,
+    black: &'a mut dyn Player,
+    start: GameState,
+    clock: Option<Clock>,
+    adjudicator: Option<Box<Adjudicator<'a>>>,
+}
+
+impl<'a> GameRunner<'a> {
+    pub fn new(white: &'a mut dyn Player, black: &'a mut dyn Player) -> Self {
+        Self {
+            white,
+            black,
+            start: GameState::default(),
+            clock: None,
+            adjudicator: None,
+        }
+    }
+    pub fn with_start(mut self, start: GameState) -> Self {
+        self.start = start;
+        self
+    }
+    /// Plays on `clock`, which the runner starts, and which should not have
+    /// been started before. The players learn their time through `Player::on_clock`.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+    /// Asks `adjudicator` before every move whether to end the game early,
+    /// e.g. because one side is hopelessly lost. Along with the position, it
+    /// gets the score the player who made the last move reported for it, see
+    /// `Player::last_score`, in centipawns from White's point of view.
+    pub fn with_adjudicator(
+        mut self,
+        adjudicator: impl FnMut(&GameState, Option<i32>) -> Option<Outcome> + 'a,
+    ) -> Self {
+        self.adjudicator = Some(Box::new(adjudicator));
+        self
+    }
+
+    pub fn run(mut self) -> GameRecord {
+        self.white.on_game_start(PieceColor::White, &self.start);
+        self.black.on_game_start(PieceColor::Black, &self.start);
+
+        let mut record = GameRecord {
+            start: self.start.clone(),
+            moves: Vec::new(),
+            move_times: Vec::new(),
+            draw_offers: Vec::new(),
+            outcome: Outcome::Draw(DrawReason::Agreement),
+        };
+        record.outcome = self.play(&mut record);
+
+        self.white.on_game_end(record.outcome);
+        self.black.on_game_end(record.outcome);
+        record
+    }
+
+    fn play(&mut self, record: &mut GameRecord) -> Outcome {
+        let mut state = self.start.clone();
+        // the positions since the last capture or pawn move, for repetitions
+        let mut positions = vec![state.hash()];
+        let mut draw_offered_by = None;
+        // the last mover's score for its move, from White's point of view
+        let mut score = None;
+        if let Some(clock) = self.clock.as_mut() {
+            clock.start(state.turn);
+        }
+
+        loop {
+            if let Some(outcome) = adjudicate(&state, &positions) {
+                return outcome;
+            }
+            if let Some(outcome) = self.adjudicator.as_mut().and_then(|adjudicator| adjudicator(&state, score)) {
+                return outcome;
+            }
+            let mover = state.turn;
+            let opponent = mover.opposite();
+            let forfeit = |reason| Outcome::Win {
+                winner: opponent,
+                reason,
+            };
+
+            let start = Instant::now();
+            let player = match mover {
+                PieceColor::White => &mut *self.white,
+                PieceColor::Black => &mut *self.black,
+            };
+            let action = loop {
+                if let Some(clock) = &self.clock {
+                    player.on_clock(clock.time_control(PieceColor::White), clock.time_control(PieceColor::Black));
+                }
+                let action = player.get_move(&state);
+                let rejected = match action {
+                    PlayerAction::AcceptDraw => draw_offered_by != Some(opponent),
+                    PlayerAction::ClaimDraw => true,
+                    _ => false,
+                };
+                // a flag that fell in the meantime ends the game below
+                if !rejected || self.clock.as_ref().and_then(Clock::flagged).is_some() {
+                    break action;
+                }
+                player.on_draw_rejected();
+            };
+            let elapsed = match self.clock.as_mut() {
+                Some(clock) => clock.press(),
+                None => start.elapsed(),
+            };
+            if self.clock.as_ref().and_then(Clock::flagged).is_some() {
+                return if state.has_insufficient_material(opponent) {
+                    Outcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+                } else {
+                    forfeit(WinReason::Timeout)
+                };
+            }
+
+            let m = match action {
+                PlayerAction::Resign => return forfeit(WinReason::Resignation),
+                PlayerAction::AcceptDraw | PlayerAction::ClaimDraw => return Outcome::Draw(DrawReason::Agreement),
+                PlayerAction::Move(m) | PlayerAction::OfferDraw(m) => m,
+            };
+            if !state.legal_moves().contains(&m) {
+                return forfeit(WinReason::IllegalMove);
+            }
+
+            state.apply_move(m);
+            if state.halfmove_clock == 0 {
+                positions.clear();
+            }
+            positions.push(state.hash());
+            let offers_draw = matches!(action, PlayerAction::OfferDraw(_));
+            record.moves.push(m);
+            record.move_times.push(elapsed);
+            record.draw_offers.push(offers_draw);
+            draw_offered_by = offers_draw.then_some(mover);
+            score = player.last_score().map(|score| match mover {
+                PieceColor::White => score,
+                PieceColor::Black => -score,
+            });
+
+            let opponent_player = match opponent {
+                PieceColor::White => &mut *self.white,
+                PieceColor::Black => &mut *self.black,
+            };
+            opponent_player.on_opponent_move(m);
+            if offers_draw {
+                opponent_player.on_draw_offer();
+            }
+        }
+    }
+}
+
+/// Returns how the game ends in `state`, if it does, given the hashes of the
+/// positions since the last capture or pawn move, `state` included.
+pub fn adjudicate(state: &GameState, positions: &[u64]) -> Option<Outcome> {
+    if state.legal_moves().is_empty() {
+        return Some(if state.is_in_check() {
+            Outcome::Win {
+                winner: state.turn.opposite(),
+                reason: WinReason::Checkmate,
+            }
+        } else {
+            Outcome::Draw(DrawReason::Stalemate)
+        });
+    }
+    let hash = state.hash();
+    if positions.iter().filter(|position| **position == hash).count() >= 3 {
+        return Some(Outcome::Draw(DrawReason::Repetition));
+    }
+    if state.is_50_move_rule() {
+        return Some(Outcome::Draw(DrawReason::FiftyMoveRule));
+    }
+    if state.is_insufficient_material() {
+        return Some(Outcome::Draw(DrawReason::InsufficientMaterial));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bots::*, test_player::Scripted};
+    use test_case::test_case;
+
+    fn play(white: &[&'static str], black: &[&'static str], fen: Option<&str>) -> GameRecord {
+        let mut white = Scripted::new(white);
+        let mut black = Scripted::new(black);
+        let mut runner = GameRunner::new(&mut white, &mut black);
+        if let Some(fen) = fen {
+            runner = runner.with_start(GameState::from_fen(fen).unwrap());
+        }
+        let record = runner.run();
+        assert_eq!(white.notes().outcome, Some(record.outcome));
+        assert_eq!(black.notes().outcome, Some(record.outcome));
+        record
+    }
+
+    fn win(winner: PieceColor, reason: WinReason) -> Outcome {
+        Outcome::Win { winner, reason }
+    }
+
+    #[test]
+    fn test_checkmate() {
+        let record = play(&["f2f3", "g2g4"], &["e7e5", "d8h4"], None);
+        assert_eq!(record.outcome, win(PieceColor::Black, WinReason::Checkmate));
+        assert_eq!(record.moves.len(), 4);
+        assert_eq!(record.move_times.len(), 4);
+        assert!(record.final_state().is_checkmate());
+    }
+
+    #[test]
+    fn test_stalemate() {
+        let record = play(&["c6c7"], &[], Some("k7/8/1KQ5/8/8/8/8/8 w - - 0 1"));
+        assert_eq!(record.outcome, Outcome::Draw(DrawReason::Stalemate));
+    }
+
+    #[test]
+    fn test_repetition() {
+        let white = ["g1f3", "f3g1", "g1f3", "f3g1"];
+        let black = ["g8f6", "f6g8", "g8f6", "f6g8"];
+        let record = play(&white, &black, None);
+        assert_eq!(record.outcome, Outcome::Draw(DrawReason::Repetition));
+        assert_eq!(record.moves.len(), 8);
+    }
+
+    #[test]
+    fn test_fifty_move_rule() {
+        let record = play(&["a1a2"], &[], Some("k7/8/8/8/8/8/8/R6K w - - 99 80"));
+        assert_eq!(record.outcome, Outcome::Draw(DrawReason::FiftyMoveRule));
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let record = play(&["a1c2"], &[], Some("k7/8/8/8/8/8/2p5/N6K w - - 0 1"));
+        assert_eq!(record.outcome, Outcome::Draw(DrawReason::InsufficientMaterial));
+    }
+
+    #[test]
+    fn test_resignation() {
+        let record = play(&["e2e4"], &[], None);
+        assert_eq!(record.outcome, win(PieceColor::White, WinReason::Resignation));
+    }
+
+    #[test]
+    fn test_illegal_move_forfeits() {
+        let record = play(&["e2e5"], &[], None);
+        assert_eq!(record.outcome, win(PieceColor::Black, WinReason::IllegalMove));
+        assert!(record.moves.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_draw_claims_are_rejected() {
+        let mut white = Scripted::new(&["claim", "accept", "e2e4"]);
+        let mut black = Scripted::new(&["e7e5"]);
+        let record = GameRunner::new(&mut white, &mut black).run();
+        assert_eq!(white.notes().rejected_draws, 2);
+        assert_eq!(record.moves.len(), 2);
+        assert_eq!(record.outcome, win(PieceColor::Black, WinReason::Resignation));
+    }
+
+    #[test]
+    fn test_draw_offers() {
+        // the offer lapses once declined by moving
+        let record = play(&["e2e4=", "d2d4"], &["e7e5", "accept"], None);
+        assert_eq!(record.outcome, win(PieceColor::White, WinReason::Resignation));
+        assert_eq!(record.draw_offers, vec![true, false, false]);
+
+        let record = play(&["e2e4="], &["accept"], None);
+        assert_eq!(record.outcome, Outcome::Draw(DrawReason::Agreement));
+    }
+
+    #[test]
+    fn test_draw_offer_notifies_opponent() {
+        let mut white = Scripted::new(&["e2e4="]);
+        let mut black = Scripted::new(&["accept"]);
+        GameRunner::new(&mut white, &mut black).run();
+        assert_eq!(black.notes().draw_offers, 1);
+        assert_eq!(white.notes().draw_offers, 0);
+    }
+
+    #[test]
+    fn test_player_hooks() {
+        let start = GameState::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let mut white = Scripted::new(&["e2e4", "e1d2"]);
+        let mut black = Scripted::new(&["e8d7"]);
+        let record = GameRunner::new(&mut white, &mut black).with_start(start.clone()).run();
+        for (player, color) in [(&white, PieceColor::White), (&black, PieceColor::Black)] {
+            let notes = player.notes();
+            assert_eq!(notes.color, Some(color));
+            assert_eq!(notes.start.as_ref(), Some(&start));
+            assert_eq!(notes.outcome, Some(record.outcome));
+        }
+        assert_eq!(white.notes().opponent_moves, record.moves[1..2]);
+        assert_eq!(black.notes().opponent_moves, [record.moves[0], record.moves[2]]);
+        // unclocked games tell nobody about clocks
+        assert!(white.notes().clocks.is_empty());
+    }
+
+    fn clocked(time: &str, white: &mut Scripted, black: &mut Scripted, fen: Option<&str>) -> GameRecord {
+        let clock = Clock::parse(time).unwrap().with_time_source(white.time.clone());
+        let start = fen.map(|fen| GameState::from_fen(fen).unwrap()).unwrap_or_default();
+        GameRunner::new(white, black).with_start(start).with_clock(clock).run()
+    }
+
+    #[test]
+    fn test_adjudicator() {
+        let mut white = Scripted::new(&["e2e4", "d2d4"]);
+        let mut black = Scripted::new(&["e7e5"]);
+        white.score = Some(20);
+        black.score = Some(-30);
+        let mut scores = Vec::new();
+        let record = GameRunner::new(&mut white, &mut black)
+            .with_adjudicator(|state, score| {
+                scores.push(score);
+                (state.fullmove_number == 2).then_some(Outcome::Draw(DrawReason::Adjudication))
+            })
+            .run();
+        assert_eq!(record.outcome, Outcome::Draw(DrawReason::Adjudication));
+        assert_eq!(record.moves.len(), 2);
+        // the adjudicator sees the movers' scores from White's point of view
+        assert_eq!(scores, vec![None, Some(20), Some(30)]);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let record = clocked("0.01", &mut Scripted::new(&["sleep"]), &mut Scripted::new(&[]), None);
+        assert_eq!(record.outcome, win(PieceColor::Black, WinReason::Timeout));
+        assert!(record.moves.is_empty());
+    }
+
+    #[test]
+    fn test_timeout_against_insufficient_material() {
+        let mut white = Scripted::new(&["sleep"]);
+        let mut black = Scripted::new(&[]);
+        let record = clocked("0.01", &mut white, &mut black, Some("k7/8/8/8/8/8/8/R6K w - - 0 1"));
+        assert_eq!(record.outcome, Outcome::Draw(DrawReason::TimeoutVsInsufficientMaterial));
+    }
+
+    #[test]
+    fn test_invalid_draw_claims_cost_time() {
+        let mut white = Scripted::new(&["stall", "stall", "stall"]);
+        let record = clocked("0.1", &mut white, &mut Scripted::new(&[]), None);
+        assert_eq!(record.outcome, win(PieceColor::Black, WinReason::Timeout));
+        assert_eq!(white.notes().rejected_draws, 1);
+    }
+
+    #[test]
+    fn test_clock_increment() {
+        let mut white = Scripted::new(&["sleep", "sleep"]);
+        let mut black = Scripted::new(&["e7e5"]);
+        let record = clocked("0.06+0.05", &mut white, &mut black, None);
+        // without the increment, the second move would lose on time
+        assert_eq!(record.outcome, win(PieceColor::White, WinReason::Resignation));
+        assert_eq!(record.move_times, vec![Duration::from_millis(50), Duration::ZERO, Duration::from_millis(50)]);
+        // the players learn the time left to both sides before each move
+        let (start, increment) = (Duration::from_millis(60), Duration::from_millis(50));
+        assert_eq!(white.notes().clocks, vec![(start, start), (start, start + increment)]);
+        assert_eq!(black.notes().clocks, vec![(start, start), (start, start + increment)]);
+    }
+
+    #[test_case(0)]
+    #[test_case(1)]
+    #[test_case(2)]
+    fn test_bot_games_run_to_completion(seed: u64) {
+        let mut random = RandomPlayer::new(seed);
+        let mut greedy = GreedyPlayer::new(seed);
+        let mut cautious = CautiousPlayer::new(seed);
+        let games = [
+            GameRunner::new(&mut random, &mut greedy).run(),
+            GameRunner::new(&mut cautious, &mut random).run(),
+        ];
+        for record in games {
+            let end = record.final_state();
+            match record.outcome {
+                Outcome::Win { winner, reason } => {
+                    assert_eq!(reason, WinReason::Checkmate);
+                    assert_eq!(end.turn, winner.opposite());
+                    assert!(end.is_checkmate());
+                }
+                Outcome::Draw(reason) => assert_ne!(reason, DrawReason::Agreement),
+            }
+        }
+    }
+}
@@ -0,0 +1,1483 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    eval::{evaluate_with, EvalParams},
+    moves::Move,
+    piece::{PieceColor, PieceKind},
+    notation::line_to_san,
+    player::{Player, PlayerAction},
+    ordering::{MoveOrdering, MovePicker},
+    rng::Rng,
+    skill::{eval_noise, Skill, SKILL_MULTI_PV},
+    state::GameState,
+    time_manager::{TimeControl, TimeManager},
+    transposition::{Bound, TranspositionTable},
+};
+
+/// The score of delivering checkmate right now. Mates further away score
+/// one point less per ply, so that shorter mates are preferred.
+pub const MATE_SCORE: i32 = 30000;
+pub const MAX_PLY: usize = 128;
+const INFINITY: i32 = MATE_SCORE + 1;
+
+/// Returns whether `score` announces a forced mate for either side.
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - MAX_PLY as i32
+}
+
+/// Converts a mate score into the number of moves (not plies) until mate,
+/// positive if the side to move mates and negative if it gets mated.
+pub fn mate_distance(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        return None;
+    }
+    let plies = MATE_SCORE - score.abs();
+    if score > 0 {
+        Some((plies + 1) / 2)
+    } else {
+        Some(-(plies + 1) / 2)
+    }
+}
+
+/// When a search should end. Limits left at `None` do not apply; a search
+/// without any limits runs until it is stopped through its `StopSignal`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+    /// Budget the time from the clock, see `TimeManager`.
+    pub time_control: Option<TimeControl>,
+}
+
+impl SearchLimits {
+    pub fn infinite() -> Self {
+        Self::default()
+    }
+    pub fn depth(depth: usize) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Self::default()
+        }
+    }
+    pub fn time(time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            ..Self::default()
+        }
+    }
+    pub fn time_control(time_control: TimeControl) -> Self {
+        Self {
+            time_control: Some(time_control),
+            ..Self::default()
+        }
+    }
+}
+
+/// Switches for the selective parts of the search, all on by default, so
+/// that their effect can be measured by turning them off in self-play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchConfig {
+    /// Skip a move and prune if the position still fails high.
+    pub null_move_pruning: bool,
+    /// Search late quiet moves less deep, unless they turn out to be good.
+    pub late_move_reductions: bool,
+    /// Prune near the horizon when the static evaluation is far above beta.
+    pub reverse_futility_pruning: bool,
+    /// Search one ply deeper when in check.
+    pub check_extensions: bool,
+    /// Start each iteration with a narrow window around the last score.
+    pub aspiration_windows: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            null_move_pruning: true,
+            late_move_reductions: true,
+            reverse_futility_pruning: true,
+            check_extensions: true,
+            aspiration_windows: true,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Plain alpha-beta, with all selectivity turned off.
+    pub fn plain() -> Self {
+        Self {
+            null_move_pruning: false,
+            late_move_reductions: false,
+            reverse_futility_pruning: false,
+            check_extensions: false,
+            aspiration_windows: false,
+        }
+    }
+}
+
+/// Returns whether `color` has any pieces besides its king and pawns. Without
+/// them zugzwang is common, and null-move pruning is unsound.
+fn has_non_pawn_material(state: &GameState, color: PieceColor) -> bool {
+    state.board.squares().iter().flatten().any(|piece| {
+        piece.color() == color && !matches!(piece.kind(), PieceKind::Pawn | PieceKind::King)
+    })
+}
+
+/// A flag that stops a running search, and can be shared across threads.
+#[derive(Debug, Clone, Default)]
+pub struct StopSignal(Arc<AtomicBool>);
+
+impl StopSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// Clears the flag so the signal can be used for another search.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// One of the lines found by a MultiPV search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PvLine {
+    pub depth: usize,
+    pub score: i32,
+    pub pv: Vec<Move>,
+    /// `pv` in standard algebraic notation with move numbers, as played from
+    /// the root position.
+    pub san: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// `None` only if the root position has no legal moves.
+    pub best_move: Option<Move>,
+    /// Centipawns from the root side to move's point of view, see `MATE_SCORE`.
+    pub score: i32,
+    /// The last fully completed iteration.
+    pub depth: usize,
+    pub nodes: u64,
+    /// The principal variation, starting with `best_move`.
+    pub pv: Vec<Move>,
+    /// The best lines with different first moves, best first. The first line
+    /// matches `best_move`, `score` and `pv`.
+    pub lines: Vec<PvLine>,
+}
+
+/// A progress report of a running search, sent to the searcher's `InfoSink`
+/// each time an iteration completes a line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: usize,
+    /// The deepest ply reached in this iteration, quiescence search included.
+    pub seldepth: usize,
+    /// Which line this is, starting at 1, see `Searcher::set_multi_pv`.
+    pub multi_pv: usize,
+    pub score: i32,
+    /// Nodes searched by the main thread so far.
+    pub nodes: u64,
+    pub nps: u64,
+    /// How full the transposition table is, in permille.
+    pub hashfull: usize,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+/// Receives the progress reports of a search.
+pub trait InfoSink: Send {
+    fn send(&mut self, info: SearchInfo);
+}
+
+impl<F: FnMut(SearchInfo) + Send> InfoSink for F {
+    fn send(&mut self, info: SearchInfo) {
+        self(info)
+    }
+}
+
+impl InfoSink for Sender<SearchInfo> {
+    fn send(&mut self, info: SearchInfo) {
+        // nobody listening is not a reason to stop searching
+        let _ = Sender::send(self, info);
+    }
+}
+
+/// Iterative deepening negamax alpha-beta search. With more than one thread
+/// it runs a Lazy SMP search: helper threads search the same root through
+/// the shared transposition table, and the main thread reports the result.
+pub struct Searcher {
+    params: EvalParams,
+    config: SearchConfig,
+    threads: usize,
+    multi_pv: usize,
+    /// The number of lines the current search looks for, which may be more
+    /// than `multi_pv` for a weakened search to choose from.
+    lines: usize,
+    /// Root moves left out of the search, because they head earlier MultiPV lines.
+    excluded: Vec<Move>,
+    stop: StopSignal,
+    info_sink: Option<Box<dyn InfoSink>>,
+    skill: Skill,
+    rng: Rng,
+    /// The evaluation noise of the current search, and the seed it is drawn with.
+    noise: (i32, u64),
+    tt: Arc<TranspositionTable>,
+    ordering: MoveOrdering,
+    /// The move made at each ply of the current line, `None` for a null move.
+    played: Vec<Option<Move>>,
+    /// The hash of the position at each ply of the current line.
+    path: Vec<u64>,
+    /// The hashes of the positions of the game before the root, see `set_history`.
+    history: Vec<u64>,
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    seldepth: usize,
+    aborted: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Self {
+        Self::with_params(EvalParams::default())
+    }
+    pub fn with_params(params: EvalParams) -> Self {
+        Self::with_tt(params, Arc::new(TranspositionTable::default()))
+    }
+    /// Creates a searcher on an existing transposition table, e.g. one shared
+    /// with other searchers.
+    pub fn with_tt(params: EvalParams, tt: Arc<TranspositionTable>) -> Self {
+        Self {
+            params,
+            config: SearchConfig::default(),
+            threads: 1,
+            multi_pv: 1,
+            lines: 1,
+            excluded: Vec::new(),
+            stop: StopSignal::new(),
+            info_sink: None,
+            skill: Skill::full(),
+            rng: Rng::from_entropy(),
+            noise: (0, 0),
+            tt,
+            ordering: MoveOrdering::new(),
+            played: vec![None; MAX_PLY],
+            path: vec![0; MAX_PLY],
+            history: Vec::new(),
+            limits: SearchLimits::default(),
+            start: Instant::now(),
+            nodes: 0,
+            seldepth: 0,
+            aborted: false,
+        }
+    }
+    /// Returns a signal that stops this searcher's current or next search.
+    /// The signal is not cleared when a search starts; call `reset` for that.
+    pub fn stop_signal(&self) -> StopSignal {
+        self.stop.clone()
+    }
+
+    pub fn config(&self) -> SearchConfig {
+        self.config
+    }
+    pub fn set_config(&mut self, config: SearchConfig) {
+        self.config = config;
+    }
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+    /// Sets the number of threads to search with, including the calling one.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+    pub fn multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+    /// Sets the number of lines with different first moves to search for.
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+    /// Sets where progress reports go, e.g. a closure or a channel `Sender`.
+    pub fn set_info_sink(&mut self, sink: impl InfoSink + 'static) {
+        self.info_sink = Some(Box::new(sink));
+    }
+    pub fn clear_info_sink(&mut self) {
+        self.info_sink = None;
+    }
+    pub fn skill(&self) -> Skill {
+        self.skill
+    }
+    /// Weakens the search to `skill`, see `Skill`. Changing the skill clears
+    /// the transposition table, as the scores of a weakened search are skewed
+    /// by noise.
+    pub fn set_skill(&mut self, skill: Skill) {
+        if skill != self.skill {
+            self.tt.clear();
+        }
+        self.skill = skill;
+    }
+    /// Seeds the random choices of a weakened search, which are different
+    /// for every searcher otherwise.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+    pub fn tt(&self) -> &Arc<TranspositionTable> {
+        &self.tt
+    }
+    /// Forgets what earlier searches learned: the transposition table and
+    /// the move ordering statistics, along with the game history.
+    pub fn new_game(&mut self) {
+        self.tt.clear();
+        self.ordering.clear();
+        self.history.clear();
+    }
+    /// Sets the hashes of the positions the game went through before the one
+    /// to search, oldest first, so that the search scores a return to any of
+    /// them as a draw. They are kept for later searches until set again.
+    pub fn set_history(&mut self, history: Vec<u64>) {
+        self.history = history;
+    }
+    /// Replaces the transposition table, e.g. to resize it or to share one
+    /// between several searchers.
+    pub fn set_tt(&mut self, tt: Arc<TranspositionTable>) {
+        self.tt = tt;
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.aborted {
+            return true;
+        }
+        let out_of_nodes = self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+        // reading the clock is comparatively slow, so only do it every so often
+        let out_of_time = self.nodes.is_multiple_of(1024)
+            && self
+                .limits
+                .time
+                .is_some_and(|time| self.start.elapsed() >= time);
+        if out_of_nodes || out_of_time || self.stop.is_stopped() {
+            self.aborted = true;
+        }
+        self.aborted
+    }
+
+    fn evaluate(&self, state: &GameState) -> i32 {
+        let (amplitude, seed) = self.noise;
+        let eval = evaluate_with(state, &self.params);
+        if amplitude == 0 {
+            return eval;
+        }
+        eval + eval_noise(state.hash(), seed, amplitude)
+    }
+
+    fn negamax(
+        &mut self,
+        state: &mut GameState,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        let in_check = state.is_in_check();
+        let depth = if in_check && self.config.check_extensions {
+            depth + 1
+        } else {
+            depth
+        };
+        if depth == 0 {
+            return self.quiescence(state, ply, alpha, beta);
+        }
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if ply >= MAX_PLY {
+            return self.evaluate(state);
+        }
+
+        let hash = state.hash();
+        self.path[ply] = hash;
+        // checked before the table, whose scores do not know the way here
+        if ply > 0 && self.is_repetition(hash, ply, state.halfmove_clock) {
+            return 0;
+        }
+        let mut hash_move = None;
+        if let Some(entry) = self.tt.probe(hash, ply) {
+            hash_move = entry.best_move;
+            if ply > 0 && entry.depth as usize >= depth {
+                let cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => entry.score >= beta,
+                    Bound::Upper => entry.score <= alpha,
+                };
+                if cutoff {
+                    return entry.score;
+                }
+            }
+        }
+
+        let previous = ply.checked_sub(1).and_then(|previous_ply| self.played[previous_ply]);
+        if ply > 0 && state.is_50_move_rule() && !state.is_checkmate() {
+            return 0;
+        }
+
+        let mut child_pv = Vec::new();
+        let pv_node = beta - alpha > 1;
+        if !pv_node && !in_check && ply > 0 {
+            let eval = self.evaluate(state);
+
+            let margin = 100 * depth as i32;
+            if self.config.reverse_futility_pruning
+                && depth <= 6
+                && eval - margin >= beta
+                && !is_mate_score(beta)
+            {
+                return eval - margin;
+            }
+
+            // a null move after a null move would just search the same position again
+            if self.config.null_move_pruning
+                && depth >= 3
+                && eval >= beta
+                && previous.is_some()
+                && has_non_pawn_material(state, state.turn)
+            {
+                let reduction = 2 + depth / 6;
+                self.played[ply] = None;
+                let undo = state.make_null_move();
+                let score = -self.negamax(
+                    state,
+                    depth.saturating_sub(1 + reduction),
+                    ply + 1,
+                    -beta,
+                    -beta + 1,
+                    &mut child_pv,
+                );
+                state.unmake_null_move(undo);
+                if self.aborted {
+                    return 0;
+                }
+                if score >= beta {
+                    // mates found after passing are not proven, so they are not returned
+                    return if is_mate_score(score) { beta } else { score };
+                }
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut moves_searched = 0;
+        let mut quiets_tried = Vec::new();
+        let mut picker = MovePicker::new(state, &self.ordering, hash_move, ply, previous);
+        while let Some(m) = picker.next(state, &self.ordering) {
+            if ply == 0 && self.excluded.contains(&m) {
+                continue;
+            }
+            self.played[ply] = Some(m);
+            let undo = state.make_move(m);
+            let reduce = self.config.late_move_reductions
+                && depth >= 3
+                && moves_searched >= 3
+                && m.is_quiet()
+                && !in_check
+                && !state.is_in_check();
+            let mut score = alpha + 1;
+            if reduce {
+                let reduction = if moves_searched >= 8 { 2 } else { 1 };
+                let reduced_depth = depth - 1 - reduction;
+                score = -self.negamax(state, reduced_depth, ply + 1, -alpha - 1, -alpha, &mut child_pv);
+            }
+            // search at full depth if not reduced, or if the reduced search beat alpha
+            if score > alpha && !self.aborted {
+                score = -self.negamax(state, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            }
+            state.unmake_move(m, undo);
+            moves_searched += 1;
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(m);
+                if score > alpha {
+                    alpha = score;
+                    pv.clear();
+                    pv.push(m);
+                    pv.extend_from_slice(&child_pv);
+                    if alpha >= beta {
+                        if m.is_quiet() {
+                            self.ordering
+                                .update_quiet_cutoff(state, m, previous, ply, depth, &quiets_tried);
+                        }
+                        break;
+                    }
+                }
+            }
+            if m.is_quiet() {
+                quiets_tried.push(m);
+            }
+        }
+
+        if best_move.is_none() {
+            return if in_check {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        let best_move = if bound == Bound::Upper { None } else { best_move };
+        // with moves excluded, the root score is not the score of the position
+        if ply > 0 || self.excluded.is_empty() {
+            self.tt.store(hash, best_move, best, depth, bound, ply);
+        }
+        best
+    }
+
+    /// Returns whether the position at `ply`, with `hash`, repeats one of the
+    /// current line or of the game since the last capture or pawn move. A
+    /// single repetition counts, as the side that aims for it can repeat again.
+    fn is_repetition(&self, hash: u64, ply: usize, halfmove_clock: usize) -> bool {
+        for back in 1..=halfmove_clock {
+            let earlier = match ply.checked_sub(back) {
+                // passing is not allowed in the game, so neither is repeating through it
+                Some(earlier_ply) if self.played[earlier_ply].is_none() => return false,
+                Some(earlier_ply) => self.path[earlier_ply],
+                None => match self.history.len().checked_sub(back - ply) {
+                    Some(index) => self.history[index],
+                    None => return false,
+                },
+            };
+            // only positions with the same side to move can be the same
+            if back % 2 == 0 && earlier == hash {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Searches captures and promotions until the position is quiet, so that
+    /// the static evaluation is never taken in the middle of an exchange. The
+    /// side to move may stand pat instead, unless it is in check, in which
+    /// case all evasions are searched.
+    fn quiescence(&mut self, state: &mut GameState, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+
+        if ply >= MAX_PLY {
+            return self.evaluate(state);
+        }
+        let in_check = state.is_in_check();
+        let mut best = -INFINITY;
+        let mut picker = if in_check {
+            MovePicker::new(state, &self.ordering, None, ply, None)
+        } else {
+            best = self.evaluate(state);
+            if best >= beta {
+                return best;
+            }
+            alpha = alpha.max(best);
+            MovePicker::new_tactical()
+        };
+
+        while let Some(m) = picker.next(state, &self.ordering) {
+            let undo = state.make_move(m);
+            let score = -self.quiescence(state, ply + 1, -beta, -alpha);
+            state.unmake_move(m, undo);
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+        if in_check && best == -INFINITY {
+            return -MATE_SCORE + ply as i32;
+        }
+        best
+    }
+
+    /// Follows the hash moves from the end of `pv`, to fill in the part of
+    /// the variation that was cut short by transposition table hits.
+    fn extend_pv_from_tt(&self, root: &GameState, pv: &mut Vec<Move>, depth: usize) {
+        let mut state = root.clone();
+        for m in pv.iter() {
+            state.apply_move(*m);
+        }
+        while pv.len() < depth {
+            let Some(m) = self
+                .tt
+                .probe(state.hash(), pv.len())
+                .and_then(|entry| entry.best_move)
+                .and_then(|m| m.resolve(&state.legal_moves()))
+            else {
+                break;
+            };
+            state.apply_move(m);
+            pv.push(m);
+        }
+    }
+
+    fn send_info(&mut self, depth: usize, multi_pv: usize, score: i32, pv: &[Move]) {
+        // lines searched only for a weakened search to choose from are not reported
+        if multi_pv > self.multi_pv {
+            return;
+        }
+        let Some(sink) = self.info_sink.as_mut() else {
+            return;
+        };
+        let time = self.start.elapsed();
+        sink.send(SearchInfo {
+            depth,
+            seldepth: self.seldepth,
+            multi_pv,
+            score,
+            nodes: self.nodes,
+            nps: (self.nodes as u128 * 1_000_000 / time.as_micros().max(1)) as u64,
+            hashfull: self.tt.hashfull(),
+            time,
+            pv: pv.to_vec(),
+        });
+    }
+
+    /// Searches `state` until one of `limits` is reached or the search is
+    /// stopped, and returns the result of the deepest completed iteration.
+    /// The result is deterministic when searching with a single thread at
+    /// full skill, or with a seed.
+    pub fn search(&mut self, state: &GameState, limits: SearchLimits) -> SearchResult {
+        if self.skill.is_full() {
+            self.noise = (0, 0);
+            self.lines = self.multi_pv;
+            return self.search_lines(state, limits);
+        }
+        self.noise = (self.skill.eval_noise(), self.rng.next_u64());
+        self.lines = self.multi_pv.max(SKILL_MULTI_PV);
+        let mut result = self.search_lines(state, self.skill.limits(limits));
+
+        if !result.lines.is_empty() {
+            let line = result.lines.remove(self.skill.pick(&result.lines, &mut self.rng));
+            result.best_move = line.pv.first().copied().or(result.best_move);
+            result.score = line.score;
+            result.pv = line.pv.clone();
+            result.lines.insert(0, line);
+            result.lines.truncate(self.multi_pv);
+            // report the lines again in the order picked, so that the last
+            // report of the first line agrees with the best move
+            for (index, line) in result.lines.iter().enumerate() {
+                self.send_info(line.depth, index + 1, line.score, &line.pv);
+            }
+        }
+        result
+    }
+
+    fn search_lines(&mut self, state: &GameState, limits: SearchLimits) -> SearchResult {
+        self.tt.new_search();
+        if self.threads == 1 {
+            return self.iterative_deepening(state, limits, 1);
+        }
+
+        // only the main thread watches the limits, the helpers run until it is done
+        let helper_stop = StopSignal::new();
+        let helper_limits = SearchLimits {
+            depth: limits.depth,
+            ..SearchLimits::infinite()
+        };
+        let mut helpers: Vec<Searcher> = (1..self.threads)
+            .map(|_| {
+                let mut helper = Searcher::with_tt(self.params.clone(), Arc::clone(&self.tt));
+                helper.config = self.config;
+                helper.history = self.history.clone();
+                helper.noise = self.noise;
+                helper.stop = helper_stop.clone();
+                helper
+            })
+            .collect();
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = helpers
+                .iter_mut()
+                .enumerate()
+                .map(|(index, helper)| {
+                    // half of the helpers skip the first iteration, so that
+                    // the threads spread out over different depths
+                    let first_depth = 1 + (index + 1) % 2;
+                    scope.spawn(move || helper.iterative_deepening(state, helper_limits, first_depth))
+                })
+                .collect();
+
+            let mut result = self.iterative_deepening(state, limits, 1);
+            helper_stop.stop();
+            for handle in handles {
+                result.nodes += handle.join().unwrap().nodes;
+            }
+            result
+        })
+    }
+
+    fn iterative_deepening(
+        &mut self,
+        state: &GameState,
+        limits: SearchLimits,
+        first_depth: usize,
+    ) -> SearchResult {
+        self.limits = limits;
+        self.start = Instant::now();
+        let mut time_manager = limits.time_control.map(|control| TimeManager::new(control, state));
+        if let Some(manager) = &time_manager {
+            let hard = manager.hard_limit();
+            self.limits.time = Some(limits.time.map_or(hard, |time| time.min(hard)));
+        }
+        self.nodes = 0;
+        self.aborted = false;
+        self.ordering.new_search();
+
+        let mut root = state.clone();
+        let moves = root.legal_moves();
+        let mut result = SearchResult {
+            best_move: moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+        };
+        if moves.is_empty() {
+            result.score = if root.is_in_check() { -MATE_SCORE } else { 0 };
+            return result;
+        }
+
+        // a depth 0 iteration would only run the quiescence search, which plays no move
+        let max_depth = limits.depth.unwrap_or(MAX_PLY).clamp(1, MAX_PLY);
+        let multi_pv = self.lines.min(moves.len());
+        let mut pv = Vec::new();
+        'deepening: for depth in first_depth.clamp(1, max_depth)..=max_depth {
+            let mut lines = Vec::with_capacity(multi_pv);
+            self.excluded.clear();
+            self.seldepth = 0;
+            for index in 0..multi_pv {
+                let previous_score = result.lines.get(index).map(|line| line.score);
+                let mut delta = 50;
+                let (mut alpha, mut beta) = match previous_score {
+                    Some(score) if self.config.aspiration_windows && depth >= 4 && !is_mate_score(score) => {
+                        (score - delta, score + delta)
+                    }
+                    _ => (-INFINITY, INFINITY),
+                };
+                // widen the window on the side the score fell out of, until it fits
+                let score = loop {
+                    let score = self.negamax(&mut root, depth, 0, alpha, beta, &mut pv);
+                    if self.aborted || (alpha < score && score < beta) {
+                        break score;
+                    }
+                    delta *= 2;
+                    if score <= alpha {
+                        alpha = (score - delta).max(-INFINITY);
+                    } else {
+                        beta = (score + delta).min(INFINITY);
+                    }
+                };
+                if self.aborted {
+                    break 'deepening;
+                }
+                self.extend_pv_from_tt(&root, &mut pv, depth);
+                // without a move, there is nothing to exclude for the next line
+                let Some(&first) = pv.first() else {
+                    break;
+                };
+                self.excluded.push(first);
+                self.send_info(depth, index + 1, score, &pv);
+                lines.push(PvLine {
+                    depth,
+                    score,
+                    pv: pv.clone(),
+                    san: line_to_san(&root, &pv),
+                });
+            }
+            // later lines can come out better than earlier ones through the table
+            lines.sort_by_key(|line| -line.score);
+            let Some(best) = lines.first() else {
+                continue;
+            };
+            result.best_move = best.pv.first().copied();
+            result.score = best.score;
+            result.depth = depth;
+            result.pv = best.pv.clone();
+            result.lines = lines;
+
+            // a full-width search finds the shortest mate, deeper ones cannot improve on it
+            if result.lines.iter().all(|line| is_mate_score(line.score)) {
+                break;
+            }
+            if let (Some(manager), Some(&best_move)) = (&mut time_manager, result.pv.first()) {
+                manager.update(best_move, result.score);
+                if manager.should_stop() {
+                    break;
+                }
+            }
+        }
+        self.excluded.clear();
+        result.nodes = self.nodes;
+        result
+    }
+}
+
+/// A `Player` that picks its moves with a `Searcher`, which it keeps from
+/// move to move, and clears at the start of each game. It follows the game
+/// to avoid or seek repetitions. In clocked games it also budgets its time
+/// from the clock, on top of its `limits`.
+pub struct EnginePlayer {
+    pub limits: SearchLimits,
+    pub params: EvalParams,
+    pub config: SearchConfig,
+    pub skill: Skill,
+    searcher: Searcher,
+    /// The clocks of White and Black for the next move, if the game is clocked.
+    clock: Option<(TimeControl, TimeControl)>,
+    /// The score of the last move played, for `Player::last_score`.
+    score: Option<i32>,
+    /// The current position of the game followed, and the hashes of the
+    /// positions before it.
+    game: Option<(GameState, Vec<u64>)>,
+}
+
+impl Default for EnginePlayer {
+    fn default() -> Self {
+        Self::new(SearchLimits::default())
+    }
+}
+
+impl EnginePlayer {
+    pub fn new(limits: SearchLimits) -> Self {
+        Self {
+            limits,
+            params: EvalParams::default(),
+            config: SearchConfig::default(),
+            skill: Skill::full(),
+            searcher: Searcher::new(),
+            clock: None,
+            score: None,
+            game: None,
+        }
+    }
+    pub fn with_skill(mut self, skill: Skill) -> Self {
+        self.skill = skill;
+        self
+    }
+    /// Seeds the random choices of a weakened player, see `Searcher::set_seed`.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.searcher.set_seed(seed);
+        self
+    }
+}
+
+impl Player for EnginePlayer {
+    /// Resigns only if there is no legal move to play.
+    fn get_move(&mut self, state: &GameState) -> PlayerAction {
+        if self.searcher.params != self.params {
+            self.searcher.params = self.params.clone();
+            self.searcher.tt.clear();
+        }
+        self.searcher.set_config(self.config);
+        self.searcher.set_skill(self.skill);
+        let mut limits = self.limits;
+        if let Some((white, black)) = self.clock.take() {
+            limits.time_control = Some(match state.turn {
+                PieceColor::White => white,
+                PieceColor::Black => black,
+            });
+        }
+        // without a game to follow, or if it went astray, start one from here
+        let mut history = match self.game.take() {
+            Some((current, history)) if current.hash() == state.hash() => history,
+            _ => Vec::new(),
+        };
+        self.searcher.set_history(history.clone());
+        let result = self.searcher.search(state, limits);
+        self.score = result.best_move.map(|_| result.score);
+        if let Some(m) = result.best_move {
+            let mut current = state.clone();
+            history.push(current.hash());
+            current.apply_move(m);
+            self.game = Some((current, history));
+        }
+        match result.best_move {
+            Some(m) => PlayerAction::Move(m),
+            None => PlayerAction::Resign,
+        }
+    }
+    fn on_game_start(&mut self, _color: PieceColor, start: &GameState) {
+        self.searcher.new_game();
+        self.game = Some((start.clone(), Vec::new()));
+    }
+    fn on_opponent_move(&mut self, m: Move) {
+        if let Some((current, history)) = &mut self.game {
+            history.push(current.hash());
+            current.apply_move(m);
+        }
+    }
+    fn on_clock(&mut self, white: TimeControl, black: TimeControl) {
+        self.clock = Some((white, black));
+    }
+    fn last_score(&self) -> Option<i32> {
+        self.score
+    }
+    fn stop_signal(&self) -> Option<StopSignal> {
+        Some(self.searcher.stop_signal())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{notation::parse_uci, position::Position};
+    use test_case::test_case;
+
+    #[test]
+    fn test_mate_distance() {
+        assert_eq!(mate_distance(MATE_SCORE - 1), Some(1));
+        assert_eq!(mate_distance(MATE_SCORE - 3), Some(2));
+        assert_eq!(mate_distance(-MATE_SCORE + 2), Some(-1));
+        assert_eq!(mate_distance(150), None);
+    }
+
+    #[test_case(1, 1; "single thread")]
+    #[test_case(3, 1; "multiple threads")]
+    #[test_case(1, 3; "multi pv")]
+    fn test_search_depth_zero_searches_one_ply(threads: usize, multi_pv: usize) {
+        let state = GameState::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_threads(threads);
+        searcher.set_multi_pv(multi_pv);
+        let result = searcher.search(&state, SearchLimits::depth(0));
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.best_move, parse_uci(&state, "a1a8"));
+    }
+
+    #[test]
+    fn test_search_scores_repetition_as_draw() {
+        // a queen down, Black can only hope to go back to where it came from
+        let state = GameState::from_fen("7k/8/8/8/8/8/2Q5/K7 b - - 10 40").unwrap();
+        let mut searcher = Searcher::new();
+        let result = searcher.search(&state, SearchLimits::depth(3));
+        assert!(result.score < -500, "{}", result.score);
+
+        let before = GameState::from_fen("6k1/8/8/8/8/8/2Q5/K7 w - - 9 40").unwrap();
+        searcher.new_game();
+        searcher.set_history(vec![before.hash()]);
+        let result = searcher.search(&state, SearchLimits::depth(3));
+        assert_eq!(result.best_move, parse_uci(&state, "h8g8"));
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn test_search_finds_mate_in_one() {
+        let state = GameState::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = Searcher::new().search(&state, SearchLimits::depth(3));
+
+        assert_eq!(
+            result.best_move,
+            Some(Move::Normal {
+                from: Position::try_from("a1").unwrap(),
+                to: Position::try_from("a8").unwrap()
+            })
+        );
+        assert_eq!(result.score, MATE_SCORE - 1);
+        // the mate is seen by the quiescence search after the first ply
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn test_search_finds_mate_in_two() {
+        let state = GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let result = Searcher::new().search(&state, SearchLimits::depth(4));
+
+        assert_eq!(mate_distance(result.score), Some(2));
+        assert_eq!(result.pv.len(), 3);
+    }
+
+    #[test]
+    fn test_search_wins_material() {
+        let state = GameState::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let result = Searcher::new().search(&state, SearchLimits::depth(2));
+
+        assert_eq!(
+            result.best_move.unwrap().to(),
+            Position::try_from("d5").unwrap()
+        );
+        assert!(result.score > 300);
+    }
+
+    #[test]
+    fn test_search_checkmated_and_stalemated_roots() {
+        let mated =
+            GameState::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        let result = Searcher::new().search(&mated, SearchLimits::depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE_SCORE);
+
+        let stalemate = GameState::from_fen("k7/8/1Q6/8/8/8/8/7K b - - 0 1").unwrap();
+        let result = Searcher::new().search(&stalemate, SearchLimits::depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn test_search_avoids_horizon_effect() {
+        // at depth 1 the pawn looks free unless the recapture is searched
+        let state = GameState::from_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let result = Searcher::new().search(&state, SearchLimits::depth(1));
+
+        assert_ne!(result.best_move.unwrap().to(), Position::try_from("d5").unwrap());
+        assert!(result.score > 500);
+    }
+
+    #[test]
+    fn test_quiescence_resolves_exchange() {
+        // white is a pawn up after Rxd5 Rxd5 Rxd5, but not after Rxd5 with two black rooks
+        let winning = GameState::from_fen("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+        let losing = GameState::from_fen("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+
+        let result = searcher.search(&winning, SearchLimits::depth(1));
+        assert_eq!(result.best_move.unwrap().to(), Position::try_from("d5").unwrap());
+        let result = searcher.search(&losing, SearchLimits::depth(1));
+        assert_ne!(result.best_move.unwrap().to(), Position::try_from("d5").unwrap());
+    }
+
+    #[test]
+    fn test_search_respects_node_limit() {
+        let result = Searcher::new().search(&GameState::default(), SearchLimits::nodes(500));
+        assert!(result.nodes <= 500);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_respects_time_limit() {
+        let start = Instant::now();
+        let result = Searcher::new().search(
+            &GameState::default(),
+            SearchLimits::time(Duration::from_millis(50)),
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_stops_from_other_thread() {
+        let mut searcher = Searcher::new();
+        let stop = searcher.stop_signal();
+        let handle =
+            thread::spawn(move || searcher.search(&GameState::default(), SearchLimits::infinite()));
+
+        thread::sleep(Duration::from_millis(100));
+        stop.stop();
+        let result = handle.join().unwrap();
+        assert!(result.best_move.is_some());
+        assert!(result.depth >= 1);
+    }
+
+    #[test]
+    fn test_search_reuses_transposition_table() {
+        let state = GameState::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let mut searcher = Searcher::new();
+        let first = searcher.search(&state, SearchLimits::depth(3));
+        let second = searcher.search(&state, SearchLimits::depth(3));
+
+        assert!(second.nodes < first.nodes);
+        assert_eq!(second.best_move, first.best_move);
+        assert_eq!(second.pv.len(), 3);
+    }
+
+    #[test]
+    fn test_search_with_shared_transposition_table() {
+        let state = GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let tt = Arc::new(TranspositionTable::new(1));
+        let mut first = Searcher::new();
+        let mut second = Searcher::new();
+        first.set_tt(Arc::clone(&tt));
+        second.set_tt(Arc::clone(&tt));
+
+        first.search(&state, SearchLimits::depth(4));
+        assert!(tt.probe(state.hash(), 0).is_some());
+        let result = second.search(&state, SearchLimits::depth(4));
+        assert_eq!(mate_distance(result.score), Some(2));
+    }
+
+    #[test_case(SearchConfig::plain(); "plain")]
+    #[test_case(SearchConfig::default(); "all")]
+    #[test_case(SearchConfig { null_move_pruning: true, ..SearchConfig::plain() }; "null move")]
+    #[test_case(SearchConfig { late_move_reductions: true, ..SearchConfig::plain() }; "late move reductions")]
+    #[test_case(SearchConfig { reverse_futility_pruning: true, ..SearchConfig::plain() }; "reverse futility")]
+    #[test_case(SearchConfig { aspiration_windows: true, ..SearchConfig::plain() }; "aspiration windows")]
+    fn test_search_config_finds_mate(config: SearchConfig) {
+        let state = GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_config(config);
+        let result = searcher.search(&state, SearchLimits::depth(5));
+        assert_eq!(mate_distance(result.score), Some(2));
+    }
+
+    #[test]
+    fn test_selective_search_uses_fewer_nodes() {
+        let state = GameState::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let mut plain = Searcher::new();
+        plain.set_config(SearchConfig::plain());
+        let plain = plain.search(&state, SearchLimits::depth(4));
+        let selective = Searcher::new().search(&state, SearchLimits::depth(4));
+        assert!(selective.nodes < plain.nodes);
+    }
+
+    #[test]
+    fn test_search_check_extensions() {
+        // 1. Qd8+ Bxd8 2. Re8#, which plain search only sees at depth 3
+        let state = GameState::from_fen("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_config(SearchConfig::plain());
+        assert!(!is_mate_score(searcher.search(&state, SearchLimits::depth(2)).score));
+
+        searcher.set_config(SearchConfig { check_extensions: true, ..SearchConfig::plain() });
+        let result = searcher.search(&state, SearchLimits::depth(2));
+        assert_eq!(mate_distance(result.score), Some(2));
+        assert_eq!(result.best_move.unwrap().to(), Position::try_from("d8").unwrap());
+    }
+
+    #[test]
+    fn test_search_single_thread_is_deterministic() {
+        let state = GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let first = Searcher::new().search(&state, SearchLimits::depth(3));
+        let second = Searcher::new().search(&state, SearchLimits::depth(3));
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_search_multiple_threads() {
+        let state = GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_threads(4);
+        let result = searcher.search(&state, SearchLimits::depth(5));
+
+        assert_eq!(searcher.threads(), 4);
+        assert_eq!(mate_distance(result.score), Some(2));
+        assert!(state.legal_moves().contains(&result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn test_search_multiple_threads_count_helper_nodes() {
+        let state = GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let tt = Arc::new(TranspositionTable::new(1));
+        let mut searcher = Searcher::with_tt(EvalParams::default(), Arc::clone(&tt));
+        searcher.set_threads(3);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        searcher.set_info_sink(sender);
+        let result = searcher.search(&state, SearchLimits::depth(5));
+
+        assert!(Arc::ptr_eq(searcher.tt(), &tt));
+        assert_eq!(result.depth, 5);
+        assert!(state.legal_moves().contains(&result.best_move.unwrap()));
+        // the reports only count the main thread, the result all of them
+        let main_nodes = receiver.try_iter().last().unwrap().nodes;
+        assert!(result.nodes > main_nodes, "{} <= {}", result.nodes, main_nodes);
+    }
+
+    #[test]
+    fn test_search_multiple_threads_stop_together() {
+        let mut searcher = Searcher::new();
+        searcher.set_threads(3);
+        let start = Instant::now();
+        let result = searcher.search(&GameState::default(), SearchLimits::time(Duration::from_millis(100)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+
+        let stop = searcher.stop_signal();
+        let handle = thread::spawn(move || searcher.search(&GameState::default(), SearchLimits::infinite()));
+        thread::sleep(Duration::from_millis(100));
+        stop.stop();
+        assert!(handle.join().unwrap().best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_multi_pv() {
+        // only Qxf7# mates, the other captures of the f7 pawn just win it
+        let state = GameState::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(3);
+        let result = searcher.search(&state, SearchLimits::depth(4));
+
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].san, "4. Qxf7#");
+        assert_eq!(result.lines[0].pv, result.pv);
+        assert_eq!(result.lines[0].score, result.score);
+        assert_eq!(mate_distance(result.score), Some(1));
+        for pair in result.lines.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+            assert_ne!(pair[0].pv[0], pair[1].pv[0]);
+        }
+        for line in &result.lines {
+            assert_eq!(line.depth, result.depth);
+            assert!(line.san.starts_with("4. "));
+            assert!(state.legal_moves().contains(&line.pv[0]));
+        }
+    }
+
+    #[test]
+    fn test_search_multi_pv_more_lines_than_moves() {
+        let state = GameState::from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(10);
+        let result = searcher.search(&state, SearchLimits::depth(3));
+        assert_eq!(result.lines.len(), state.legal_moves().len());
+    }
+
+    #[test]
+    fn test_search_single_pv_line() {
+        let result = Searcher::new().search(&GameState::default(), SearchLimits::depth(3));
+        assert_eq!(result.lines.len(), 1);
+        assert_eq!(result.lines[0].pv, result.pv);
+        assert!(result.lines[0].san.starts_with("1. "));
+    }
+
+    #[test]
+    fn test_search_info_callback() {
+        let infos = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut searcher = Searcher::new();
+        let sink = Arc::clone(&infos);
+        searcher.set_info_sink(move |info| sink.lock().unwrap().push(info));
+        let result = searcher.search(&GameState::default(), SearchLimits::depth(4));
+
+        let infos = infos.lock().unwrap();
+        assert_eq!(infos.iter().map(|info| info.depth).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        for info in infos.iter() {
+            assert_eq!(info.multi_pv, 1);
+            assert!(info.seldepth >= info.depth);
+            assert!(info.nodes > 0);
+        }
+        let last = infos.last().unwrap();
+        assert_eq!(last.pv, result.pv);
+        assert_eq!(last.score, result.score);
+        assert_eq!(last.nodes, result.nodes);
+        assert!(last.hashfull <= 1000);
+    }
+
+    #[test]
+    fn test_search_info_channel() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut searcher = Searcher::new();
+        searcher.set_multi_pv(2);
+        searcher.set_info_sink(sender);
+        searcher.search(&GameState::default(), SearchLimits::depth(2));
+
+        let infos: Vec<SearchInfo> = receiver.try_iter().collect();
+        let lines: Vec<_> = infos.iter().map(|info| (info.depth, info.multi_pv)).collect();
+        assert_eq!(lines, vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+
+        // a dropped receiver does not disturb the search
+        drop(receiver);
+        assert!(searcher.search(&GameState::default(), SearchLimits::depth(2)).best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_with_time_control() {
+        let control = TimeControl {
+            remaining: Duration::from_millis(3000),
+            ..TimeControl::default()
+        };
+        let start = Instant::now();
+        let result = Searcher::new().search(&GameState::default(), SearchLimits::time_control(control));
+        // the hard limit is three times the 100ms share of the remaining time
+        assert!(start.elapsed() < Duration::from_millis(1000));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_search_forced_move_stops_early() {
+        let state = GameState::from_fen("k7/8/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        let control = TimeControl {
+            remaining: Duration::from_secs(600),
+            ..TimeControl::default()
+        };
+        let result = Searcher::new().search(&state, SearchLimits::time_control(control));
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.best_move, state.legal_moves().first().copied());
+    }
+
+    #[test]
+    fn test_engine_player_plays_legal_move() {
+        let state = GameState::default();
+        let mut player = EnginePlayer::new(SearchLimits::depth(2));
+        let m = player.get_move(&state).played_move().unwrap();
+        assert!(state.legal_moves().contains(&m));
+
+        let checkmated = GameState::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(player.get_move(&checkmated), PlayerAction::Resign);
+        assert_eq!(player.last_score(), None);
+    }
+
+    #[test]
+    fn test_engine_player_reports_score() {
+        let mut player = EnginePlayer::new(SearchLimits::depth(2));
+        let queen_down = GameState::from_fen("3qk3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        player.get_move(&queen_down);
+        assert!(player.last_score().unwrap() < -500, "{:?}", player.last_score());
+        let mate_in_one = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        player.get_move(&mate_in_one);
+        assert_eq!(player.last_score(), Some(MATE_SCORE - 1));
+    }
+
+    #[test]
+    fn test_engine_player_keeps_table_within_game() {
+        let state = GameState::default();
+        let mut player = EnginePlayer::new(SearchLimits::depth(2));
+        player.on_game_start(PieceColor::White, &state);
+        player.get_move(&state);
+        assert!(player.searcher.tt().probe(state.hash(), 0).is_some());
+        player.on_game_start(PieceColor::Black, &state);
+        assert!(player.searcher.tt().probe(state.hash(), 0).is_none());
+    }
+
+    #[test]
+    fn test_engine_player_follows_game_to_repeat() {
+        let start = GameState::from_fen("6k1/8/8/8/8/8/2Q5/K7 w - - 10 40").unwrap();
+        let mut player = EnginePlayer::new(SearchLimits::depth(3));
+        player.on_game_start(PieceColor::Black, &start);
+        let mut state = start.clone();
+        for uci in ["c2c3", "c3c2"] {
+            let m = parse_uci(&state, uci).unwrap();
+            state.apply_move(m);
+            player.on_opponent_move(m);
+            let reply = player.get_move(&state).played_move().unwrap();
+            state.apply_move(reply);
+        }
+        assert_eq!(state.hash(), start.hash());
+        assert_eq!(player.last_score(), Some(0));
+    }
+
+    #[test]
+    fn test_engine_player_uses_clock() {
+        let state = GameState::default();
+        let mut player = EnginePlayer::new(SearchLimits::infinite());
+        let clock = TimeControl {
+            remaining: Duration::from_millis(300),
+            ..TimeControl::default()
+        };
+        player.on_clock(clock, TimeControl::default());
+        let start = Instant::now();
+        let m = player.get_move(&state).played_move().unwrap();
+        assert!(state.legal_moves().contains(&m));
+        assert!(start.elapsed() < Duration::from_millis(300), "{:?}", start.elapsed());
+        // the clock only holds for the move it was given for
+        assert!(player.clock.is_none());
+    }
+
+    #[test]
+    fn test_search_with_skill() {
+        let state = GameState::default();
+        let search = |seed| {
+            let mut searcher = Searcher::new();
+            searcher.set_skill(Skill::new(2));
+            searcher.set_seed(seed);
+            searcher.search(&state, SearchLimits::depth(6))
+        };
+        let result = search(1);
+        assert_eq!(result, search(1));
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.lines.len(), 1);
+        assert_eq!(Some(result.lines[0].pv[0]), result.best_move);
+        assert_eq!(result.score, result.lines[0].score);
+
+        let moves: Vec<_> = (0..20).map(|seed| search(seed).best_move).collect();
+        assert!(moves.iter().any(|m| *m != moves[0]));
+    }
+
+    #[test]
+    fn test_search_with_skill_keeps_multi_pv() {
+        let state = GameState::default();
+        let mut searcher = Searcher::new();
+        searcher.set_skill(Skill::new(5));
+        searcher.set_multi_pv(6);
+        let result = searcher.search(&state, SearchLimits::depth(2));
+        assert_eq!(result.lines.len(), 6);
+        assert_eq!(searcher.multi_pv(), 6);
+    }
+
+    #[test]
+    fn test_search_with_skill_reports_requested_lines() {
+        let state = GameState::default();
+        let mut searcher = Searcher::new();
+        searcher.set_skill(Skill::new(3));
+        let (sender, receiver) = std::sync::mpsc::channel();
+        searcher.set_info_sink(sender);
+        for seed in 0..5 {
+            searcher.set_seed(seed);
+            let result = searcher.search(&state, SearchLimits::depth(4));
+            let infos: Vec<SearchInfo> = receiver.try_iter().collect();
+            assert!(infos.iter().all(|info| info.multi_pv == 1));
+            assert_eq!(infos.last().unwrap().pv[0], result.best_move.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_changing_skill_clears_table() {
+        let state = GameState::default();
+        let mut searcher = Searcher::new();
+        searcher.set_skill(Skill::new(0));
+        searcher.search(&state, SearchLimits::depth(2));
+        assert!(searcher.tt().probe(state.hash(), 0).is_some());
+        searcher.set_skill(Skill::new(0));
+        assert!(searcher.tt().probe(state.hash(), 0).is_some());
+        searcher.set_skill(Skill::full());
+        assert!(searcher.tt().probe(state.hash(), 0).is_none());
+    }
+
+    #[test]
+    fn test_weak_engine_player_still_mates() {
+        let state = GameState::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let mate = parse_uci(&state, "a1a8").unwrap();
+        for seed in 0..5 {
+            let mut player = EnginePlayer::new(SearchLimits::depth(3)).with_skill(Skill::new(10)).with_seed(seed);
+            assert_eq!(player.get_move(&state), PlayerAction::Move(mate));
+        }
+    }
+}
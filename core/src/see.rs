@@ -0,0 +1,104 @@
+use crate::{
+    attacks::attackers_to,
+    board::Board,
+    moves::Move,
+    piece::PieceKind,
+};
+
+/// Piece values used by `see`, indexed by `PieceKind as usize`.
+pub const SEE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20000];
+
+#[inline]
+fn see_value(kind: PieceKind) -> i32 {
+    SEE_VALUES[kind as usize]
+}
+
+/// Static exchange evaluation: the material the side making `m` wins or
+/// loses once all captures on its target square have been played out, with
+/// each side capturing with its least valuable piece first and free to stop
+/// when continuing would lose material. Pieces lined up behind each other
+/// (x-rays) join the exchange once the piece in front has captured. Pins
+/// are not taken into account.
+pub fn see(board: &Board, m: Move) -> i32 {
+    let Some(mover) = board.get(m.from()) else {
+        return 0;
+    };
+    let target = m.to();
+    let mut board = *board;
+
+    let mut gains = vec![m.captured_kind().map_or(0, see_value)];
+    let mut on_target = mover;
+    if let Some(promoted) = m.promotion() {
+        gains[0] += see_value(promoted.kind()) - see_value(PieceKind::Pawn);
+        on_target = promoted;
+    }
+    if let Move::EnPassant { captured, .. } = m {
+        board.set(captured, None);
+    }
+    board.set(m.from(), None);
+    board.set(target, Some(on_target));
+
+    let mut side = mover.color().opposite();
+    let least_valuable_attacker = |board: &Board, side| {
+        attackers_to(board, target, side)
+            .into_iter()
+            .min_by_key(|position| see_value(board.get(*position).unwrap().kind()))
+    };
+    while let Some(attacker) = least_valuable_attacker(&board, side) {
+        let piece = board.get(attacker).unwrap();
+        board.set(attacker, None);
+        // the king may only capture if nothing can take it back
+        let defended = !attackers_to(&board, target, side.opposite()).is_empty();
+        if piece.kind() == PieceKind::King && defended {
+            break;
+        }
+        gains.push(see_value(on_target.kind()) - gains[gains.len() - 1]);
+        board.set(target, Some(piece));
+        on_target = piece;
+        side = side.opposite();
+    }
+
+    // every side may decline to recapture, so fold back from the last capture
+    while gains.len() > 1 {
+        let last = gains.pop().unwrap();
+        let previous = gains.last_mut().unwrap();
+        *previous = -i32::max(-*previous, last);
+    }
+    gains[0]
+}
+
+/// Returns whether `see` of `m` is at least `threshold`.
+pub fn see_ge(board: &Board, m: Move, threshold: i32) -> bool {
+    see(board, m) >= threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+    use crate::state::GameState;
+    use test_case::test_case;
+
+    #[test_case("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4", "d5", None, 100; "undefended pawn")]
+    #[test_case("4k3/2p5/3p4/8/4N3/8/8/4K3 w - - 0 1", "e4", "d6", None, -220; "knight takes defended pawn")]
+    #[test_case("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", "d1", "d5", None, -800; "queen takes defended pawn")]
+    #[test_case("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2", "d5", None, 100; "rook x-ray")]
+    #[test_case("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2", "d5", None, -400; "doubled defenders")]
+    #[test_case("3qk3/8/8/3p4/8/8/B7/3RK3 w - - 0 1", "d1", "d5", None, 100; "queen does not recapture rook")]
+    #[test_case("8/8/4k3/4p3/8/8/8/4RK2 w - - 0 1", "e1", "e5", None, -400; "king recaptures")]
+    #[test_case("8/8/4k3/4p3/8/8/1B6/4RK2 w - - 0 1", "e1", "e5", None, 100; "king cannot recapture")]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5", "d6", None, 100; "en passant")]
+    #[test_case("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7", "b8", Some(PieceKind::Queen), 800; "promotion")]
+    #[test_case("1rk5/P7/8/8/8/8/8/4K3 w - - 0 1", "a7", "b8", Some(PieceKind::Queen), 400; "defended promotion capture")]
+    #[test_case("4k3/8/8/3p4/8/8/8/2Q1K3 w - - 0 1", "c1", "c4", None, -900; "quiet move onto attacked square")]
+    #[test_case("4k3/8/8/8/8/8/8/2Q1K3 w - - 0 1", "c1", "c4", None, 0; "quiet move")]
+    fn test_see(fen: &str, from: &str, to: &str, promotion: Option<PieceKind>, expected: i32) {
+        let state = GameState::from_fen(fen).unwrap();
+        let m = state
+            .find_move(Position::try_from(from).unwrap(), Position::try_from(to).unwrap(), promotion)
+            .unwrap();
+        assert_eq!(see(&state.board, m), expected);
+        assert!(see_ge(&state.board, m, expected));
+        assert!(!see_ge(&state.board, m, expected + 1));
+    }
+}
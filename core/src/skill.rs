@@ -0,0 +1,179 @@
+use crate::{
+    rng::Rng,
+    search::{PvLine, SearchLimits},
+    zobrist::split_mix,
+};
+
+pub const MAX_SKILL_LEVEL: u8 = 20;
+/// The Elo range `Skill::from_elo` maps onto the skill levels. The numbers
+/// are rough estimates, not calibrated against any rating list.
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2600;
+/// How many lines a weakened search looks at, to pick its move from.
+pub const SKILL_MULTI_PV: usize = 4;
+
+/// How well the engine plays, from 0 to `MAX_SKILL_LEVEL`, which is full
+/// strength. Below that, the search is cut short, the evaluation is blurred
+/// by noise, and the move is picked among the best few lines, with worse
+/// lines more likely the lower the level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+impl Skill {
+    /// Levels above `MAX_SKILL_LEVEL` are taken as full strength.
+    pub fn new(level: u8) -> Self {
+        Self {
+            level: level.min(MAX_SKILL_LEVEL),
+        }
+    }
+    pub fn full() -> Self {
+        Self::new(MAX_SKILL_LEVEL)
+    }
+    /// The level playing at about `elo`, clamped to the levels below full strength.
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let step = (MAX_ELO - MIN_ELO) / MAX_SKILL_LEVEL as u32;
+        let level = (elo - MIN_ELO + step / 2) / step;
+        Self::new((level as u8).min(MAX_SKILL_LEVEL - 1))
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+    /// The rating this level plays at, roughly.
+    pub fn elo(&self) -> u32 {
+        MIN_ELO + (MAX_ELO - MIN_ELO) * self.level as u32 / MAX_SKILL_LEVEL as u32
+    }
+    pub fn is_full(&self) -> bool {
+        self.level == MAX_SKILL_LEVEL
+    }
+
+    /// Tightens `limits` to the depth and nodes this level may search.
+    pub fn limits(&self, limits: SearchLimits) -> SearchLimits {
+        if self.is_full() {
+            return limits;
+        }
+        let depth = 1 + self.level as usize / 2;
+        let nodes = 4000 << (self.level / 2);
+        SearchLimits {
+            depth: Some(limits.depth.map_or(depth, |limit| limit.min(depth))),
+            nodes: Some(limits.nodes.map_or(nodes, |limit| limit.min(nodes))),
+            ..limits
+        }
+    }
+
+    /// The most the static evaluation is off by, in centipawns.
+    pub fn eval_noise(&self) -> i32 {
+        (MAX_SKILL_LEVEL - self.level) as i32 * 15
+    }
+
+    /// Picks one of `lines`, which must be sorted best first and not be
+    /// empty, and returns its index. Each line's score is raised by part of
+    /// how much worse it is than the best, plus a random amount up to a pawn,
+    /// and the highest wins: the lower the level, the more the scores even out.
+    pub fn pick(&self, lines: &[PvLine], rng: &mut Rng) -> usize {
+        if self.is_full() {
+            return 0;
+        }
+        let top = lines[0].score;
+        let spread = (top - lines[lines.len() - 1].score).min(100);
+        let weakness = 120 - 2 * self.level as i32;
+        let mut best = (0, i32::MIN);
+        for (index, line) in lines.iter().enumerate() {
+            let push = (weakness * (top - line.score) + spread * rng.below(weakness as usize) as i32) / 128;
+            if line.score + push > best.1 {
+                best = (index, line.score + push);
+            }
+        }
+        best.0
+    }
+}
+
+/// The noise added to the evaluation of the position with `hash`, at most
+/// `amplitude` either way. It only depends on the position and `seed`, so
+/// that the same position evaluates the same throughout a search.
+pub fn eval_noise(hash: u64, seed: u64, amplitude: i32) -> i32 {
+    if amplitude == 0 {
+        return 0;
+    }
+    let (_, random) = split_mix(hash ^ seed);
+    (random % (2 * amplitude as u64 + 1)) as i32 - amplitude
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn line(score: i32) -> PvLine {
+        PvLine {
+            depth: 1,
+            score,
+            pv: Vec::new(),
+            san: String::new(),
+        }
+    }
+
+    #[test_case(0, 0)]
+    #[test_case(800, 0)]
+    #[test_case(1700, 10)]
+    #[test_case(2555, 19)]
+    #[test_case(3500, 19)]
+    fn test_skill_from_elo(elo: u32, level: u8) {
+        assert_eq!(Skill::from_elo(elo).level(), level);
+    }
+
+    #[test]
+    fn test_skill_elo_round_trip() {
+        for level in 0..MAX_SKILL_LEVEL {
+            assert_eq!(Skill::from_elo(Skill::new(level).elo()).level(), level);
+        }
+        assert_eq!(Skill::new(50), Skill::full());
+    }
+
+    #[test]
+    fn test_skill_limits() {
+        assert_eq!(Skill::full().limits(SearchLimits::infinite()), SearchLimits::infinite());
+        let limits = Skill::new(0).limits(SearchLimits::depth(5));
+        assert_eq!(limits.depth, Some(1));
+        assert_eq!(limits.nodes, Some(4000));
+        let limits = Skill::new(19).limits(SearchLimits::depth(5));
+        assert_eq!(limits.depth, Some(5));
+    }
+
+    #[test]
+    fn test_eval_noise() {
+        assert_eq!(eval_noise(1, 2, 0), 0);
+        for hash in 0..100 {
+            let noise = eval_noise(hash, 7, 30);
+            assert!((-30..=30).contains(&noise));
+            assert_eq!(noise, eval_noise(hash, 7, 30));
+        }
+    }
+
+    #[test]
+    fn test_skill_pick() {
+        let lines = [line(100), line(60), line(-200), line(-900)];
+        let mut rng = Rng::new(1);
+        assert_eq!(Skill::full().pick(&lines, &mut rng), 0);
+
+        let mut picks = [[0; 4]; 2];
+        for (picks, level) in picks.iter_mut().zip([0, 15]) {
+            for _ in 0..1000 {
+                picks[Skill::new(level).pick(&lines, &mut rng)] += 1;
+            }
+        }
+        // weaker levels stray from the best line more often, but rarely blunder outright
+        assert!(picks[0][0] < picks[1][0]);
+        assert!(picks[0][1] > picks[1][1]);
+        assert!(picks[1][3] == 0);
+    }
+}
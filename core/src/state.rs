@@ -1,22 +1,107 @@
+use crate::attacks::is_square_attacked;
 use crate::board::Board;
-use crate::moves::{get_moves, prune_moves_into_check, Move};
+use crate::moves::{
+    get_moves, get_moves_for_square, make_castle_move, prune_moves_into_check, Move, MoveError,
+};
 use crate::piece::{Piece, PieceColor, PieceKind};
 use crate::position::Position;
+use crate::zobrist::zobrist_hash;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CastleSide {
     KingSide,
     QueenSide,
 }
 
+/// Castling rights, recorded as the file of the rook each right belongs to so
+/// that Chess960 positions can be represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CastlingRights {
+    rook_files: [Option<u8>; 4],
+}
+
+impl CastlingRights {
+    pub fn none() -> Self {
+        Self::default()
+    }
+    /// The standard rights: a- and h-file rooks for both colors.
+    pub fn all() -> Self {
+        Self::from(0b1111)
+    }
+    fn index(color: PieceColor, side: CastleSide) -> usize {
+        match (color, side) {
+            (PieceColor::White, CastleSide::KingSide) => 0,
+            (PieceColor::White, CastleSide::QueenSide) => 1,
+            (PieceColor::Black, CastleSide::KingSide) => 2,
+            (PieceColor::Black, CastleSide::QueenSide) => 3,
+        }
+    }
+    /// Returns the file of the rook `color` may castle with towards `side`.
+    pub fn get(&self, color: PieceColor, side: CastleSide) -> Option<u8> {
+        self.rook_files[Self::index(color, side)]
+    }
+    pub fn set(&mut self, color: PieceColor, side: CastleSide, rook_file: u8) {
+        if rook_file > 7 {
+            panic!("Invalid file: {}", rook_file)
+        }
+        self.rook_files[Self::index(color, side)] = Some(rook_file);
+    }
+    pub fn unset(&mut self, color: PieceColor, side: CastleSide) {
+        self.rook_files[Self::index(color, side)] = None;
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rook_files.iter().all(Option::is_none)
+    }
+}
+
+/// Converts the classic `KQkq` bit mask (`0b1000` is white king side, `0b0001`
+/// black queen side) into rights on the a- and h-file rooks.
+impl From<u8> for CastlingRights {
+    fn from(mask: u8) -> Self {
+        let mut result = Self::none();
+        if mask & 0b1000 != 0 {
+            result.set(PieceColor::White, CastleSide::KingSide, 7);
+        }
+        if mask & 0b0100 != 0 {
+            result.set(PieceColor::White, CastleSide::QueenSide, 0);
+        }
+        if mask & 0b0010 != 0 {
+            result.set(PieceColor::Black, CastleSide::KingSide, 7);
+        }
+        if mask & 0b0001 != 0 {
+            result.set(PieceColor::Black, CastleSide::QueenSide, 0);
+        }
+        result
+    }
+}
+
+/// The parts of a `GameState` a move cannot be reversed from, as returned
+/// by `GameState::make_move` and consumed by `GameState::unmake_move`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoInfo {
+    castling_rights: CastlingRights,
+    en_passant: Option<Position>,
+    halfmove_clock: usize,
+    fullmove_number: usize,
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct GameState {
     pub board: Board,
     pub turn: PieceColor,
-    pub castling_rights: u8,
+    pub castling_rights: CastlingRights,
+    pub chess960: bool,
     pub en_passant: Option<Position>,
     pub halfmove_clock: usize,
     pub fullmove_number: usize,
     pub captured_pieces: Vec<Piece>,
+    pub promoted_pieces: Vec<Piece>,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -63,15 +148,30 @@ fn fen_char_to_piece(c: char) -> Option<Piece> {
     }
 }
 
+fn piece_to_fen_char(piece: Piece) -> char {
+    let c = match piece.kind() {
+        PieceKind::Pawn => 'p',
+        PieceKind::Knight => 'n',
+        PieceKind::Bishop => 'b',
+        PieceKind::Rook => 'r',
+        PieceKind::Queen => 'q',
+        PieceKind::King => 'k',
+    };
+    match piece.color() {
+        PieceColor::White => c.to_ascii_uppercase(),
+        PieceColor::Black => c,
+    }
+}
+
 fn lex_fen_str(fen: &str) -> Result<Vec<FENToken>, FENParserError> {
     let mut result = Vec::new();
     let mut args = fen.split_whitespace();
-    let mut board_chars = args
+    let board_chars = args
         .next()
         .ok_or(FENParserError::NotEnoughArguments)?
         .chars();
 
-    while let Some(c) = board_chars.next() {
+    for c in board_chars {
         match c {
             '1'..='8' => {
                 let n = c.to_digit(10).unwrap() as u8;
@@ -96,11 +196,11 @@ fn lex_fen_str(fen: &str) -> Result<Vec<FENToken>, FENParserError> {
     let castling = args.next().ok_or(FENParserError::NotEnoughArguments)?;
 
     if castling != "-" {
-        let mut castling: Vec<FENToken> = castling.chars().map(|c| FENToken::Castle(c)).collect();
+        let mut castling: Vec<FENToken> = castling.chars().map(FENToken::Castle).collect();
         result.append(&mut castling);
     }
 
-    let mut en_passant = args.next().ok_or(FENParserError::NotEnoughArguments)?;
+    let en_passant = args.next().ok_or(FENParserError::NotEnoughArguments)?;
 
     if en_passant != "-" {
         if en_passant.len() != 2 {
@@ -137,17 +237,19 @@ impl GameState {
         Self {
             board: Board::empty(),
             turn: PieceColor::White,
-            castling_rights: 0b0000,
+            castling_rights: CastlingRights::none(),
+            chess960: false,
             en_passant: None,
             halfmove_clock: 0,
             fullmove_number: 1,
             captured_pieces: Vec::new(),
+            promoted_pieces: Vec::new(),
         }
     }
     pub fn new(
         board: Board,
         turn: PieceColor,
-        castling_rights: u8,
+        castling_rights: impl Into<CastlingRights>,
         en_passant: Option<Position>,
         halfmove_clock: usize,
         fullmove_number: usize,
@@ -155,19 +257,20 @@ impl GameState {
         Self {
             board,
             turn,
-            castling_rights,
+            castling_rights: castling_rights.into(),
+            chess960: false,
             en_passant,
             halfmove_clock,
-            fullmove_number,
+            // the FEN spec counts moves from 1
+            fullmove_number: fullmove_number.max(1),
             captured_pieces: Vec::new(),
+            promoted_pieces: Vec::new(),
         }
     }
     fn parse_fen_tokens(&mut self, tokens: Vec<FENToken>) -> Result<(), FENParserError> {
         let mut rank = 7;
         let mut file = 0;
 
-        let board_squares = self.board.squares_mut();
-
         for token in tokens {
             match token {
                 FENToken::Piece(c) => {
@@ -175,7 +278,7 @@ impl GameState {
                         return Err(FENParserError::InvalidFileCount(file));
                     }
                     let piece = fen_char_to_piece(c).ok_or(FENParserError::InvalidPiece(c))?;
-                    board_squares[(rank << 3 | file) as usize] = Some(piece);
+                    self.board.set(Position::new(rank, file), Some(piece));
                     file += 1;
                 }
                 FENToken::Empty(n) => {
@@ -203,13 +306,7 @@ impl GameState {
                         _ => return Err(FENParserError::InvalidTurn(c)),
                     };
                 }
-                FENToken::Castle(c) => match c {
-                    'K' => self.castling_rights |= 0b1000,
-                    'Q' => self.castling_rights |= 0b0100,
-                    'k' => self.castling_rights |= 0b0010,
-                    'q' => self.castling_rights |= 0b0001,
-                    _ => return Err(FENParserError::InvalidCastle(c)),
-                },
+                FENToken::Castle(c) => self.parse_fen_castle(c)?,
                 FENToken::EnPassant(f, r) => {
                     let pos = Position::try_from(format!("{}{}", f, r).as_str())
                         .map_err(|_| FENParserError::InvalidPosition(f, r))?;
@@ -219,48 +316,205 @@ impl GameState {
                     self.halfmove_clock = n;
                 }
                 FENToken::FullmoveNumber(n) => {
-                    self.fullmove_number = n;
+                    self.fullmove_number = n.max(1);
                 }
             }
         }
         Ok(())
     }
+    /// Parses a castling character of standard FEN (`KQkq`), X-FEN (`KQkq`
+    /// meaning the outermost rook, or a rook file) or Shredder-FEN (rook files).
+    pub(crate) fn parse_fen_castle(&mut self, c: char) -> Result<(), FENParserError> {
+        let color = if c.is_ascii_uppercase() {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        let rank = color.back_rank();
+        let king_file = self
+            .board
+            .find_king(color)
+            .filter(|king| king.rank() == rank)
+            .map_or(4, |king| king.file());
+        let is_rook = |file: &u8| {
+            self.board.get(Position::new(rank, *file)) == Some(Piece::new(PieceKind::Rook, color))
+        };
+
+        let (side, rook_file) = match c.to_ascii_lowercase() {
+            'k' => (
+                CastleSide::KingSide,
+                (king_file + 1..8).rev().find(is_rook).unwrap_or(7),
+            ),
+            'q' => (
+                CastleSide::QueenSide,
+                (0..king_file).find(is_rook).unwrap_or(0),
+            ),
+            file @ 'a'..='h' => {
+                let file = file as u8 - b'a';
+                self.chess960 = true;
+                if file > king_file {
+                    (CastleSide::KingSide, file)
+                } else {
+                    (CastleSide::QueenSide, file)
+                }
+            }
+            _ => return Err(FENParserError::InvalidCastle(c)),
+        };
+        if king_file != 4 || (rook_file != 0 && rook_file != 7) {
+            self.chess960 = true;
+        }
+        self.castling_rights.set(color, side, rook_file);
+        Ok(())
+    }
+    /// Parses standard FEN as well as the X-FEN and Shredder-FEN extensions
+    /// for Chess960. Positions that can only be Chess960 set `chess960`.
     pub fn from_fen(fen: &str) -> Result<Self, FENParserError> {
         let mut result = Self::empty();
         result.parse_fen_tokens(lex_fen_str(fen)?)?;
         Ok(result)
     }
-    pub fn default() -> Self {
-        Self::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
+    fn fen_board(&self) -> String {
+        let mut result = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for square in self.board.rank(rank) {
+                match square {
+                    Some(piece) => {
+                        if empty > 0 {
+                            result.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        result.push(piece_to_fen_char(*piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                result.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                result.push('/');
+            }
+        }
+        result
     }
-    pub fn can_castle(&self, color: PieceColor, side: CastleSide) -> bool {
-        let mask = match (color, side) {
-            (PieceColor::White, CastleSide::KingSide) => 0b1000,
-            (PieceColor::White, CastleSide::QueenSide) => 0b0100,
-            (PieceColor::Black, CastleSide::KingSide) => 0b0010,
-            (PieceColor::Black, CastleSide::QueenSide) => 0b0001,
+    fn fen_castling(&self, shredder: bool) -> String {
+        let mut result = String::new();
+        for color in [PieceColor::White, PieceColor::Black] {
+            for side in [CastleSide::KingSide, CastleSide::QueenSide] {
+                let Some(rook_file) = self.castling_rights.get(color, side) else {
+                    continue;
+                };
+                // X-FEN only names the file when another rook stands further out
+                let rank = color.back_rank();
+                let mut outer_files = match side {
+                    CastleSide::KingSide => rook_file + 1..8,
+                    CastleSide::QueenSide => 0..rook_file,
+                };
+                let is_outermost = !outer_files.any(|file| {
+                    self.board.get(Position::new(rank, file))
+                        == Some(Piece::new(PieceKind::Rook, color))
+                });
+
+                let c = if shredder || (self.chess960 && !is_outermost) {
+                    (b'a' + rook_file) as char
+                } else {
+                    match side {
+                        CastleSide::KingSide => 'k',
+                        CastleSide::QueenSide => 'q',
+                    }
+                };
+                result.push(match color {
+                    PieceColor::White => c.to_ascii_uppercase(),
+                    PieceColor::Black => c,
+                });
+            }
+        }
+        if result.is_empty() {
+            result.push('-');
+        }
+        result
+    }
+    fn fen_with_castling(&self, castling: String) -> String {
+        let turn = match self.turn {
+            PieceColor::White => 'w',
+            PieceColor::Black => 'b',
         };
-        self.castling_rights & mask != 0
+        let en_passant = self
+            .en_passant
+            .map_or("-".to_string(), |position| position.to_string());
+        format!(
+            "{} {} {} {} {} {}",
+            self.fen_board(),
+            turn,
+            castling,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+    /// Writes the position as FEN. Chess960 positions use X-FEN castling rights,
+    /// which are identical to standard FEN unless a right belongs to an inner rook.
+    pub fn to_fen(&self) -> String {
+        self.fen_with_castling(self.fen_castling(false))
+    }
+    /// Writes the position as Shredder-FEN, naming the rook file of every castling right.
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen_with_castling(self.fen_castling(true))
+    }
+    pub fn can_castle(&self, color: PieceColor, side: CastleSide) -> bool {
+        self.castling_rights.get(color, side).is_some()
     }
     pub fn unset_castle(&mut self, color: PieceColor, side: CastleSide) {
-        let mask = match (color, side) {
-            (PieceColor::White, CastleSide::KingSide) => 0b1000,
-            (PieceColor::White, CastleSide::QueenSide) => 0b0100,
-            (PieceColor::Black, CastleSide::KingSide) => 0b0010,
-            (PieceColor::Black, CastleSide::QueenSide) => 0b0001,
-        };
-        self.castling_rights &= !mask;
+        self.castling_rights.unset(color, side);
     }
     #[cfg(not(tarpaulin_include))]
     pub fn legal_moves(&self) -> Vec<Move> {
         let moves = get_moves(self, self.turn);
         prune_moves_into_check(moves, self)
     }
+    /// Returns the legal moves of the piece on `from`, including castling if it is the king.
+    pub fn legal_moves_from(&self, from: Position) -> Vec<Move> {
+        let mut moves = get_moves_for_square(self.board.get(from), from, self, self.turn);
+        if self.board.find_king(self.turn) == Some(from) {
+            for side in [CastleSide::KingSide, CastleSide::QueenSide] {
+                moves.extend(make_castle_move(self, self.turn, side));
+            }
+        }
+        prune_moves_into_check(moves, self)
+    }
+    /// Finds the legal move going from `from` to `to`, promoting to `promotion` if given.
+    /// Castling may be given either as the king's target square or, as is usual
+    /// for Chess960, as the king capturing its own rook.
+    pub fn find_move(
+        &self,
+        from: Position,
+        to: Position,
+        promotion: Option<PieceKind>,
+    ) -> Option<Move> {
+        self.legal_moves().into_iter().find(|m| {
+            let target_matches = match m {
+                Move::Castle { rook_from, .. } if self.chess960 => *rook_from == to,
+                Move::Castle { rook_from, .. } => *rook_from == to || m.to() == to,
+                _ => m.to() == to,
+            };
+            m.from() == from
+                && target_matches
+                && m.promotion().map(|piece| piece.kind()) == promotion
+        })
+    }
+    pub fn is_square_attacked(&self, position: Position, attacker: PieceColor) -> bool {
+        is_square_attacked(&self.board, position, attacker)
+    }
+    /// Returns whether the king of `color` is attacked, regardless of whose turn it is.
+    pub fn is_king_attacked(&self, color: PieceColor) -> bool {
+        match self.board.find_king(color) {
+            Some(king_pos) => self.is_square_attacked(king_pos, color.opposite()),
+            None => false,
+        }
+    }
     pub fn is_in_check(&self) -> bool {
-        let king_pos = self.board.find_king(self.turn).unwrap();
-        let enemy_color = self.turn.opposite();
-        let enemy_moves = get_moves(self, enemy_color);
-        enemy_moves.iter().any(|m| m.to() == king_pos)
+        self.is_king_attacked(self.turn)
     }
     #[cfg(not(tarpaulin_include))]
     pub fn is_checkmate(&self) -> bool {
@@ -274,14 +528,59 @@ impl GameState {
     pub fn is_50_move_rule(&self) -> bool {
         self.halfmove_clock >= 100
     }
+    /// Returns whether `color` lacks the material to ever checkmate, having
+    /// only its king, or its king and a single knight or bishop.
+    pub fn has_insufficient_material(&self, color: PieceColor) -> bool {
+        let mut minors = 0;
+        for piece in self.board.squares().iter().flatten().filter(|piece| piece.color() == color) {
+            match piece.kind() {
+                PieceKind::King => {}
+                PieceKind::Knight | PieceKind::Bishop => minors += 1,
+                _ => return false,
+            }
+        }
+        minors <= 1
+    }
+    /// Returns whether neither side can checkmate: there are no pawns, rooks
+    /// or queens, and either at most one minor piece or only bishops all on
+    /// squares of the same color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minors = 0;
+        let mut knights = false;
+        let mut bishop_square_colors = [false; 2];
+        for (index, square) in self.board.squares().iter().enumerate() {
+            let Some(piece) = square else {
+                continue;
+            };
+            let position = Position::from(index);
+            match piece.kind() {
+                PieceKind::King => {}
+                PieceKind::Knight => {
+                    minors += 1;
+                    knights = true;
+                }
+                PieceKind::Bishop => {
+                    minors += 1;
+                    bishop_square_colors[((position.rank() + position.file()) % 2) as usize] = true;
+                }
+                _ => return false,
+            }
+        }
+        let mixed_bishops = bishop_square_colors[0] && bishop_square_colors[1];
+        minors <= 1 || !(knights || mixed_bishops)
+    }
+    /// Returns the Zobrist hash of this position, see `zobrist_hash`.
+    pub fn hash(&self) -> u64 {
+        zobrist_hash(self)
+    }
 
     fn check_castle_rights_waived(&mut self, pos: Position, piece: Piece) {
         match piece.kind() {
-            PieceKind::Rook => {
-                if pos.file() == 0 {
-                    self.unset_castle(piece.color(), CastleSide::QueenSide);
-                } else if pos.file() == 7 {
-                    self.unset_castle(piece.color(), CastleSide::KingSide);
+            PieceKind::Rook if pos.rank() == piece.color().back_rank() => {
+                for side in [CastleSide::KingSide, CastleSide::QueenSide] {
+                    if self.castling_rights.get(piece.color(), side) == Some(pos.file()) {
+                        self.unset_castle(piece.color(), side);
+                    }
                 }
             }
             PieceKind::King => {
@@ -291,6 +590,117 @@ impl GameState {
             _ => (),
         }
     }
+    /// Applies `m` if it is legal in this position, and otherwise leaves the
+    /// state untouched and reports why the move is not allowed.
+    pub fn try_apply_move(&mut self, m: Move) -> Result<(), MoveError> {
+        if self.legal_moves().contains(&m) {
+            self.apply_move(m);
+            return Ok(());
+        }
+        Err(self.diagnose_illegal_move(m))
+    }
+    fn diagnose_illegal_move(&self, m: Move) -> MoveError {
+        let piece = match self.board.get(m.from()) {
+            Some(piece) => piece,
+            None => return MoveError::NoPieceOnSquare(m.from()),
+        };
+        if piece.color() != self.turn {
+            return MoveError::WrongColor;
+        }
+        if let Move::Castle { from, rook_from, .. } = m {
+            let side = if rook_from.file() > from.file() {
+                CastleSide::KingSide
+            } else {
+                CastleSide::QueenSide
+            };
+            return match make_castle_move(self, self.turn, side) {
+                Err(error) => error,
+                Ok(castle) if castle != m => MoveError::IllegalPieceMove,
+                Ok(_) => MoveError::LeavesKingInCheck,
+            };
+        }
+        if get_moves(self, self.turn).contains(&m) {
+            MoveError::LeavesKingInCheck
+        } else {
+            MoveError::IllegalPieceMove
+        }
+    }
+    /// Applies `m` and returns what is needed to take it back with `unmake_move`.
+    pub fn make_move(&mut self, m: Move) -> UndoInfo {
+        let undo = self.undo_info();
+        self.apply_move(m);
+        undo
+    }
+    fn undo_info(&self) -> UndoInfo {
+        UndoInfo {
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        }
+    }
+    /// Passes the turn without moving, as the search does for null-move pruning.
+    /// The position may not be in check.
+    pub fn make_null_move(&mut self) -> UndoInfo {
+        let undo = self.undo_info();
+        self.en_passant = None;
+        self.halfmove_clock += 1;
+        if self.turn == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.opposite();
+        undo
+    }
+    pub fn unmake_null_move(&mut self, undo: UndoInfo) {
+        self.turn = self.turn.opposite();
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+    }
+    /// Takes back `m`, which must be the last move made with `make_move`.
+    pub fn unmake_move(&mut self, m: Move, undo: UndoInfo) {
+        self.turn = self.turn.opposite();
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+
+        match m {
+            Move::Normal { from, to } | Move::DoublePawnPush { from, to, .. } => {
+                self.board.set(from, self.board.get(to));
+                self.board.set(to, None);
+            }
+            Move::Capture { from, to, captured } => {
+                self.board.set(from, self.board.get(to));
+                self.board.set(to, Some(captured));
+                self.captured_pieces.pop();
+            }
+            Move::EnPassant { from, to, captured } => {
+                self.board.set(from, self.board.get(to));
+                self.board.set(to, None);
+                self.board.set(captured, self.captured_pieces.pop());
+            }
+            Move::Promotion { from, to, .. } => {
+                self.board.set(from, Some(Piece::new(PieceKind::Pawn, self.turn)));
+                self.board.set(to, None);
+                self.promoted_pieces.pop();
+            }
+            Move::PromotionCapture { from, to, captured, .. } => {
+                self.board.set(from, Some(Piece::new(PieceKind::Pawn, self.turn)));
+                self.board.set(to, Some(captured));
+                self.captured_pieces.pop();
+                self.promoted_pieces.pop();
+            }
+            Move::Castle { from, to, rook_from, rook_to } => {
+                let king = self.board.get(to);
+                let rook = self.board.get(rook_to);
+                self.board.set(to, None);
+                self.board.set(rook_to, None);
+                self.board.set(from, king);
+                self.board.set(rook_from, rook);
+            }
+        }
+    }
     pub fn apply_move(&mut self, m: Move) {
         self.halfmove_clock += 1;
         self.en_passant = None;
@@ -319,10 +729,10 @@ impl GameState {
                 self.check_castle_rights_waived(to, captured);
             }
             Move::Promotion { from, to, promoted } => {
-                let piece = self.board.get(from).unwrap();
                 self.board.set(from, None);
                 self.board.set(to, Some(promoted));
 
+                self.promoted_pieces.push(promoted);
                 self.halfmove_clock = 0; // pawn moved -> reset halfmove clock
             }
             Move::PromotionCapture {
@@ -331,11 +741,11 @@ impl GameState {
                 captured,
                 promoted,
             } => {
-                let piece = self.board.get(from).unwrap();
                 self.board.set(from, None);
                 self.board.set(to, Some(promoted));
 
                 self.captured_pieces.push(captured);
+                self.promoted_pieces.push(promoted);
                 self.halfmove_clock = 0; // capture -> reset halfmove clock
 
                 self.check_castle_rights_waived(to, captured);
@@ -347,9 +757,7 @@ impl GameState {
 
                 let captured_pawn = self.board.get(captured).unwrap();
                 self.captured_pieces.push(captured_pawn);
-                
                 self.board.set(captured, None);
-                
 
                 self.halfmove_clock = 0; // capture -> reset halfmove clock
             }
@@ -375,21 +783,29 @@ impl GameState {
                 let king = self.board.get(from).unwrap();
                 let rook = self.board.get(rook_from).unwrap();
 
+                // in Chess960 the king and rook may land on each other's squares,
+                // so both are lifted before either is placed
                 self.board.set(from, None);
-                self.board.set(to, Some(king));
-
                 self.board.set(rook_from, None);
+
+                self.board.set(to, Some(king));
                 self.board.set(rook_to, Some(rook));
 
                 self.unset_castle(king.color(), CastleSide::KingSide);
                 self.unset_castle(king.color(), CastleSide::QueenSide);
             }
         }
+
+        if self.turn == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.opposite();
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use devutil::VecAsserts;
     use test_case::test_case;
 
     #[test]
@@ -508,7 +924,7 @@ mod tests {
         }
 
         assert_eq!(game_state.turn, PieceColor::Black);
-        assert_eq!(game_state.castling_rights, 0b1111);
+        assert_eq!(game_state.castling_rights, CastlingRights::all());
         assert_eq!(game_state.en_passant, Some(Position::new(2, 4)));
         assert_eq!(game_state.halfmove_clock, 0);
         assert_eq!(game_state.fullmove_number, 1);
@@ -664,6 +1080,249 @@ mod tests {
         assert_eq!(game_state.board, Board::empty());
     }
 
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"; "start position")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"; "en passant")]
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 17"; "partial castling")]
+    #[test_case("8/8/8/8/8/8/8/8 b - - 99 120"; "empty board")]
+    fn test_fen_round_trip(fen: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        assert!(!state.chess960);
+        assert_eq!(state.to_fen(), fen);
+        assert_eq!(GameState::from_fen(&state.to_shredder_fen()).unwrap().castling_rights, state.castling_rights);
+    }
+
+    #[test]
+    fn test_fen_parser_shredder_fen() {
+        let fen = "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+
+        assert!(state.chess960);
+        assert_eq!(state.castling_rights.get(PieceColor::White, CastleSide::KingSide), Some(7));
+        assert_eq!(state.castling_rights.get(PieceColor::White, CastleSide::QueenSide), Some(5));
+        assert_eq!(state.castling_rights.get(PieceColor::Black, CastleSide::KingSide), Some(7));
+        assert_eq!(state.castling_rights.get(PieceColor::Black, CastleSide::QueenSide), Some(5));
+        assert_eq!(state.to_shredder_fen(), fen);
+    }
+
+    #[test]
+    fn test_fen_parser_x_fen_outermost_rook() {
+        let fen = "rk2r3/8/8/8/8/8/8/1K3R1R w KQkq - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+
+        assert!(state.chess960);
+        assert_eq!(state.castling_rights.get(PieceColor::White, CastleSide::KingSide), Some(7));
+        assert_eq!(state.castling_rights.get(PieceColor::White, CastleSide::QueenSide), Some(0));
+        assert_eq!(state.castling_rights.get(PieceColor::Black, CastleSide::KingSide), Some(4));
+        assert_eq!(state.castling_rights.get(PieceColor::Black, CastleSide::QueenSide), Some(0));
+    }
+
+    #[test]
+    fn test_fen_writer_x_fen_inner_rook() {
+        let fen = "1k6/8/8/8/8/8/8/1K3R1R w F - 0 1";
+        let state = GameState::from_fen(fen).unwrap();
+
+        assert_eq!(state.castling_rights.get(PieceColor::White, CastleSide::KingSide), Some(5));
+        assert_eq!(state.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_find_move_castle_king_takes_rook() {
+        let state = GameState::from_fen("4r1k1/8/8/8/8/8/8/RK6 w A - 0 1").unwrap();
+        let castle = Move::Castle {
+            from: Position::try_from("b1").unwrap(),
+            to: Position::try_from("c1").unwrap(),
+            rook_from: Position::try_from("a1").unwrap(),
+            rook_to: Position::try_from("d1").unwrap(),
+        };
+        let b1 = Position::try_from("b1").unwrap();
+
+        assert_eq!(state.find_move(b1, Position::try_from("a1").unwrap(), None), Some(castle));
+        // in Chess960 the king's target square means a normal king move
+        assert_eq!(
+            state.find_move(b1, Position::try_from("c1").unwrap(), None),
+            Some(Move::Normal { from: b1, to: Position::try_from("c1").unwrap() })
+        );
+
+        let mut state = state;
+        state.apply_move(castle);
+        assert_eq!(state.to_fen(), "4r1k1/8/8/8/8/8/8/2KR4 b - - 1 1");
+    }
+
+    #[test]
+    fn test_apply_move_castle_king_stays_on_square() {
+        let mut state = GameState::from_fen("6k1/8/8/8/8/8/8/6KR w H - 0 1").unwrap();
+        let castle = state
+            .find_move(Position::try_from("g1").unwrap(), Position::try_from("h1").unwrap(), None)
+            .unwrap();
+        state.apply_move(castle);
+        assert_eq!(state.to_fen(), "6k1/8/8/8/8/8/8/5RK1 b - - 1 1");
+    }
+
+    #[test]
+    fn test_find_move_standard_castle_both_notations() {
+        let state = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let e1 = Position::try_from("e1").unwrap();
+        let by_target = state.find_move(e1, Position::try_from("g1").unwrap(), None);
+        let by_rook = state.find_move(e1, Position::try_from("h1").unwrap(), None);
+
+        assert!(matches!(by_target, Some(Move::Castle { .. })));
+        assert_eq!(by_target, by_rook);
+    }
+
+    #[test]
+    fn test_find_move_promotion() {
+        let state = GameState::from_fen("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let a7 = Position::try_from("a7").unwrap();
+        let a8 = Position::try_from("a8").unwrap();
+
+        assert_eq!(
+            state.find_move(a7, a8, Some(PieceKind::Knight)),
+            Some(Move::Promotion { from: a7, to: a8, promoted: piece!(Knight, White) })
+        );
+        assert_eq!(state.find_move(a7, a8, None), None);
+    }
+
+    #[test]
+    fn test_try_apply_move_ok() {
+        let mut state = GameState::default();
+        let m = Move::DoublePawnPush {
+            from: Position::try_from("e2").unwrap(),
+            to: Position::try_from("e4").unwrap(),
+            en_passant: Position::try_from("e3").unwrap(),
+        };
+        assert_eq!(state.try_apply_move(m), Ok(()));
+        assert_eq!(state.board.get(Position::try_from("e4").unwrap()), Some(piece!(Pawn, White)));
+    }
+
+    #[test_case("e4", "e5", MoveError::NoPieceOnSquare(Position::new(3, 4)); "no piece")]
+    #[test_case("e7", "e6", MoveError::WrongColor; "wrong color")]
+    #[test_case("g1", "g3", MoveError::IllegalPieceMove; "knight moving like a rook")]
+    #[test_case("d1", "d3", MoveError::IllegalPieceMove; "queen blocked")]
+    fn test_try_apply_move_err(from: &str, to: &str, expected: MoveError) {
+        let mut state = GameState::default();
+        let m = Move::Normal {
+            from: Position::try_from(from).unwrap(),
+            to: Position::try_from(to).unwrap(),
+        };
+        assert_eq!(state.try_apply_move(m), Err(expected));
+        assert_eq!(state, GameState::default());
+    }
+
+    #[test]
+    fn test_try_apply_move_err_leaves_king_in_check() {
+        let mut state = GameState::from_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        let m = Move::Normal {
+            from: Position::try_from("e2").unwrap(),
+            to: Position::try_from("d3").unwrap(),
+        };
+        assert_eq!(state.try_apply_move(m), Err(MoveError::LeavesKingInCheck));
+    }
+
+    #[test_case("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1", MoveError::CastlingRightLost(CastleSide::KingSide); "right lost")]
+    #[test_case("r3k2r/8/8/8/8/8/8/R3KB1R w KQkq - 0 1", MoveError::CastlingPathBlocked; "blocked")]
+    #[test_case("r3kr2/8/8/8/8/8/8/R3K2R w KQq - 0 1", MoveError::CastlingPathAttacked; "path attacked")]
+    fn test_try_apply_move_err_castle(fen: &str, expected: MoveError) {
+        let mut state = GameState::from_fen(fen).unwrap();
+        let m = Move::Castle {
+            from: Position::try_from("e1").unwrap(),
+            to: Position::try_from("g1").unwrap(),
+            rook_from: Position::try_from("h1").unwrap(),
+            rook_to: Position::try_from("f1").unwrap(),
+        };
+        assert_eq!(state.try_apply_move(m), Err(expected));
+    }
+
+    #[test]
+    fn test_move_error_display() {
+        assert_eq!(
+            MoveError::NoPieceOnSquare(Position::try_from("e4").unwrap()).to_string(),
+            "there is no piece on e4"
+        );
+    }
+
+    #[test]
+    fn test_fullmove_number_starts_at_one() {
+        let state = GameState::from_fen("8/8/8/8/8/8/8/8 w - - 0 0").unwrap();
+        assert_eq!(state.fullmove_number, 1);
+
+        let state = GameState::new(Board::empty(), PieceColor::White, 0b0000, None, 0, 0);
+        assert_eq!(state.fullmove_number, 1);
+    }
+
+    #[test]
+    fn test_apply_move_advances_turn_and_counters() {
+        let mut state = GameState::default();
+        state.apply_move(Move::Normal {
+            from: Position::try_from("g1").unwrap(),
+            to: Position::try_from("f3").unwrap(),
+        });
+        assert_eq!(state.turn, PieceColor::Black);
+        assert_eq!(state.fullmove_number, 1);
+        assert_eq!(state.halfmove_clock, 1);
+
+        state.apply_move(Move::Normal {
+            from: Position::try_from("g8").unwrap(),
+            to: Position::try_from("f6").unwrap(),
+        });
+        assert_eq!(state.turn, PieceColor::White);
+        assert_eq!(state.fullmove_number, 2);
+        assert_eq!(state.halfmove_clock, 2);
+    }
+
+    #[test]
+    fn test_apply_move_records_promotions() {
+        let mut state = GameState::from_fen("1r5k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        state.apply_move(Move::PromotionCapture {
+            from: Position::try_from("a7").unwrap(),
+            to: Position::try_from("b8").unwrap(),
+            captured: piece!(Rook, Black),
+            promoted: piece!(Queen, White),
+        });
+        assert_eq!(state.captured_pieces, vec![piece!(Rook, Black)]);
+        assert_eq!(state.promoted_pieces, vec![piece!(Queen, White)]);
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"; "kiwipete")]
+    #[test_case("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"; "pins")]
+    #[test_case("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9"; "chess960")]
+    fn test_legal_moves_from_covers_legal_moves(fen: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        let mut from_squares = Vec::new();
+        for index in 0..64usize {
+            from_squares.append(&mut state.legal_moves_from(Position::from(index)));
+        }
+        let legal = state.legal_moves();
+        assert_eq!(from_squares.len(), legal.len());
+        from_squares.assert_forall(|m| legal.contains(m));
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"; "kiwipete")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"; "promotions")]
+    #[test_case("8/8/8/2k5/2pP4/8/B7/4K3 b - d3 0 3"; "en passant")]
+    #[test_case("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9"; "chess960")]
+    fn test_make_unmake_move_restores_state(fen: &str) {
+        let original = GameState::from_fen(fen).unwrap();
+        let mut state = original.clone();
+        for m in original.legal_moves() {
+            let undo = state.make_move(m);
+            assert_ne!(state, original);
+            state.unmake_move(m, undo);
+            assert_eq!(state, original, "after {:?}", m);
+        }
+    }
+
+    #[test]
+    fn test_make_unmake_null_move() {
+        let original = GameState::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+        let mut state = original.clone();
+
+        let undo = state.make_null_move();
+        assert_eq!(state.to_fen(), "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 1 3");
+        assert_ne!(state.hash(), original.hash());
+        state.unmake_null_move(undo);
+        assert_eq!(state, original);
+    }
+
     #[test]
     fn test_game_state_default_no_error() {
         GameState::default();
@@ -722,4 +1381,26 @@ mod tests {
         let state = GameState::from_fen(state_fen).unwrap();
         assert_eq!(state.is_in_check(), expected);
     }
+
+    #[test_case("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true; "kings only")]
+    #[test_case("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true; "knight")]
+    #[test_case("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", true; "bishop")]
+    #[test_case("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true; "same colored bishops")]
+    #[test_case("4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1", false; "opposite colored bishops")]
+    #[test_case("4kn2/8/8/8/8/8/8/4KN2 w - - 0 1", false; "two knights")]
+    #[test_case("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false; "pawn")]
+    #[test_case("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", false; "rook")]
+    fn test_game_state_is_insufficient_material(state_fen: &str, expected: bool) {
+        let state = GameState::from_fen(state_fen).unwrap();
+        assert_eq!(state.is_insufficient_material(), expected);
+    }
+
+    #[test_case("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true, true; "knight against king")]
+    #[test_case("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", false, true; "two knights against king")]
+    #[test_case("4k3/p7/8/8/8/8/8/4K3 w - - 0 1", true, false; "king against pawn")]
+    fn test_game_state_has_insufficient_material(state_fen: &str, white: bool, black: bool) {
+        let state = GameState::from_fen(state_fen).unwrap();
+        assert_eq!(state.has_insufficient_material(PieceColor::White), white);
+        assert_eq!(state.has_insufficient_material(PieceColor::Black), black);
+    }
 }
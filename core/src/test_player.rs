@@ -0,0 +1,127 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::{
+    clock::MockTimeSource,
+    moves::Move,
+    notation::parse_uci,
+    piece::PieceColor,
+    player::{Outcome, Player, PlayerAction},
+    search::StopSignal,
+    state::GameState,
+    time_manager::TimeControl,
+};
+
+/// What a `Scripted` player was told by whoever it played for.
+#[derive(Debug, Default)]
+pub(crate) struct Notes {
+    pub color: Option<PieceColor>,
+    pub start: Option<GameState>,
+    pub opponent_moves: Vec<Move>,
+    pub draw_offers: usize,
+    pub rejected_draws: usize,
+    /// The time left to White and Black before each move.
+    pub clocks: Vec<(Duration, Duration)>,
+    pub outcome: Option<Outcome>,
+}
+
+/// A player for tests, which does what its script says, one entry per move,
+/// and resigns once the script runs out. An entry is one of:
+///
+/// - a move in UCI notation, offering a draw with a trailing `=`; illegal
+///   moves are made up from the first legal one
+/// - `first`, the first legal move
+/// - `sleep`, the first legal move after 50ms of `time`
+/// - `think`, the first legal move once `stop_signal` is stopped
+/// - `accept` or `claim`, a draw
+/// - `stall`, a draw claim after 50ms of `time`
+/// - `panic`
+pub(crate) struct Scripted {
+    moves: Vec<&'static str>,
+    /// Advanced by the moves that take time.
+    pub time: MockTimeSource,
+    /// Shared, to be read after the player was handed over.
+    pub notes: Arc<Mutex<Notes>>,
+    /// Reported as the score of each move.
+    pub score: Option<i32>,
+    stop: StopSignal,
+}
+
+impl Scripted {
+    pub fn new(moves: &[&'static str]) -> Self {
+        Self {
+            moves: moves.to_vec(),
+            time: MockTimeSource::default(),
+            notes: Arc::default(),
+            score: None,
+            stop: StopSignal::new(),
+        }
+    }
+
+    pub fn notes(&self) -> MutexGuard<'_, Notes> {
+        self.notes.lock().unwrap()
+    }
+}
+
+impl Player for Scripted {
+    fn get_move(&mut self, state: &GameState) -> PlayerAction {
+        if self.moves.is_empty() {
+            return PlayerAction::Resign;
+        }
+        let first = state.legal_moves()[0];
+        match self.moves.remove(0) {
+            "first" => PlayerAction::Move(first),
+            "sleep" => {
+                self.time.advance(Duration::from_millis(50));
+                PlayerAction::Move(first)
+            }
+            "think" => {
+                while !self.stop.is_stopped() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                PlayerAction::Move(first)
+            }
+            "accept" => PlayerAction::AcceptDraw,
+            "claim" => PlayerAction::ClaimDraw,
+            "stall" => {
+                self.time.advance(Duration::from_millis(50));
+                PlayerAction::ClaimDraw
+            }
+            "panic" => panic!("scripted panic"),
+            uci => match uci.strip_suffix('=') {
+                Some(uci) => PlayerAction::OfferDraw(parse_uci(state, uci).unwrap()),
+                None => PlayerAction::Move(parse_uci(state, uci).unwrap_or(Move::Normal {
+                    from: first.from(),
+                    to: first.from(),
+                })),
+            },
+        }
+    }
+    fn on_game_start(&mut self, color: PieceColor, start: &GameState) {
+        let mut notes = self.notes();
+        notes.color = Some(color);
+        notes.start = Some(start.clone());
+    }
+    fn on_opponent_move(&mut self, m: Move) {
+        self.notes().opponent_moves.push(m);
+    }
+    fn on_draw_offer(&mut self) {
+        self.notes().draw_offers += 1;
+    }
+    fn on_draw_rejected(&mut self) {
+        self.notes().rejected_draws += 1;
+    }
+    fn on_clock(&mut self, white: TimeControl, black: TimeControl) {
+        self.notes().clocks.push((white.remaining, black.remaining));
+    }
+    fn on_game_end(&mut self, outcome: Outcome) {
+        self.notes().outcome = Some(outcome);
+    }
+    fn last_score(&self) -> Option<i32> {
+        self.score
+    }
+    fn stop_signal(&self) -> Option<StopSignal> {
+        Some(self.stop.clone())
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::{Duration, Instant};
+
+use crate::{moves::Move, state::GameState};
+
+/// The time left on the side to move's clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeControl {
+    pub remaining: Duration,
+    pub increment: Duration,
+    /// Moves until the next time control, `None` for sudden death.
+    pub moves_to_go: Option<u32>,
+    /// Time lost per move outside the search, e.g. to a GUI or the network.
+    pub move_overhead: Duration,
+}
+
+/// How many moves a sudden death game is assumed to have left.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// The score drop between iterations, in centipawns, that counts as failing low.
+const FAIL_LOW_MARGIN: i32 = 30;
+const MAX_SCALE: f64 = 2.5;
+
+/// Decides how long to think about a move. The search should not start a
+/// new iteration past the soft deadline, and must stop at the hard one. The
+/// soft deadline moves out while the best move keeps changing or the score
+/// drops, up to the hard deadline, and with only one legal move the search
+/// stops as soon as it can.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft: Duration,
+    hard: Duration,
+    forced: bool,
+    /// Grows each time the best move changes, and halves each iteration.
+    instability: f64,
+    failing_low: bool,
+    last: Option<(Move, i32)>,
+}
+
+impl TimeManager {
+    pub fn new(control: TimeControl, state: &GameState) -> Self {
+        let available = control.remaining.saturating_sub(control.move_overhead);
+        let moves_to_go = control.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        // keep a reserve, so that a slow move does not lose on time
+        let maximum = available * 9 / 10;
+        let soft = (available / moves_to_go + control.increment * 3 / 4).min(maximum);
+        let hard = (soft * 3).min(maximum);
+        Self {
+            start: Instant::now(),
+            soft,
+            hard,
+            forced: state.legal_moves().len() == 1,
+            instability: 0.0,
+            failing_low: false,
+            last: None,
+        }
+    }
+
+    /// Records the result of a completed iteration.
+    pub fn update(&mut self, best_move: Move, score: i32) {
+        self.instability /= 2.0;
+        self.failing_low = false;
+        if let Some((last_move, last_score)) = self.last {
+            if last_move != best_move {
+                self.instability += 1.0;
+            }
+            self.failing_low = score <= last_score - FAIL_LOW_MARGIN;
+        }
+        self.last = Some((best_move, score));
+    }
+
+    pub fn soft_limit(&self) -> Duration {
+        if self.forced {
+            return Duration::ZERO;
+        }
+        let mut scale = 1.0 + self.instability;
+        if self.failing_low {
+            scale *= 1.5;
+        }
+        self.soft.mul_f64(scale.min(MAX_SCALE)).min(self.hard)
+    }
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+    pub fn soft_deadline(&self) -> Instant {
+        self.start + self.soft_limit()
+    }
+    pub fn hard_deadline(&self) -> Instant {
+        self.start + self.hard
+    }
+
+    /// Returns whether the search should not start another iteration.
+    pub fn should_stop(&self) -> bool {
+        Instant::now() >= self.soft_deadline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+
+    fn control(remaining: u64, increment: u64, moves_to_go: Option<u32>) -> TimeControl {
+        TimeControl {
+            remaining: Duration::from_millis(remaining),
+            increment: Duration::from_millis(increment),
+            moves_to_go,
+            move_overhead: Duration::ZERO,
+        }
+    }
+
+    fn find_move(state: &GameState, from: &str, to: &str) -> Move {
+        state
+            .find_move(Position::try_from(from).unwrap(), Position::try_from(to).unwrap(), None)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_time_manager_limits() {
+        let state = GameState::default();
+        let manager = TimeManager::new(control(60_000, 0, None), &state);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(2000));
+        assert_eq!(manager.hard_limit(), Duration::from_millis(6000));
+
+        let manager = TimeManager::new(control(60_000, 1000, Some(10)), &state);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(6750));
+        assert_eq!(manager.hard_limit(), Duration::from_millis(20250));
+        assert!(manager.soft_deadline() < manager.hard_deadline());
+    }
+
+    #[test]
+    fn test_time_manager_keeps_reserve() {
+        let state = GameState::default();
+        let manager = TimeManager::new(control(1000, 0, Some(1)), &state);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(900));
+        assert_eq!(manager.hard_limit(), Duration::from_millis(900));
+
+        let manager = TimeManager::new(control(1000, 5000, None), &state);
+        assert_eq!(manager.hard_limit(), Duration::from_millis(900));
+
+        let mut overhead = control(1000, 0, Some(1));
+        overhead.move_overhead = Duration::from_millis(2000);
+        let manager = TimeManager::new(overhead, &state);
+        assert_eq!(manager.hard_limit(), Duration::ZERO);
+        assert!(manager.should_stop());
+    }
+
+    #[test]
+    fn test_time_manager_extends_on_unstable_pv() {
+        let state = GameState::default();
+        let mut manager = TimeManager::new(control(60_000, 0, None), &state);
+        let e4 = find_move(&state, "e2", "e4");
+        let d4 = find_move(&state, "d2", "d4");
+
+        manager.update(e4, 20);
+        manager.update(e4, 25);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(2000));
+        manager.update(d4, 25);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(4000));
+        manager.update(e4, 25);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(5000));
+        manager.update(e4, 25);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(3500));
+        assert!(!manager.should_stop());
+    }
+
+    #[test]
+    fn test_time_manager_extends_on_fail_low() {
+        let state = GameState::default();
+        let mut manager = TimeManager::new(control(60_000, 0, None), &state);
+        let e4 = find_move(&state, "e2", "e4");
+
+        manager.update(e4, 50);
+        manager.update(e4, 0);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(3000));
+        manager.update(e4, 10);
+        assert_eq!(manager.soft_limit(), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn test_time_manager_forced_move() {
+        let state = GameState::from_fen("k7/8/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(state.legal_moves().len(), 1);
+        let manager = TimeManager::new(control(60_000, 0, None), &state);
+        assert_eq!(manager.soft_limit(), Duration::ZERO);
+        assert!(manager.should_stop());
+    }
+}
@@ -0,0 +1,403 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::{
+    moves::Move,
+    piece::PieceKind,
+    position::Position,
+    search::{is_mate_score, MAX_PLY},
+};
+
+/// How the score of a `TtEntry` relates to the true score of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The search failed high, the true score is at least this.
+    Lower,
+    /// The search failed low, the true score is at most this.
+    Upper,
+}
+
+/// A move packed into 16 bits: 6 bits each for the from and to squares, and
+/// 4 bits telling promotions and castling apart from other moves. It only
+/// identifies a move among the legal moves of a position, see `resolve`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactMove(u16);
+
+const FLAG_CASTLE: u16 = 0b1111;
+
+impl CompactMove {
+    pub fn new(m: Move) -> Self {
+        let flag = match m {
+            Move::Castle { .. } => FLAG_CASTLE,
+            _ => m.promotion().map_or(0, |piece| piece.kind() as u16),
+        };
+        Self(u8::from(m.from()) as u16 | (u8::from(m.to()) as u16) << 6 | flag << 12)
+    }
+    pub fn from(&self) -> Position {
+        Position::from((self.0 & 0b111111) as u8)
+    }
+    pub fn to(&self) -> Position {
+        Position::from((self.0 >> 6 & 0b111111) as u8)
+    }
+    pub fn promotion(&self) -> Option<PieceKind> {
+        match self.0 >> 12 {
+            1 => Some(PieceKind::Knight),
+            2 => Some(PieceKind::Bishop),
+            3 => Some(PieceKind::Rook),
+            4 => Some(PieceKind::Queen),
+            _ => None,
+        }
+    }
+    pub fn matches(&self, m: &Move) -> bool {
+        Self::new(*m) == *self
+    }
+    /// Finds the move this stands for among `moves`.
+    pub fn resolve(&self, moves: &[Move]) -> Option<Move> {
+        moves.iter().find(|m| self.matches(m)).copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<CompactMove>,
+    /// Adjusted to be relative to the ply the entry was probed at.
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    /// The search generation the entry was written in, see `TranspositionTable::new_search`.
+    pub age: u8,
+}
+
+impl TtEntry {
+    // the bound is stored as 1..=3 so that an occupied slot never packs to 0
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        self.best_move.map_or(0, |m| m.0) as u64
+            | (self.score as i16 as u16 as u64) << 16
+            | (self.depth as u64) << 32
+            | bound << 40
+            | (self.age as u64) << 48
+    }
+    fn unpack(data: u64) -> Self {
+        let best_move = match data as u16 {
+            0 => None,
+            m => Some(CompactMove(m)),
+        };
+        let bound = match data >> 40 & 0b11 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        Self {
+            best_move,
+            score: (data >> 16) as u16 as i16 as i32,
+            depth: (data >> 32) as u8,
+            bound,
+            age: (data >> 48) as u8,
+        }
+    }
+}
+
+/// Mate scores are stored relative to the node they were found at rather
+/// than the root, so they stay correct when the position is reached at a
+/// different ply.
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score + ply as i32
+    } else {
+        score - ply as i32
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if !is_mate_score(score) {
+        score
+    } else if score > 0 {
+        score - ply as i32
+    } else {
+        score + ply as i32
+    }
+}
+
+/// An entry is stored as its data and its key xor-ed with its data, so that
+/// a slot torn by concurrent writes fails the key check instead of being
+/// read as a bogus entry.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+const BUCKET_SIZE: usize = 2;
+type Bucket = [Slot; BUCKET_SIZE];
+
+/// A fixed-size hash table of search results, keyed by `GameState::hash`.
+/// It can be shared between search threads; all access is lockless.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_SIZE_MB: usize = 16;
+
+    /// Allocates a table of at most `size_mb` megabytes. The number of
+    /// buckets is rounded down to a power of two.
+    pub fn new(size_mb: usize) -> Self {
+        let bytes = size_mb * 1024 * 1024;
+        let count = (bytes / std::mem::size_of::<Bucket>()).max(1);
+        let count = 1 << count.ilog2();
+        Self {
+            buckets: (0..count).map(|_| Bucket::default()).collect(),
+            age: AtomicU8::new(0),
+        }
+    }
+    pub fn resize(&mut self, size_mb: usize) {
+        *self = Self::new(size_mb);
+    }
+    /// The number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flatten() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+    /// Starts a new generation, so entries of earlier searches are replaced first.
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn bucket(&self, hash: u64) -> &Bucket {
+        &self.buckets[hash as usize & (self.buckets.len() - 1)]
+    }
+
+    /// Looks up the entry for `hash`, with its score adjusted to `ply`.
+    pub fn probe(&self, hash: u64, ply: usize) -> Option<TtEntry> {
+        self.bucket(hash).iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            let key = slot.key.load(Ordering::Relaxed) ^ data;
+            (data != 0 && key == hash).then(|| {
+                let mut entry = TtEntry::unpack(data);
+                entry.score = score_from_tt(entry.score, ply);
+                entry
+            })
+        })
+    }
+
+    /// Stores a search result for `hash`, found at `ply` from the root.
+    /// Replaces the entry for the same position if there is one, and otherwise
+    /// the entry of the bucket that is shallowest and oldest.
+    pub fn store(
+        &self,
+        hash: u64,
+        best_move: Option<Move>,
+        score: i32,
+        depth: usize,
+        bound: Bound,
+        ply: usize,
+    ) {
+        let age = self.age.load(Ordering::Relaxed);
+        let mut best_move = best_move.map(CompactMove::new);
+
+        let mut target = &self.bucket(hash)[0];
+        let mut target_worth = i32::MAX;
+        for slot in self.bucket(hash) {
+            let data = slot.data.load(Ordering::Relaxed);
+            if data == 0 {
+                target = slot;
+                break;
+            }
+            let entry = TtEntry::unpack(data);
+            if slot.key.load(Ordering::Relaxed) ^ data == hash {
+                // a fail-low has no best move, keep the one found earlier
+                best_move = best_move.or(entry.best_move);
+                target = slot;
+                break;
+            }
+            let worth = entry.depth as i32 - 8 * age.wrapping_sub(entry.age) as i32;
+            if worth < target_worth {
+                target = slot;
+                target_worth = worth;
+            }
+        }
+
+        let entry = TtEntry {
+            best_move,
+            score: score_to_tt(score, ply),
+            depth: depth.min(MAX_PLY) as u8,
+            bound,
+            age,
+        };
+        let data = entry.pack();
+        target.key.store(hash ^ data, Ordering::Relaxed);
+        target.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Returns how full the table is in permille, sampled from its first
+    /// entries and only counting those of the current search.
+    pub fn hashfull(&self) -> usize {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self.buckets.iter().flatten().take(1000);
+        let total = sample.clone().count();
+        let used = sample
+            .filter(|slot| {
+                let data = slot.data.load(Ordering::Relaxed);
+                data != 0 && TtEntry::unpack(data).age == age
+            })
+            .count();
+        used * 1000 / total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::MATE_SCORE;
+    use crate::state::GameState;
+    use std::sync::Arc;
+    use std::thread;
+    use test_case::test_case;
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"; "kiwipete")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"; "promotions")]
+    #[test_case("1r2k1r1/8/8/8/8/8/8/R3K1R1 w GAgb - 0 1"; "chess960")]
+    #[test_case("4k3/8/8/8/8/8/8/5K1R w H - 0 1"; "castle next to a king move")]
+    fn test_compact_move_resolves_uniquely(fen: &str) {
+        let moves = GameState::from_fen(fen).unwrap().legal_moves();
+        for m in &moves {
+            let compact = CompactMove::new(*m);
+            assert_eq!(compact.from(), m.from());
+            assert_eq!(compact.to(), m.to());
+            assert_eq!(compact.promotion(), m.promotion().map(|piece| piece.kind()));
+            assert_eq!(compact.resolve(&moves), Some(*m));
+        }
+    }
+
+    #[test_case(Some(Move::Normal { from: Position::new(1, 4), to: Position::new(3, 4) }), 150, 7, Bound::Exact)]
+    #[test_case(None, -2000, 0, Bound::Upper)]
+    #[test_case(None, MATE_SCORE - 5, 127, Bound::Lower)]
+    fn test_tt_entry_pack_round_trip(m: Option<Move>, score: i32, depth: u8, bound: Bound) {
+        let entry = TtEntry {
+            best_move: m.map(CompactMove::new),
+            score,
+            depth,
+            bound,
+            age: 200,
+        };
+        assert_ne!(entry.pack(), 0);
+        assert_eq!(TtEntry::unpack(entry.pack()), entry);
+    }
+
+    #[test]
+    fn test_tt_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let state = GameState::default();
+        let m = state.legal_moves()[0];
+
+        assert_eq!(tt.probe(state.hash(), 0), None);
+        tt.store(state.hash(), Some(m), 35, 4, Bound::Exact, 0);
+        let entry = tt.probe(state.hash(), 0).unwrap();
+        assert_eq!(entry.score, 35);
+        assert_eq!(entry.depth, 4);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert!(entry.best_move.unwrap().matches(&m));
+        assert_eq!(tt.probe(state.hash() ^ 1, 0), None);
+
+        tt.clear();
+        assert_eq!(tt.probe(state.hash(), 0), None);
+    }
+
+    #[test]
+    fn test_tt_mate_scores_are_ply_relative() {
+        let tt = TranspositionTable::new(1);
+        // mate in 3 plies from the root, found at ply 1
+        tt.store(42, None, MATE_SCORE - 3, 2, Bound::Exact, 1);
+        assert_eq!(tt.probe(42, 1).unwrap().score, MATE_SCORE - 3);
+        assert_eq!(tt.probe(42, 5).unwrap().score, MATE_SCORE - 7);
+
+        tt.store(43, None, -MATE_SCORE + 4, 2, Bound::Exact, 2);
+        assert_eq!(tt.probe(43, 0).unwrap().score, -MATE_SCORE + 2);
+    }
+
+    #[test]
+    fn test_tt_keeps_best_move_on_fail_low() {
+        let tt = TranspositionTable::new(1);
+        let m = GameState::default().legal_moves()[0];
+        tt.store(42, Some(m), 10, 2, Bound::Exact, 0);
+        tt.store(42, None, -5, 3, Bound::Upper, 0);
+
+        let entry = tt.probe(42, 0).unwrap();
+        assert_eq!(entry.depth, 3);
+        assert!(entry.best_move.unwrap().matches(&m));
+    }
+
+    #[test]
+    fn test_tt_replaces_shallow_and_old_entries() {
+        let tt = TranspositionTable::new(0);
+        assert_eq!(tt.capacity(), BUCKET_SIZE);
+
+        tt.store(1, None, 0, 10, Bound::Exact, 0);
+        tt.store(2, None, 0, 2, Bound::Exact, 0);
+        tt.store(3, None, 0, 5, Bound::Exact, 0);
+        assert!(tt.probe(1, 0).is_some());
+        assert!(tt.probe(2, 0).is_none());
+        assert!(tt.probe(3, 0).is_some());
+
+        tt.new_search();
+        tt.new_search();
+        tt.store(4, None, 0, 1, Bound::Exact, 0);
+        assert!(tt.probe(4, 0).is_some());
+        assert!(tt.probe(3, 0).is_none());
+    }
+
+    #[test]
+    fn test_tt_size_and_hashfull() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.capacity() * std::mem::size_of::<Slot>(), 1024 * 1024);
+        assert_eq!(tt.hashfull(), 0);
+        for hash in 0..tt.capacity() as u64 {
+            tt.store(hash, None, 0, 1, Bound::Exact, 0);
+        }
+        assert_eq!(tt.hashfull(), 1000);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_tt_shared_between_threads() {
+        let tt = Arc::new(TranspositionTable::new(1));
+        let handles: Vec<_> = (0..4u64)
+            .map(|thread| {
+                let tt = Arc::clone(&tt);
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        tt.store(i * 4 + thread, None, i as i32, 1, Bound::Lower, 0);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        for thread in 0..4u64 {
+            let entry = tt.probe(999 * 4 + thread, 0).unwrap();
+            assert_eq!(entry.score, 999);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    piece::{Piece, PieceColor},
+    state::{CastleSide, GameState},
+};
+
+/// Random keys for Zobrist hashing, generated at compile time so that hashes
+/// are stable across runs.
+struct ZobristKeys {
+    pieces: [[u64; 64]; 12],
+    /// One key per castling right and rook file.
+    castling: [[u64; 8]; 4],
+    en_passant: [u64; 8],
+    black_to_move: u64,
+}
+
+/// One step of the SplitMix64 generator, returning the new state and the output.
+pub(crate) const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+impl ZobristKeys {
+    const fn generate() -> Self {
+        let mut keys = Self {
+            pieces: [[0; 64]; 12],
+            castling: [[0; 8]; 4],
+            en_passant: [0; 8],
+            black_to_move: 0,
+        };
+        let mut seed = 0x5255_5343_4845_5353;
+        let mut key;
+
+        let mut i = 0;
+        while i < 12 * 64 {
+            (seed, key) = split_mix(seed);
+            keys.pieces[i / 64][i % 64] = key;
+            i += 1;
+        }
+        i = 0;
+        while i < 4 * 8 {
+            (seed, key) = split_mix(seed);
+            keys.castling[i / 8][i % 8] = key;
+            i += 1;
+        }
+        i = 0;
+        while i < 8 {
+            (seed, key) = split_mix(seed);
+            keys.en_passant[i] = key;
+            i += 1;
+        }
+        (_, keys.black_to_move) = split_mix(seed);
+        keys
+    }
+}
+
+const KEYS: ZobristKeys = ZobristKeys::generate();
+
+#[inline]
+fn piece_index(piece: Piece) -> usize {
+    let color = match piece.color() {
+        PieceColor::White => 0,
+        PieceColor::Black => 6,
+    };
+    color + piece.kind() as usize
+}
+
+/// Computes the Zobrist hash of `state` from the board, the side to move,
+/// the castling rights and the en passant file. The move counters are not
+/// part of the hash.
+pub fn zobrist_hash(state: &GameState) -> u64 {
+    let mut hash = 0;
+
+    for (index, square) in state.board.squares().iter().enumerate() {
+        if let Some(piece) = square {
+            hash ^= KEYS.pieces[piece_index(*piece)][index];
+        }
+    }
+
+    let rights = [
+        (PieceColor::White, CastleSide::KingSide),
+        (PieceColor::White, CastleSide::QueenSide),
+        (PieceColor::Black, CastleSide::KingSide),
+        (PieceColor::Black, CastleSide::QueenSide),
+    ];
+    for (index, (color, side)) in rights.into_iter().enumerate() {
+        if let Some(file) = state.castling_rights.get(color, side) {
+            hash ^= KEYS.castling[index][file as usize];
+        }
+    }
+
+    if let Some(en_passant) = state.en_passant {
+        hash ^= KEYS.en_passant[en_passant.file() as usize];
+    }
+    if state.turn == PieceColor::Black {
+        hash ^= KEYS.black_to_move;
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+    use std::collections::HashSet;
+    use test_case::test_case;
+
+    #[test]
+    fn test_zobrist_keys_unique() {
+        let mut seen = HashSet::new();
+        let keys = KEYS.pieces.iter().flatten()
+            .chain(KEYS.castling.iter().flatten())
+            .chain(KEYS.en_passant.iter())
+            .chain(std::iter::once(&KEYS.black_to_move));
+        for key in keys {
+            assert!(seen.insert(*key));
+        }
+    }
+
+    #[test]
+    fn test_zobrist_hash_transposition() {
+        let mut a = GameState::default();
+        let mut b = GameState::default();
+        for (from, to) in [("g1", "f3"), ("g8", "f6"), ("b1", "c3"), ("b8", "c6")] {
+            let m = a.find_move(Position::try_from(from).unwrap(), Position::try_from(to).unwrap(), None);
+            a.apply_move(m.unwrap());
+        }
+        for (from, to) in [("b1", "c3"), ("b8", "c6"), ("g1", "f3"), ("g8", "f6")] {
+            let m = b.find_move(Position::try_from(from).unwrap(), Position::try_from(to).unwrap(), None);
+            b.apply_move(m.unwrap());
+        }
+        assert_eq!(a.hash(), b.hash());
+        assert_ne!(a.hash(), GameState::default().hash());
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"; "side to move")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1"; "castling rights")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1"; "en passant")]
+    fn test_zobrist_hash_differs(fen: &str) {
+        let state = GameState::from_fen(fen).unwrap();
+        assert_ne!(state.hash(), GameState::default().hash());
+    }
+
+    #[test]
+    fn test_zobrist_hash_ignores_counters() {
+        let state = GameState::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 12 40").unwrap();
+        assert_eq!(state.hash(), GameState::default().hash());
+    }
+}
@@ -0,0 +1,100 @@
+use ruschess_core::*;
+
+/// Plays a move given as `<from><to>[promotion]`, e.g. `e2e4` or `b7b8q`,
+/// using nothing but the public API.
+fn play(state: &mut GameState, m: &str) {
+    let from = Position::try_from(&m[0..2]).unwrap();
+    let to = Position::try_from(&m[2..4]).unwrap();
+    let promotion = match m.chars().nth(4) {
+        Some('q') => Some(PieceKind::Queen),
+        Some('r') => Some(PieceKind::Rook),
+        Some('b') => Some(PieceKind::Bishop),
+        Some('n') => Some(PieceKind::Knight),
+        _ => None,
+    };
+    let found = state
+        .find_move(from, to, promotion)
+        .unwrap_or_else(|| panic!("{} is not legal in {}", m, state.to_fen()));
+    state.try_apply_move(found).unwrap();
+}
+
+fn assert_sequence(start: &str, moves: &[(&str, &str)]) -> GameState {
+    let mut state = GameState::from_fen(start).unwrap();
+    for (m, fen) in moves {
+        play(&mut state, m);
+        assert_eq!(&state.to_fen(), fen, "after {}", m);
+    }
+    state
+}
+
+#[test]
+fn test_sequence_open_game() {
+    let state = assert_sequence(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[
+            ("e2e4", "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+            ("e7e5", "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"),
+            ("g1f3", "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"),
+            ("b8c6", "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"),
+            ("f1c4", "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3"),
+            ("f8c5", "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"),
+            ("e1g1", "r1bqk1nr/pppp1ppp/2n5/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4"),
+            ("g8f6", "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 6 5"),
+            ("d2d4", "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2BPP3/5N2/PPP2PPP/RNBQ1RK1 b kq d3 0 5"),
+            ("e5d4", "r1bqk2r/pppp1ppp/2n2n2/2b5/2BpP3/5N2/PPP2PPP/RNBQ1RK1 w kq - 0 6"),
+            ("e4e5", "r1bqk2r/pppp1ppp/2n2n2/2b1P3/2Bp4/5N2/PPP2PPP/RNBQ1RK1 b kq - 0 6"),
+            ("d7d5", "r1bqk2r/ppp2ppp/2n2n2/2bpP3/2Bp4/5N2/PPP2PPP/RNBQ1RK1 w kq d6 0 7"),
+            ("e5d6", "r1bqk2r/ppp2ppp/2nP1n2/2b5/2Bp4/5N2/PPP2PPP/RNBQ1RK1 b kq - 0 7"),
+            ("e8g8", "r1bq1rk1/ppp2ppp/2nP1n2/2b5/2Bp4/5N2/PPP2PPP/RNBQ1RK1 w - - 1 8"),
+        ],
+    );
+    assert_eq!(state.captured_pieces.len(), 2);
+    assert!(!state.is_in_check());
+}
+
+#[test]
+fn test_sequence_fools_mate() {
+    let state = assert_sequence(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[
+            ("f2f3", "rnbqkbnr/pppppppp/8/8/8/5P2/PPPPP1PP/RNBQKBNR b KQkq - 0 1"),
+            ("e7e5", "rnbqkbnr/pppp1ppp/8/4p3/8/5P2/PPPPP1PP/RNBQKBNR w KQkq e6 0 2"),
+            ("g2g4", "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2"),
+            ("d8h4", "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"),
+        ],
+    );
+    assert!(state.is_checkmate());
+}
+
+#[test]
+fn test_sequence_promotions() {
+    let state = assert_sequence(
+        "7k/1P6/8/8/8/8/6p1/K7 w - - 0 60",
+        &[
+            ("b7b8q", "1Q5k/8/8/8/8/8/6p1/K7 b - - 0 60"),
+            ("h8h7", "1Q6/7k/8/8/8/8/6p1/K7 w - - 1 61"),
+            ("a1a2", "1Q6/7k/8/8/8/8/K5p1/8 b - - 2 61"),
+            ("g2g1n", "1Q6/7k/8/8/8/8/K7/6n1 w - - 0 62"),
+        ],
+    );
+    assert_eq!(
+        state.promoted_pieces,
+        vec![
+            Piece::new(PieceKind::Queen, PieceColor::White),
+            Piece::new(PieceKind::Knight, PieceColor::Black),
+        ]
+    );
+}
+
+#[test]
+fn test_sequence_rejects_illegal_move() {
+    let mut state = GameState::default();
+    play(&mut state, "e2e4");
+
+    // white cannot move twice in a row
+    let m = Move::Normal {
+        from: Position::try_from("d2").unwrap(),
+        to: Position::try_from("d3").unwrap(),
+    };
+    assert_eq!(state.try_apply_move(m), Err(MoveError::WrongColor));
+}
@@ -5,7 +5,7 @@ pub trait VecAsserts<T> {
     fn assert_exists(&self, f: impl Fn(&&T) -> bool) 
     where T: std::fmt::Debug;
     
-    fn assert_eq(&self, other: &Vec<T>) 
+    fn assert_eq(&self, other: &[T]) 
     where T: std::fmt::Debug + PartialEq;
     
     fn assert_len(&self, len: usize) 
@@ -33,7 +33,7 @@ impl<T> VecAsserts<T> for Vec<T> {
         }
         panic!("no element satisfied the predicate");
     }
-    fn assert_eq(&self, other: &Vec<T>) where T: std::fmt::Debug + PartialEq {
+    fn assert_eq(&self, other: &[T]) where T: std::fmt::Debug + PartialEq {
         let other = other.iter();
         for (i, (x, y)) in self.iter().zip(other).enumerate() {
             assert_eq!(x, y, "failed for index {} ({:?} != {:?})", i, x, y);
@@ -0,0 +1,71 @@
+//! A scripted stand-in for a UCI engine, to test talking to external engines.
+//! It always plays the first legal move, unless its mode, given as the first
+//! argument or as the `Mode` option, tells it to misbehave, or in mode
+//! `count` to play the legal move numbered by how many moves it was sent, or
+//! in mode `clock` by how many milliseconds the side to move has left.
+
+use std::io::{self, BufRead, Write};
+use std::process;
+
+use ruschess_core::*;
+use ruschess_uci::parse_position;
+
+fn main() {
+    let mut mode = std::env::args().nth(1).unwrap_or_else(|| "first".to_string());
+    let mut state = GameState::default();
+    let mut moves_sent = 0;
+    let mut stdout = io::stdout();
+    let mut reply = |line: &str| {
+        writeln!(stdout, "{}", line).unwrap();
+        stdout.flush().unwrap();
+    };
+    let bestmove = |state: &GameState, index: usize| {
+        let moves = state.legal_moves();
+        let m = moves[index % moves.len()];
+        format!("bestmove {}", to_uci(state, m))
+    };
+
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        let (command, args) = line.split_once(' ').unwrap_or((&line, ""));
+        match command {
+            "uci" if mode != "mute" => {
+                reply("id name mock");
+                reply("option name Mode type string default first");
+                reply("uciok");
+            }
+            "setoption" => {
+                if let Some(value) = args.strip_prefix("name Mode value ") {
+                    mode = value.to_string();
+                }
+            }
+            "isready" => reply("readyok"),
+            "position" => {
+                state = parse_position(args).unwrap();
+                moves_sent = args.split(" moves ").nth(1).map_or(0, |moves| moves.split_whitespace().count());
+            }
+            "go" => match mode.as_str() {
+                "illegal" => reply("bestmove a1a1"),
+                "crash" => process::exit(1),
+                "hang" | "deaf" => reply("info string thinking forever"),
+                "count" => reply(&bestmove(&state, moves_sent)),
+                "clock" => {
+                    let key = match state.turn {
+                        PieceColor::White => "wtime",
+                        PieceColor::Black => "btime",
+                    };
+                    let mut words = args.split_whitespace().skip_while(|word| *word != key).skip(1);
+                    let time = words.next().and_then(|time| time.parse().ok()).unwrap_or(0);
+                    reply(&bestmove(&state, time));
+                }
+                _ => {
+                    reply("info depth 1 score cp 15");
+                    reply(&bestmove(&state, 0));
+                }
+            },
+            "stop" if mode == "hang" => reply(&bestmove(&state, 0)),
+            "quit" if mode != "deaf" => return,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ruschess_core::*;
+
+/// Reports the result of a search, see `Engine::start`.
+type OnDone = Box<dyn FnOnce(SearchResult) + Send>;
+
+/// The part of the engine shared by the protocol front-ends: the game being
+/// played, and a searcher that thinks about it in the background.
+pub struct Engine {
+    state: GameState,
+    /// The moves played since the position was set, to take them back.
+    history: Vec<(Move, UndoInfo)>,
+    /// `None` while a search has it.
+    searcher: Option<Searcher>,
+    search: Option<JoinHandle<Searcher>>,
+    /// The report of the running search, until the search makes it.
+    on_done: Arc<Mutex<Option<OnDone>>>,
+    stop: StopSignal,
+    /// Keeps a finished search from reporting until it is released, as an
+    /// infinite or pondering search must not report on its own.
+    hold: Arc<AtomicBool>,
+    /// Counts the searches started, so that a timer set for one search does
+    /// not stop the next.
+    generation: Arc<AtomicU64>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let searcher = Searcher::new();
+        Self {
+            state: GameState::default(),
+            history: Vec::new(),
+            stop: searcher.stop_signal(),
+            searcher: Some(searcher),
+            search: None,
+            on_done: Arc::default(),
+            hold: Arc::new(AtomicBool::new(false)),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+    pub fn history(&self) -> impl Iterator<Item = Move> + '_ {
+        self.history.iter().map(|(m, _)| *m)
+    }
+    pub fn set_position(&mut self, state: GameState) {
+        self.halt();
+        self.state = state;
+        self.history.clear();
+    }
+    /// Plays `m`, which must be legal in the current position.
+    pub fn play(&mut self, m: Move) {
+        self.halt();
+        let undo = self.state.make_move(m);
+        self.history.push((m, undo));
+    }
+    /// Takes back the last move played, if any.
+    pub fn undo(&mut self) -> Option<Move> {
+        self.halt();
+        let (m, undo) = self.history.pop()?;
+        self.state.unmake_move(m, undo);
+        Some(m)
+    }
+    /// Forgets everything learned in earlier games and sets up the start position.
+    pub fn new_game(&mut self) {
+        self.searcher().new_game();
+        self.set_position(GameState::default());
+    }
+
+    /// Stops any running search and returns the searcher, to change its settings.
+    pub fn searcher(&mut self) -> &mut Searcher {
+        self.halt();
+        self.searcher.as_mut().unwrap()
+    }
+    pub fn set_hash(&mut self, size_mb: usize) {
+        self.searcher().set_tt(Arc::new(TranspositionTable::new(size_mb)));
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.search.as_ref().is_some_and(|search| !search.is_finished())
+    }
+
+    /// Starts searching the current position in the background, sending the
+    /// progress to `sink` and the result to `on_done`. With `hold`, the result
+    /// is not sent before `stop` or `release`, even if the search ends first.
+    pub fn start<F>(&mut self, limits: SearchLimits, hold: bool, sink: impl InfoSink + 'static, on_done: F)
+    where
+        F: FnOnce(SearchResult) + Send + 'static,
+    {
+        self.halt();
+        self.stop.reset();
+        self.hold.store(hold, Ordering::Relaxed);
+        self.generation.fetch_add(1, Ordering::Relaxed);
+
+        let mut searcher = self.searcher.take().unwrap();
+        searcher.set_info_sink(sink);
+        searcher.set_history(self.earlier_hashes());
+        *self.on_done.lock().unwrap() = Some(Box::new(on_done));
+        let pending = Arc::clone(&self.on_done);
+        let state = self.state.clone();
+        let stop = self.stop.clone();
+        let hold = Arc::clone(&self.hold);
+        self.search = Some(thread::spawn(move || {
+            let result = searcher.search(&state, limits);
+            while hold.load(Ordering::Relaxed) && !stop.is_stopped() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            searcher.clear_info_sink();
+            let on_done = pending.lock().unwrap().take();
+            if let Some(on_done) = on_done {
+                on_done(result);
+            }
+            searcher
+        }));
+    }
+
+    /// The hashes of the positions before the current one, oldest first.
+    fn earlier_hashes(&self) -> Vec<u64> {
+        let mut state = self.state.clone();
+        let mut hashes: Vec<u64> = self
+            .history
+            .iter()
+            .rev()
+            .map(|&(m, undo)| {
+                state.unmake_move(m, undo);
+                state.hash()
+            })
+            .collect();
+        hashes.reverse();
+        hashes
+    }
+
+    /// Lets a held search report once it is done, and stops it after `time`
+    /// if given.
+    pub fn release(&mut self, time: Option<Duration>) {
+        self.hold.store(false, Ordering::Relaxed);
+        let Some(time) = time else {
+            return;
+        };
+        let stop = self.stop.clone();
+        let generation = Arc::clone(&self.generation);
+        let current = generation.load(Ordering::Relaxed);
+        thread::spawn(move || {
+            thread::sleep(time);
+            if generation.load(Ordering::Relaxed) == current {
+                stop.stop();
+            }
+        });
+    }
+
+    /// Asks the running search to stop and report, without waiting for it.
+    pub fn stop(&self) {
+        self.stop.stop();
+    }
+
+    /// Waits until the running search, if any, has reported. A search that
+    /// panicked reports no move, and leaves a new searcher with the default
+    /// settings in its place.
+    pub fn wait(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+        match search.join() {
+            Ok(searcher) => self.searcher = Some(searcher),
+            Err(_) => {
+                let searcher = Searcher::new();
+                self.stop = searcher.stop_signal();
+                self.searcher = Some(searcher);
+                let on_done = self.on_done.lock().unwrap().take();
+                if let Some(on_done) = on_done {
+                    on_done(SearchResult {
+                        best_move: None,
+                        score: 0,
+                        depth: 0,
+                        nodes: 0,
+                        pv: Vec::new(),
+                        lines: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Stops the running search, if any, and waits until it has reported.
+    pub fn halt(&mut self) {
+        if self.search.is_some() {
+            self.stop();
+            self.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_engine_play_and_undo() {
+        let mut engine = Engine::new();
+        let e4 = parse_uci(engine.state(), "e2e4").unwrap();
+        engine.play(e4);
+        let e5 = parse_uci(engine.state(), "e7e5").unwrap();
+        engine.play(e5);
+        assert_eq!(engine.history().collect::<Vec<_>>(), vec![e4, e5]);
+
+        assert_eq!(engine.undo(), Some(e5));
+        assert_eq!(engine.undo(), Some(e4));
+        assert_eq!(engine.undo(), None);
+        assert_eq!(engine.state().to_fen(), GameState::default().to_fen());
+    }
+
+    #[test]
+    fn test_engine_search_sees_played_positions() {
+        let mut engine = Engine::new();
+        engine.set_position(GameState::from_fen("6k1/8/8/8/8/8/2Q5/K7 b - - 10 40").unwrap());
+        for uci in ["g8h8", "c2c3", "h8g8", "c3c2"] {
+            let m = parse_uci(engine.state(), uci).unwrap();
+            engine.play(m);
+        }
+        let (sender, receiver) = mpsc::channel();
+        engine.start(SearchLimits::depth(3), false, |_| {}, move |result| sender.send(result).unwrap());
+        let result = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(result.best_move, parse_uci(engine.state(), "g8h8"));
+        assert_eq!(result.score, 0);
+        engine.wait();
+    }
+
+    #[test]
+    fn test_engine_search_reports() {
+        let mut engine = Engine::new();
+        let (sender, receiver) = mpsc::channel();
+        engine.start(SearchLimits::depth(3), false, |_| {}, move |result| sender.send(result).unwrap());
+        let result = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(result.depth, 3);
+        engine.wait();
+        assert!(!engine.is_searching());
+    }
+
+    #[test]
+    fn test_engine_survives_panicking_search() {
+        let mut engine = Engine::new();
+        engine.start(SearchLimits::depth(1), false, |_| {}, |_| panic!("lost the output"));
+        engine.wait();
+        let (sender, receiver) = mpsc::channel();
+        engine.start(SearchLimits::depth(2), false, |_| {}, move |result| sender.send(result).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_secs(10)).unwrap().best_move.is_some());
+    }
+
+    #[test]
+    fn test_engine_held_search_waits_for_release() {
+        let mut engine = Engine::new();
+        let (sender, receiver) = mpsc::channel();
+        engine.start(SearchLimits::depth(1), true, |_| {}, move |result| sender.send(result).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+        engine.release(None);
+        assert!(receiver.recv_timeout(Duration::from_secs(10)).is_ok());
+    }
+
+    #[test]
+    fn test_engine_release_with_time_stops_search() {
+        let mut engine = Engine::new();
+        let (sender, receiver) = mpsc::channel();
+        engine.start(SearchLimits::infinite(), true, |_| {}, move |result| sender.send(result).unwrap());
+        engine.release(Some(Duration::from_millis(50)));
+        assert!(receiver.recv_timeout(Duration::from_secs(10)).unwrap().best_move.is_some());
+    }
+
+    #[test]
+    fn test_engine_changing_position_stops_search() {
+        let mut engine = Engine::new();
+        let (sender, receiver) = mpsc::channel();
+        engine.start(SearchLimits::infinite(), true, |_| {}, move |result| sender.send(result).unwrap());
+        engine.set_position(GameState::default());
+        assert!(receiver.try_recv().is_ok());
+        assert!(!engine.is_searching());
+    }
+}
@@ -0,0 +1,9 @@
+mod engine;
+mod uci;
+mod uci_player;
+mod xboard;
+
+pub use engine::*;
+pub use uci::*;
+pub use uci_player::*;
+pub use xboard::*;
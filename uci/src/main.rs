@@ -0,0 +1,24 @@
+use std::io::{self, BufRead, BufReader};
+
+use ruschess_uci::{Uci, Xboard};
+
+/// Speaks UCI, unless the first command asks for the XBoard protocol.
+fn main() {
+    let mut input = BufReader::new(io::stdin());
+    let mut first = String::new();
+    while first.trim().is_empty() {
+        first.clear();
+        if input.read_line(&mut first).unwrap_or(0) == 0 {
+            return;
+        }
+    }
+
+    if first.trim() == "xboard" {
+        Xboard::new(io::stdout()).run(input);
+    } else {
+        let mut uci = Uci::new(io::stdout());
+        if uci.handle(&first) {
+            uci.run(input);
+        }
+    }
+}
@@ -0,0 +1,538 @@
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ruschess_core::*;
+
+use crate::engine::Engine;
+
+const MAX_HASH_MB: usize = 65536;
+const MAX_THREADS: usize = 256;
+const MAX_MULTI_PV: usize = 256;
+const DEFAULT_ELO: u32 = 1500;
+pub(crate) const DEFAULT_MOVE_OVERHEAD_MS: u64 = 10;
+
+/// The arguments of a `go` command.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Go {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub infinite: bool,
+    pub ponder: bool,
+}
+
+impl Go {
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut go = Self::default();
+        let mut tokens = args.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let mut value = || -> Result<u64, String> {
+                let value = tokens.next().ok_or(format!("missing value for {}", token))?;
+                value.parse().map_err(|_| format!("invalid value for {}: {}", token, value))
+            };
+            match token {
+                // the shallowest search that finds a move
+                "depth" => go.depth = Some((value()? as usize).max(1)),
+                "nodes" => go.nodes = Some(value()?),
+                "movetime" => go.movetime = Some(Duration::from_millis(value()?)),
+                "wtime" => go.wtime = Some(Duration::from_millis(value()?)),
+                "btime" => go.btime = Some(Duration::from_millis(value()?)),
+                "winc" => go.winc = Some(Duration::from_millis(value()?)),
+                "binc" => go.binc = Some(Duration::from_millis(value()?)),
+                "movestogo" => go.movestogo = Some(value()? as u32),
+                "infinite" => go.infinite = true,
+                "ponder" => go.ponder = true,
+                _ => return Err(format!("unknown go argument: {}", token)),
+            }
+        }
+        Ok(go)
+    }
+
+    /// The clock of the side to move, if one was given.
+    pub fn time_control(&self, turn: PieceColor, move_overhead: Duration) -> Option<TimeControl> {
+        let (remaining, increment) = match turn {
+            PieceColor::White => (self.wtime, self.winc),
+            PieceColor::Black => (self.btime, self.binc),
+        };
+        Some(TimeControl {
+            remaining: remaining?,
+            increment: increment.unwrap_or_default(),
+            moves_to_go: self.movestogo,
+            move_overhead,
+        })
+    }
+
+    /// The limits of the search. Infinite and pondering searches only stop
+    /// at the depth or node limit, if any.
+    pub fn limits(&self, turn: PieceColor, move_overhead: Duration) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            ..SearchLimits::infinite()
+        };
+        if !self.infinite && !self.ponder {
+            limits.time = self.movetime.map(|time| time.saturating_sub(move_overhead));
+            limits.time_control = self.time_control(turn, move_overhead);
+        }
+        limits
+    }
+}
+
+impl std::fmt::Display for Go {
+    /// Formats the `go` command with these arguments, e.g. `go wtime 1000 btime 1000`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "go")?;
+        let times = [
+            ("movetime", self.movetime),
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+        ];
+        for (name, time) in times {
+            if let Some(time) = time {
+                write!(f, " {} {}", name, time.as_millis())?;
+            }
+        }
+        if let Some(movestogo) = self.movestogo {
+            write!(f, " movestogo {}", movestogo)?;
+        }
+        if let Some(depth) = self.depth {
+            write!(f, " depth {}", depth)?;
+        }
+        if let Some(nodes) = self.nodes {
+            write!(f, " nodes {}", nodes)?;
+        }
+        if self.infinite {
+            write!(f, " infinite")?;
+        }
+        if self.ponder {
+            write!(f, " ponder")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a score as `cp <centipawns>` or `mate <moves>`.
+pub fn format_score(score: i32) -> String {
+    match mate_distance(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+/// Reads the score of the best line from an `info` line, the inverse of
+/// `format_score`. Scores of the other lines of a MultiPV search are skipped.
+pub fn parse_info_score(line: &str) -> Option<i32> {
+    let mut words = line.strip_prefix("info ")?.split_whitespace();
+    let mut score = None;
+    while let Some(word) = words.next() {
+        match word {
+            "multipv" if words.next() != Some("1") => return None,
+            "string" => break,
+            "score" => {
+                score = match (words.next(), words.next().and_then(|value| value.parse::<i32>().ok())) {
+                    (Some("cp"), Some(cp)) => Some(cp),
+                    (Some("mate"), Some(moves)) if moves > 0 => Some(MATE_SCORE - (2 * moves - 1)),
+                    (Some("mate"), Some(moves)) => Some(-MATE_SCORE - 2 * moves),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+    score
+}
+
+pub fn format_info(state: &GameState, info: &SearchInfo) -> String {
+    let pv: Vec<String> = info.pv.iter().map(|m| to_uci(state, *m)).collect();
+    format!(
+        "info depth {} seldepth {} multipv {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        info.depth,
+        info.seldepth,
+        info.multi_pv,
+        format_score(info.score),
+        info.nodes,
+        info.nps,
+        info.hashfull,
+        info.time.as_millis(),
+        pv.join(" ")
+    )
+}
+
+pub fn format_bestmove(state: &GameState, result: &SearchResult) -> String {
+    let Some(best_move) = result.best_move else {
+        return "bestmove 0000".to_string();
+    };
+    let mut line = format!("bestmove {}", to_uci(state, best_move));
+    if let Some(ponder) = result.pv.get(1) {
+        let mut after = state.clone();
+        after.apply_move(best_move);
+        line.push_str(&format!(" ponder {}", to_uci(&after, *ponder)));
+    }
+    line
+}
+
+/// Parses the arguments of a `position` command, `startpos` or `fen <fen>`,
+/// followed by the moves played from there.
+pub fn parse_position(args: &str) -> Result<GameState, String> {
+    let (mut state, moves) = parse_position_moves(args)?;
+    for m in moves {
+        state.apply_move(m);
+    }
+    Ok(state)
+}
+
+/// Like `parse_position`, but returns the position set up and the moves
+/// played from it rather than the position they lead to.
+pub fn parse_position_moves(args: &str) -> Result<(GameState, Vec<Move>), String> {
+    let (setup, moves) = match args.split_once("moves") {
+        Some((setup, moves)) => (setup.trim(), moves),
+        None => (args.trim(), ""),
+    };
+    let start = if setup == "startpos" {
+        GameState::default()
+    } else if let Some(fen) = setup.strip_prefix("fen") {
+        GameState::from_fen(fen.trim()).map_err(|error| format!("invalid fen: {:?}", error))?
+    } else {
+        return Err(format!("invalid position: {}", args));
+    };
+    let mut state = start.clone();
+    let mut played = Vec::new();
+    for uci in moves.split_whitespace() {
+        let m = parse_uci(&state, uci).ok_or(format!("illegal move: {}", uci))?;
+        state.apply_move(m);
+        played.push(m);
+    }
+    Ok((start, played))
+}
+
+/// Formats a `position` command for `moves` played from `start`.
+pub fn format_position(start: &GameState, moves: &[Move]) -> String {
+    let mut command = format!("position fen {}", start.to_fen());
+    if !moves.is_empty() {
+        command.push_str(" moves");
+        let mut state = start.clone();
+        for m in moves {
+            command.push(' ');
+            command.push_str(&to_uci(&state, *m));
+            state.apply_move(*m);
+        }
+    }
+    command
+}
+
+pub(crate) fn send<W: Write>(output: &Mutex<W>, line: &str) {
+    let mut output = output.lock().unwrap();
+    // a closed output means the GUI is gone, and there is nobody left to tell
+    let _ = writeln!(output, "{}", line);
+    let _ = output.flush();
+}
+
+/// A UCI protocol session, reading commands line by line and writing the
+/// replies to `output`.
+pub struct Uci<W> {
+    engine: Engine,
+    output: Arc<Mutex<W>>,
+    move_overhead: Duration,
+    /// How long to keep thinking after a `ponderhit`.
+    ponder_time: Option<Duration>,
+    skill_level: u8,
+    /// Whether to play at `elo` rather than at `skill_level`.
+    limit_strength: bool,
+    elo: u32,
+    /// Whether castling is written as the king taking its own rook.
+    chess960: bool,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            engine: Engine::new(),
+            output: Arc::new(Mutex::new(output)),
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            ponder_time: None,
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: DEFAULT_ELO,
+            chess960: false,
+        }
+    }
+
+    pub fn engine(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line);
+    }
+
+    /// Handles one line of input, and returns `false` once the session is over.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let result = match command {
+            "uci" => {
+                self.identify();
+                Ok(())
+            }
+            "isready" => {
+                self.send("readyok");
+                Ok(())
+            }
+            "ucinewgame" => {
+                self.engine.new_game();
+                Ok(())
+            }
+            "setoption" => self.set_option(args),
+            "position" => parse_position_moves(args).map(|(mut start, moves)| {
+                // positions that can only be Chess960 are read as such anyway
+                start.chess960 |= self.chess960;
+                // played one by one, so that the search knows the positions before
+                self.engine.set_position(start);
+                for m in moves {
+                    self.engine.play(m);
+                }
+            }),
+            "go" => Go::parse(args).map(|go| self.go(go)),
+            "stop" => {
+                self.engine.stop();
+                Ok(())
+            }
+            "ponderhit" => {
+                self.engine.release(self.ponder_time);
+                Ok(())
+            }
+            "quit" => {
+                self.engine.halt();
+                return false;
+            }
+            "" | "debug" | "register" => Ok(()),
+            _ => Err(format!("unknown command: {}", command)),
+        };
+        if let Err(error) = result {
+            self.send(&format!("info string {}", error));
+        }
+        true
+    }
+
+    /// Handles commands from `input` until `quit` or the end of the input,
+    /// and waits for the last search to report.
+    pub fn run(&mut self, input: impl BufRead) {
+        for line in input.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if !self.handle(&line) {
+                return;
+            }
+        }
+        self.engine.wait();
+    }
+
+    fn identify(&self) {
+        self.send(&format!("id name ruschess {}", env!("CARGO_PKG_VERSION")));
+        self.send("id author the ruschess authors");
+        self.send(&format!(
+            "option name Hash type spin default {} min 1 max {}",
+            TranspositionTable::DEFAULT_SIZE_MB,
+            MAX_HASH_MB
+        ));
+        self.send(&format!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS));
+        self.send(&format!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV));
+        self.send(&format!(
+            "option name Move Overhead type spin default {} min 0 max 5000",
+            DEFAULT_MOVE_OVERHEAD_MS
+        ));
+        self.send("option name Ponder type check default false");
+        self.send(&format!(
+            "option name Skill Level type spin default {} min 0 max {}",
+            MAX_SKILL_LEVEL, MAX_SKILL_LEVEL
+        ));
+        self.send("option name UCI_LimitStrength type check default false");
+        self.send(&format!(
+            "option name UCI_Elo type spin default {} min {} max {}",
+            DEFAULT_ELO, MIN_ELO, MAX_ELO
+        ));
+        self.send("option name UCI_Chess960 type check default false");
+        self.send("uciok");
+    }
+
+    fn set_option(&mut self, args: &str) -> Result<(), String> {
+        let args = args.trim().strip_prefix("name").ok_or("setoption needs a name")?;
+        let (name, value) = match args.split_once(" value") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (args.trim(), ""),
+        };
+        let invalid = || format!("invalid value for {}: {}", name, value);
+        let number = |max: usize| -> Result<usize, String> {
+            value.parse::<usize>().map(|number| number.min(max)).map_err(|_| invalid())
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "hash" => self.engine.set_hash(number(MAX_HASH_MB)?.max(1)),
+            "threads" => self.engine.searcher().set_threads(number(MAX_THREADS)?),
+            "multipv" => self.engine.searcher().set_multi_pv(number(MAX_MULTI_PV)?),
+            "move overhead" => self.move_overhead = Duration::from_millis(number(5000)? as u64),
+            // pondering needs nothing set up, the option only tells the GUI it may ask for it
+            "ponder" => {}
+            "skill level" => self.skill_level = number(MAX_SKILL_LEVEL as usize)? as u8,
+            "uci_limitstrength" => self.limit_strength = value.parse().map_err(|_| invalid())?,
+            "uci_elo" => self.elo = (number(MAX_ELO as usize)? as u32).max(MIN_ELO),
+            "uci_chess960" => self.chess960 = value.parse().map_err(|_| invalid())?,
+            _ => return Err(format!("unknown option: {}", name)),
+        }
+        let skill = if self.limit_strength {
+            Skill::from_elo(self.elo)
+        } else {
+            Skill::new(self.skill_level)
+        };
+        self.engine.searcher().set_skill(skill);
+        Ok(())
+    }
+
+    fn go(&mut self, go: Go) {
+        let state = self.engine.state().clone();
+        let limits = go.limits(state.turn, self.move_overhead);
+        self.ponder_time = if go.ponder {
+            let time_control = go.time_control(state.turn, self.move_overhead);
+            go.movetime
+                .or_else(|| time_control.map(|control| TimeManager::new(control, &state).soft_limit()))
+        } else {
+            None
+        };
+
+        let info_output = Arc::clone(&self.output);
+        let info_state = state.clone();
+        let sink = move |info: SearchInfo| send(&info_output, &format_info(&info_state, &info));
+        let output = Arc::clone(&self.output);
+        let on_done = move |result: SearchResult| send(&output, &format_bestmove(&state, &result));
+        self.engine.start(limits, go.infinite || go.ponder, sink, on_done);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn test_parse_go() {
+        let go = Go::parse("wtime 1000 btime 2000 winc 10 binc 20 movestogo 5 depth 8 nodes 100").unwrap();
+        assert_eq!(
+            go,
+            Go {
+                depth: Some(8),
+                nodes: Some(100),
+                wtime: Some(Duration::from_millis(1000)),
+                btime: Some(Duration::from_millis(2000)),
+                winc: Some(Duration::from_millis(10)),
+                binc: Some(Duration::from_millis(20)),
+                movestogo: Some(5),
+                ..Go::default()
+            }
+        );
+        assert!(Go::parse("infinite").unwrap().infinite);
+        assert!(Go::parse("ponder movetime 10").unwrap().ponder);
+        assert_eq!(Go::parse("depth 0").unwrap().depth, Some(1));
+    }
+
+    #[test_case("go"; "no arguments")]
+    #[test_case("go movetime 100 depth 3"; "fixed limits")]
+    #[test_case("go wtime 1000 btime 2000 winc 10 binc 20 movestogo 5"; "clock")]
+    #[test_case("go infinite ponder"; "flags")]
+    fn test_go_round_trip(command: &str) {
+        let go = Go::parse(command.strip_prefix("go").unwrap()).unwrap();
+        assert_eq!(go.to_string(), command);
+    }
+
+    #[test_case("depth"; "missing value")]
+    #[test_case("depth x"; "invalid value")]
+    #[test_case("searchmoves e2e4"; "unknown argument")]
+    fn test_parse_go_invalid(args: &str) {
+        assert!(Go::parse(args).is_err());
+    }
+
+    #[test]
+    fn test_go_limits() {
+        let overhead = Duration::from_millis(10);
+        let go = Go::parse("wtime 1000 btime 2000 binc 20").unwrap();
+        let limits = go.limits(PieceColor::Black, overhead);
+        let control = limits.time_control.unwrap();
+        assert_eq!(control.remaining, Duration::from_millis(2000));
+        assert_eq!(control.increment, Duration::from_millis(20));
+        assert_eq!(control.move_overhead, overhead);
+
+        let go = Go::parse("movetime 100 depth 3").unwrap();
+        let limits = go.limits(PieceColor::White, overhead);
+        assert_eq!(limits.time, Some(Duration::from_millis(90)));
+        assert_eq!(limits.depth, Some(3));
+
+        let go = Go::parse("ponder wtime 1000 btime 1000 depth 5").unwrap();
+        assert_eq!(go.limits(PieceColor::White, overhead), SearchLimits::depth(5));
+    }
+
+    #[test_case("startpos", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"; "start position")]
+    #[test_case("startpos moves e2e4 e7e5", "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"; "start position with moves")]
+    #[test_case("fen 4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1 moves e1g1", "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1"; "fen with moves")]
+    fn test_parse_position(args: &str, fen: &str) {
+        assert_eq!(parse_position(args).unwrap().to_fen(), fen);
+    }
+
+    #[test]
+    fn test_format_position() {
+        let start = GameState::default();
+        let state = parse_position("startpos moves e2e4 e7e5").unwrap();
+        assert_eq!(format_position(&state, &[]), format!("position fen {}", state.to_fen()));
+
+        let e4 = parse_uci(&start, "e2e4").unwrap();
+        let command = format_position(&start, &[e4]);
+        assert_eq!(command, format!("position fen {} moves e2e4", start.to_fen()));
+        let after = parse_position(command.strip_prefix("position").unwrap()).unwrap();
+        assert_eq!(after.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+    }
+
+    #[test]
+    fn test_position_keeps_moves() {
+        let mut uci = Uci::new(Vec::new());
+        uci.handle("position startpos moves e2e4 e7e5");
+        let mut state = GameState::default();
+        let e4 = parse_uci(&state, "e2e4").unwrap();
+        state.apply_move(e4);
+        let e5 = parse_uci(&state, "e7e5").unwrap();
+        assert_eq!(uci.engine().history().collect::<Vec<_>>(), vec![e4, e5]);
+        assert_eq!(
+            uci.engine().state().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+    }
+
+    #[test_case("startpos moves e2e5"; "illegal move")]
+    #[test_case("fen 8/8 w"; "invalid fen")]
+    #[test_case("nowhere"; "invalid setup")]
+    fn test_parse_position_invalid(args: &str) {
+        assert!(parse_position(args).is_err());
+    }
+
+    #[test]
+    fn test_format_score() {
+        assert_eq!(format_score(35), "cp 35");
+        assert_eq!(format_score(MATE_SCORE - 3), "mate 2");
+        assert_eq!(format_score(-MATE_SCORE + 2), "mate -1");
+    }
+
+    #[test_case("info depth 5 score cp 35 nodes 100 pv e2e4", Some(35))]
+    #[test_case("info depth 9 multipv 1 score mate 2 pv a1a8", Some(MATE_SCORE - 3))]
+    #[test_case("info depth 9 score mate -1 pv g1h1", Some(-MATE_SCORE + 2))]
+    #[test_case("info depth 5 multipv 2 score cp 10 pv d2d4", None)]
+    #[test_case("info string score cp 10", None; "text")]
+    #[test_case("info depth 5 nodes 100", None; "no score")]
+    fn test_parse_info_score(line: &str, expected: Option<i32>) {
+        assert_eq!(parse_info_score(line), expected);
+    }
+}
@@ -0,0 +1,315 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ruschess_core::*;
+
+use crate::uci::{format_position, parse_info_score, Go};
+
+/// How long an engine gets to report its move after it has been told to stop.
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Why an external engine did not come up with a move.
+#[derive(Debug)]
+pub enum UciPlayerError {
+    /// The engine executable could not be started.
+    Spawn(io::Error),
+    /// Talking to the engine failed.
+    Io(io::Error),
+    /// The engine exited, or closed its output.
+    EngineExited,
+    /// The engine did not send the expected reply in time.
+    Timeout(&'static str),
+    /// The engine sent a move that is not legal in the position.
+    IllegalMove(String),
+}
+
+impl std::fmt::Display for UciPlayerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UciPlayerError::Spawn(error) => write!(f, "cannot start the engine: {}", error),
+            UciPlayerError::Io(error) => write!(f, "cannot talk to the engine: {}", error),
+            UciPlayerError::EngineExited => write!(f, "the engine exited"),
+            UciPlayerError::Timeout(reply) => write!(f, "the engine did not send {} in time", reply),
+            UciPlayerError::IllegalMove(m) => write!(f, "the engine played an illegal move: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for UciPlayerError {}
+
+/// A running engine process.
+struct UciProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// The score of the last `info` line that had one, from the engine's side.
+    score: Option<i32>,
+}
+
+impl UciProcess {
+    fn spawn(command: &Path, args: &[String]) -> Result<Self, UciPlayerError> {
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(UciPlayerError::Spawn)?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            score: None,
+        })
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), UciPlayerError> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::BrokenPipe => UciPlayerError::EngineExited,
+                _ => UciPlayerError::Io(error),
+            })
+    }
+
+    /// Waits until `deadline` for a line starting with `prefix`, skipping
+    /// others but the scores they report.
+    fn expect(&mut self, prefix: &'static str, deadline: Instant) -> Result<String, UciPlayerError> {
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(timeout) {
+                Ok(line) if line.starts_with(prefix) => return Ok(line),
+                Ok(line) => {
+                    if let Some(score) = parse_info_score(&line) {
+                        self.score = Some(score);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => return Err(UciPlayerError::Timeout(prefix)),
+                Err(RecvTimeoutError::Disconnected) => return Err(UciPlayerError::EngineExited),
+            }
+        }
+    }
+}
+
+impl Drop for UciProcess {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A `Player` backed by an external UCI engine, run as a subprocess. The
+/// engine is started on the first move, and started again after it crashed
+/// or had to be killed. As a `Player` it resigns when the engine fails.
+pub struct UciPlayer {
+    command: PathBuf,
+    args: Vec<String>,
+    /// Sent as `setoption` after the handshake.
+    pub options: Vec<(String, String)>,
+    /// The arguments of the `go` command, such as `movetime` or the clocks.
+    /// The clocks given through `Player::on_clock` take precedence.
+    pub go: Go,
+    /// How long the engine may go over the time it was given, or think in
+    /// total if it was given no time.
+    pub timeout: Duration,
+    /// How long the engine may take to start up, or to get ready.
+    pub handshake_timeout: Duration,
+    process: Option<UciProcess>,
+    last_error: Option<UciPlayerError>,
+    /// The start position of the game played as a `Player`, and the moves
+    /// since, so that the engine sees the whole game and its repetitions.
+    game: Option<(GameState, Vec<Move>)>,
+    /// The clocks of White and Black for the next move, if the game is clocked.
+    clock: Option<(TimeControl, TimeControl)>,
+    /// The score the engine reported for its last move.
+    score: Option<i32>,
+}
+
+impl UciPlayer {
+    pub fn new(command: impl Into<PathBuf>) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            options: Vec::new(),
+            go: Go {
+                movetime: Some(Duration::from_millis(100)),
+                ..Go::default()
+            },
+            timeout: Duration::from_secs(10),
+            handshake_timeout: Duration::from_secs(10),
+            process: None,
+            last_error: None,
+            game: None,
+            clock: None,
+            score: None,
+        }
+    }
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn with_option(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.options.push((name.into(), value.into()));
+        self
+    }
+    pub fn with_go(mut self, go: Go) -> Self {
+        self.go = go;
+        self
+    }
+
+    fn start(&self) -> Result<UciProcess, UciPlayerError> {
+        let mut process = UciProcess::spawn(&self.command, &self.args)?;
+        let deadline = Instant::now() + self.handshake_timeout;
+        process.send("uci")?;
+        process.expect("uciok", deadline)?;
+        for (name, value) in &self.options {
+            process.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        process.send("isready")?;
+        process.expect("readyok", deadline)?;
+        Ok(process)
+    }
+
+    /// The reason the engine last failed to move, and this player resigned.
+    pub fn last_error(&self) -> Option<&UciPlayerError> {
+        self.last_error.as_ref()
+    }
+
+    /// Tells the engine, if it is running, that a new game starts.
+    pub fn new_game(&mut self) -> Result<(), UciPlayerError> {
+        let Some(running) = self.process.as_mut() else {
+            return Ok(());
+        };
+        let result = running
+            .send("ucinewgame")
+            .and_then(|_| running.send("isready"))
+            .and_then(|_| running.expect("readyok", Instant::now() + self.handshake_timeout));
+        if result.is_err() {
+            self.process = None;
+        }
+        result.map(|_| ())
+    }
+
+    /// Asks the engine for its move after `moves` have been played from
+    /// `start`. On any error the engine process is killed, and started again
+    /// on the next call.
+    pub fn try_get_move(&mut self, start: &GameState, moves: &[Move]) -> Result<Move, UciPlayerError> {
+        let mut process = match self.process.take() {
+            Some(process) => process,
+            None => self.start()?,
+        };
+        let result = self.think(&mut process, start, moves);
+        self.score = result.as_ref().ok().and(process.score);
+        if result.is_ok() {
+            self.process = Some(process);
+        }
+        result
+    }
+
+    fn think(&self, process: &mut UciProcess, start: &GameState, moves: &[Move]) -> Result<Move, UciPlayerError> {
+        let mut state = start.clone();
+        for m in moves {
+            state.apply_move(*m);
+        }
+        let mut go = self.go.clone();
+        if let Some((white, black)) = self.clock {
+            go.wtime = Some(white.remaining);
+            go.btime = Some(black.remaining);
+            go.winc = Some(white.increment);
+            go.binc = Some(black.increment);
+            go.movestogo = match state.turn {
+                PieceColor::White => white.moves_to_go,
+                PieceColor::Black => black.moves_to_go,
+            };
+        }
+        let clock = match state.turn {
+            PieceColor::White => go.wtime,
+            PieceColor::Black => go.btime,
+        };
+        let allowed = go.movetime.or(clock).unwrap_or_default() + self.timeout;
+
+        process.score = None;
+        process.send(&format_position(start, moves))?;
+        process.send(&go.to_string())?;
+        let line = match process.expect("bestmove", Instant::now() + allowed) {
+            Err(UciPlayerError::Timeout(_)) => {
+                process.send("stop")?;
+                process.expect("bestmove", Instant::now() + STOP_TIMEOUT)?
+            }
+            result => result?,
+        };
+        let uci = line.split_whitespace().nth(1).unwrap_or_default();
+        parse_uci(&state, uci).ok_or_else(|| UciPlayerError::IllegalMove(uci.to_string()))
+    }
+}
+
+impl Player for UciPlayer {
+    fn get_move(&mut self, state: &GameState) -> PlayerAction {
+        // without a game to follow, or if it went astray, start one from here
+        let follows = self.game.as_ref().is_some_and(|(start, moves)| {
+            let mut current = start.clone();
+            for m in moves {
+                current.apply_move(*m);
+            }
+            current.hash() == state.hash()
+        });
+        if !follows {
+            self.game = Some((state.clone(), Vec::new()));
+        }
+        let (start, mut moves) = self.game.take().unwrap();
+        let result = self.try_get_move(&start, &moves);
+        self.clock = None;
+        if let Ok(m) = result {
+            moves.push(m);
+        }
+        self.game = Some((start, moves));
+        match result {
+            Ok(m) => PlayerAction::Move(m),
+            Err(error) => {
+                self.last_error = Some(error);
+                PlayerAction::Resign
+            }
+        }
+    }
+    fn on_game_start(&mut self, _color: PieceColor, start: &GameState) {
+        self.game = Some((start.clone(), Vec::new()));
+        // an engine that does not survive this starts afresh with the first move
+        let _ = self.new_game();
+    }
+    fn on_opponent_move(&mut self, m: Move) {
+        if let Some((_, moves)) = &mut self.game {
+            moves.push(m);
+        }
+    }
+    fn on_clock(&mut self, white: TimeControl, black: TimeControl) {
+        self.clock = Some((white, black));
+    }
+    fn last_score(&self) -> Option<i32> {
+        self.score
+    }
+}
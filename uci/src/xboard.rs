@@ -0,0 +1,412 @@
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use ruschess_core::*;
+
+use crate::engine::Engine;
+use crate::uci::{send, DEFAULT_MOVE_OVERHEAD_MS};
+
+/// Scores at least this far from zero announce a mate, in the usual XBoard
+/// convention of adding the number of moves to mate.
+const XBOARD_MATE_SCORE: i32 = 100000;
+
+/// The time control set by `level`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    /// Moves per time control, 0 for the whole game.
+    pub moves: u32,
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            moves: 40,
+            base: Duration::from_secs(5 * 60),
+            increment: Duration::ZERO,
+        }
+    }
+}
+
+impl Level {
+    /// Parses the arguments of `level`: the moves per time control, the base
+    /// time in minutes or minutes:seconds, and the increment in seconds.
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid level: {}", args);
+        let [moves, base, increment] = args.split_whitespace().collect::<Vec<_>>()[..] else {
+            return Err(invalid());
+        };
+        let base = match base.split_once(':') {
+            Some((minutes, seconds)) => {
+                let minutes: u64 = minutes.parse().map_err(|_| invalid())?;
+                let seconds: u64 = seconds.parse().map_err(|_| invalid())?;
+                Duration::from_secs(minutes * 60 + seconds)
+            }
+            None => Duration::from_secs(base.parse::<u64>().map_err(|_| invalid())? * 60),
+        };
+        let increment: f64 = increment.parse().map_err(|_| invalid())?;
+        Ok(Self {
+            moves: moves.parse().map_err(|_| invalid())?,
+            base,
+            increment: Duration::try_from_secs_f64(increment).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Formats a search info as a line of XBoard thinking output: depth, score,
+/// time in centiseconds, nodes and the principal variation.
+pub fn format_thinking(state: &GameState, info: &SearchInfo) -> String {
+    let score = match mate_distance(info.score) {
+        Some(moves) if moves > 0 => XBOARD_MATE_SCORE + moves,
+        Some(moves) => -XBOARD_MATE_SCORE + moves,
+        None => info.score,
+    };
+    format!(
+        "{} {} {} {} {}",
+        info.depth,
+        score,
+        info.time.as_millis() / 10,
+        info.nodes,
+        line_to_san(state, &info.pv)
+    )
+}
+
+/// Returns the result to announce if the game is over in `state`.
+pub fn game_result(state: &GameState) -> Option<&'static str> {
+    if state.is_checkmate() {
+        Some(match state.turn {
+            PieceColor::White => "0-1 {Black mates}",
+            PieceColor::Black => "1-0 {White mates}",
+        })
+    } else if state.is_stalemate() {
+        Some("1/2-1/2 {Stalemate}")
+    } else if state.is_50_move_rule() {
+        Some("1/2-1/2 {Fifty move rule}")
+    } else {
+        None
+    }
+}
+
+enum Event {
+    Input(String),
+    /// A search finished, tagged with the id it was started with.
+    Done(u64, SearchResult),
+}
+
+/// A CECP (XBoard/WinBoard protocol) session.
+pub struct Xboard<W> {
+    engine: Engine,
+    output: Arc<Mutex<W>>,
+    events: Sender<Event>,
+    receiver: Receiver<Event>,
+    /// The side the engine plays, `None` in force mode.
+    engine_color: Option<PieceColor>,
+    analyzing: bool,
+    post: bool,
+    level: Level,
+    move_time: Option<Duration>,
+    depth: Option<usize>,
+    time_left: Option<Duration>,
+    /// Tags the current search, so that the results of cancelled ones are ignored.
+    search_id: u64,
+}
+
+impl<W: Write + Send + 'static> Xboard<W> {
+    pub fn new(output: W) -> Self {
+        let (events, receiver) = mpsc::channel();
+        Self {
+            engine: Engine::new(),
+            output: Arc::new(Mutex::new(output)),
+            events,
+            receiver,
+            engine_color: Some(PieceColor::Black),
+            analyzing: false,
+            post: false,
+            level: Level::default(),
+            move_time: None,
+            depth: None,
+            time_left: None,
+            search_id: 0,
+        }
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line);
+    }
+
+    /// Handles commands from `input` until `quit` or the end of the input,
+    /// playing the engine's moves as its searches finish.
+    pub fn run(&mut self, input: impl BufRead + Send + 'static) {
+        let events = self.events.clone();
+        thread::spawn(move || {
+            for line in input.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if events.send(Event::Input(line)).is_err() {
+                    break;
+                }
+            }
+            let _ = events.send(Event::Input("quit".to_string()));
+        });
+        while let Ok(event) = self.receiver.recv() {
+            match event {
+                Event::Input(line) => {
+                    if !self.handle(&line) {
+                        return;
+                    }
+                }
+                Event::Done(id, result) if id == self.search_id => self.play_result(result),
+                Event::Done(..) => {}
+            }
+        }
+    }
+
+    /// Handles one line of input, and returns `false` once the session is over.
+    fn handle(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match command {
+            "protover" => self.send(&format!(
+                "feature myname=\"ruschess {}\" setboard=1 usermove=1 ping=1 time=1 colors=0 \
+                 playother=1 analyze=1 sigint=0 sigterm=0 reuse=1 done=1",
+                env!("CARGO_PKG_VERSION")
+            )),
+            "new" => {
+                self.cancel();
+                self.engine.new_game();
+                self.engine_color = Some(PieceColor::Black);
+                self.depth = None;
+                self.move_time = None;
+                self.restart();
+            }
+            "force" => {
+                self.cancel();
+                self.engine_color = None;
+            }
+            "go" => {
+                self.cancel();
+                self.engine_color = Some(self.engine.state().turn);
+                self.restart();
+            }
+            "playother" => {
+                self.cancel();
+                self.engine_color = Some(self.engine.state().turn.opposite());
+            }
+            "setboard" => {
+                self.cancel();
+                match GameState::from_fen(args) {
+                    Ok(state) => self.engine.set_position(state),
+                    Err(_) => self.send("tellusererror Illegal position"),
+                }
+                self.restart();
+            }
+            "usermove" => self.user_move(args),
+            "undo" | "remove" => {
+                self.cancel();
+                let count = if command == "undo" { 1 } else { 2 };
+                for _ in 0..count {
+                    self.engine.undo();
+                }
+                if self.analyzing {
+                    self.restart();
+                }
+            }
+            "level" => match Level::parse(args) {
+                Ok(level) => {
+                    self.level = level;
+                    self.move_time = None;
+                }
+                Err(error) => self.send(&format!("Error ({}): level", error)),
+            },
+            "st" => {
+                let seconds = args.trim().parse().ok();
+                match seconds.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()) {
+                    Some(time) => self.move_time = Some(time),
+                    None => self.send(&format!("Error (invalid time): {}", line)),
+                }
+            }
+            "sd" => match args.trim().parse::<usize>() {
+                // the shallowest search that finds a move, as for UCI
+                Ok(depth) => self.depth = Some(depth.max(1)),
+                Err(_) => self.send(&format!("Error (invalid depth): {}", line)),
+            },
+            "time" => match args.trim().parse::<u64>() {
+                Ok(centiseconds) => self.time_left = Some(Duration::from_millis(centiseconds * 10)),
+                Err(_) => self.send(&format!("Error (invalid time): {}", line)),
+            },
+            "analyze" => {
+                self.cancel();
+                self.analyzing = true;
+                self.engine_color = None;
+                self.restart();
+            }
+            "exit" => {
+                self.cancel();
+                self.analyzing = false;
+            }
+            "result" => {
+                self.cancel();
+                self.engine_color = None;
+            }
+            "?" => self.engine.stop(),
+            "ping" => self.send(&format!("pong {}", args.trim())),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "quit" => {
+                self.cancel();
+                return false;
+            }
+            "xboard" | "accepted" | "rejected" | "otim" | "hard" | "easy" | "random" | "computer" | "name"
+            | "rating" | "ics" | "." | "" => {}
+            // before usermove is accepted, moves come on their own
+            _ if parse_uci(self.engine.state(), command).is_some() => self.user_move(command),
+            _ => self.send(&format!("Error (unknown command): {}", command)),
+        }
+        true
+    }
+
+    fn user_move(&mut self, uci: &str) {
+        let uci = uci.trim();
+        let Some(m) = parse_uci(self.engine.state(), uci) else {
+            self.send(&format!("Illegal move: {}", uci));
+            return;
+        };
+        self.cancel();
+        self.engine.play(m);
+        self.restart();
+    }
+
+    /// Stops the current search without playing its result.
+    fn cancel(&mut self) {
+        self.search_id += 1;
+        self.engine.halt();
+    }
+
+    /// Starts analysing or thinking about a move, if there is anything to do
+    /// in the current position.
+    fn restart(&mut self) {
+        let state = self.engine.state().clone();
+        if game_result(&state).is_some() && !self.analyzing {
+            return;
+        }
+        if !self.analyzing && self.engine_color != Some(state.turn) {
+            return;
+        }
+
+        let limits = if self.analyzing {
+            SearchLimits {
+                depth: self.depth,
+                ..SearchLimits::infinite()
+            }
+        } else {
+            self.limits(&state)
+        };
+        self.search_id += 1;
+        let id = self.search_id;
+        let events = self.events.clone();
+        let output = Arc::clone(&self.output);
+        let post = self.post || self.analyzing;
+        let info_state = state.clone();
+        let sink = move |info: SearchInfo| {
+            if post {
+                send(&output, &format_thinking(&info_state, &info));
+            }
+        };
+        let on_done = move |result| {
+            let _ = events.send(Event::Done(id, result));
+        };
+        self.engine.start(limits, self.analyzing, sink, on_done);
+    }
+
+    fn limits(&self, state: &GameState) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            ..SearchLimits::infinite()
+        };
+        if let Some(time) = self.move_time {
+            limits.time = Some(time);
+        } else {
+            let moves = self.level.moves as usize;
+            limits.time_control = Some(TimeControl {
+                remaining: self.time_left.unwrap_or(self.level.base),
+                increment: self.level.increment,
+                moves_to_go: (moves > 0).then(|| (moves - (state.fullmove_number - 1) % moves) as u32),
+                move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            });
+        }
+        limits
+    }
+
+    fn play_result(&mut self, result: SearchResult) {
+        if self.analyzing || self.engine_color != Some(self.engine.state().turn) {
+            return;
+        }
+        if let Some(m) = result.best_move {
+            let uci = to_uci(self.engine.state(), m);
+            self.engine.play(m);
+            self.send(&format!("move {}", uci));
+        }
+        if let Some(result) = game_result(self.engine.state()) {
+            self.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("40 5 0", 40, 300, 0.0; "minutes")]
+    #[test_case("0 2:30 1", 0, 150, 1.0; "minutes and seconds")]
+    #[test_case("0 1 0.5", 0, 60, 0.5; "fractional increment")]
+    fn test_parse_level(args: &str, moves: u32, base: u64, increment: f64) {
+        let level = Level::parse(args).unwrap();
+        assert_eq!(level.moves, moves);
+        assert_eq!(level.base, Duration::from_secs(base));
+        assert_eq!(level.increment, Duration::from_secs_f64(increment));
+    }
+
+    #[test_case("40 5"; "missing increment")]
+    #[test_case("x 5 0"; "invalid moves")]
+    #[test_case("40 5:x 0"; "invalid seconds")]
+    #[test_case("40 5 -1"; "negative increment")]
+    fn test_parse_level_invalid(args: &str) {
+        assert!(Level::parse(args).is_err());
+    }
+
+    #[test_case("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1", None; "ongoing")]
+    #[test_case("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", Some("1-0 {White mates}"); "white mates")]
+    #[test_case("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1", Some("1/2-1/2 {Stalemate}"); "stalemate")]
+    #[test_case("k7/8/1K6/8/8/8/8/8 b - - 100 80", Some("1/2-1/2 {Fifty move rule}"); "fifty moves")]
+    fn test_game_result(fen: &str, expected: Option<&str>) {
+        assert_eq!(game_result(&GameState::from_fen(fen).unwrap()), expected);
+    }
+
+    #[test]
+    fn test_format_thinking() {
+        let info = SearchInfo {
+            depth: 3,
+            seldepth: 5,
+            multi_pv: 1,
+            score: MATE_SCORE - 1,
+            nodes: 1234,
+            nps: 0,
+            hashfull: 0,
+            time: Duration::from_millis(150),
+            pv: Vec::new(),
+        };
+        assert_eq!(format_thinking(&GameState::default(), &info), "3 100001 15 1234 ");
+        let info = SearchInfo {
+            score: -25,
+            pv: vec![parse_uci(&GameState::default(), "e2e4").unwrap()],
+            ..info
+        };
+        assert_eq!(format_thinking(&GameState::default(), &info), "3 -25 15 1234 1. e4");
+    }
+}
@@ -0,0 +1,154 @@
+use std::time::{Duration, Instant};
+
+use ruschess_core::*;
+use ruschess_uci::*;
+use test_case::test_case;
+
+fn mock_engine(mode: &str) -> UciPlayer {
+    UciPlayer::new(env!("CARGO_BIN_EXE_mock_uci_engine")).with_args([mode])
+}
+
+#[test]
+fn test_plays_the_engine_move() {
+    let mut player = mock_engine("first");
+    let state = GameState::default();
+    let expected = PlayerAction::Move(state.legal_moves()[0]);
+    assert_eq!(player.get_move(&state), expected);
+    // the process is kept for the next game
+    player.on_game_start(PieceColor::White, &state);
+    assert_eq!(player.get_move(&state), expected);
+    assert!(player.last_error().is_none());
+}
+
+#[test]
+fn test_reports_the_engine_score() {
+    let state = GameState::default();
+    let mut player = mock_engine("first");
+    player.get_move(&state);
+    assert_eq!(player.last_score(), Some(15));
+    // an engine that says nothing about its score has none
+    let mut player = mock_engine("count");
+    player.get_move(&state);
+    assert_eq!(player.last_score(), None);
+}
+
+#[test]
+fn test_sends_fen_and_moves() {
+    let mut player = mock_engine("first");
+    let start = GameState::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+    let moves = [parse_uci(&start, "e1g1").unwrap()];
+    let mut after = start.clone();
+    after.apply_move(moves[0]);
+    assert_eq!(player.try_get_move(&start, &moves).unwrap(), after.legal_moves()[0]);
+}
+
+#[test]
+fn test_player_sends_game_history() {
+    let mut player = mock_engine("count");
+    let mut state = GameState::default();
+    player.on_game_start(PieceColor::Black, &state);
+    for _ in 0..3 {
+        let opponent_move = state.legal_moves()[0];
+        state.apply_move(opponent_move);
+        player.on_opponent_move(opponent_move);
+        // the engine plays the move numbered by the length of the history it got
+        let expected = state.legal_moves()[state.fullmove_number * 2 - 1];
+        let m = player.get_move(&state).played_move().unwrap();
+        assert_eq!(m, expected);
+        state.apply_move(m);
+    }
+
+    // a position outside the game starts over from there
+    let other = GameState::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+    assert_eq!(player.get_move(&other).played_move(), Some(other.legal_moves()[0]));
+}
+
+#[test]
+fn test_options_are_sent() {
+    let mut player = mock_engine("first").with_option("Mode", "illegal");
+    let error = player.try_get_move(&GameState::default(), &[]).unwrap_err();
+    assert!(matches!(error, UciPlayerError::IllegalMove(ref m) if m == "a1a1"), "{}", error);
+}
+
+#[test]
+fn test_crash_then_restart() {
+    let mut player = mock_engine("first").with_option("Mode", "crash");
+    let error = player.try_get_move(&GameState::default(), &[]).unwrap_err();
+    assert!(matches!(error, UciPlayerError::EngineExited), "{}", error);
+
+    // the engine is started again with the same options, and crashes again
+    let error = player.try_get_move(&GameState::default(), &[]).unwrap_err();
+    assert!(matches!(error, UciPlayerError::EngineExited), "{}", error);
+}
+
+#[test]
+fn test_slow_engine_is_stopped() {
+    let mut player = mock_engine("hang");
+    player.timeout = Duration::from_millis(100);
+    let start = Instant::now();
+    let state = GameState::default();
+    assert_eq!(player.try_get_move(&state, &[]).unwrap(), state.legal_moves()[0]);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test_case("deaf", "bestmove"; "ignores stop")]
+#[test_case("mute", "uciok"; "no handshake")]
+fn test_timeouts(mode: &str, reply: &str) {
+    let mut player = mock_engine(mode);
+    player.timeout = Duration::from_millis(100);
+    player.handshake_timeout = Duration::from_millis(500);
+    let start = Instant::now();
+    let error = player.try_get_move(&GameState::default(), &[]).unwrap_err();
+    assert!(matches!(error, UciPlayerError::Timeout(expected) if expected == reply), "{}", error);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_missing_executable() {
+    let mut player = UciPlayer::new("/nonexistent/engine");
+    let error = player.try_get_move(&GameState::default(), &[]).unwrap_err();
+    assert!(matches!(error, UciPlayerError::Spawn(_)), "{}", error);
+}
+
+#[test]
+fn test_go_arguments() {
+    let go = Go {
+        wtime: Some(Duration::from_millis(500)),
+        btime: Some(Duration::from_millis(500)),
+        ..Go::default()
+    };
+    let mut player = mock_engine("first").with_go(go);
+    assert!(player.try_get_move(&GameState::default(), &[]).is_ok());
+}
+
+#[test]
+fn test_player_sends_clock() {
+    let mut player = mock_engine("clock");
+    let state = GameState::default();
+    let time_control = |millis| TimeControl {
+        remaining: Duration::from_millis(millis),
+        ..TimeControl::default()
+    };
+    player.on_clock(time_control(7), time_control(3));
+    assert_eq!(player.get_move(&state), PlayerAction::Move(state.legal_moves()[7]));
+    // without a clock for this move, only the configured limits are sent
+    assert_eq!(player.get_move(&state), PlayerAction::Move(state.legal_moves()[0]));
+}
+
+#[test]
+fn test_resigns_on_failure() {
+    let mut player = mock_engine("crash");
+    assert_eq!(player.get_move(&GameState::default()), PlayerAction::Resign);
+    assert!(matches!(player.last_error(), Some(UciPlayerError::EngineExited)));
+}
+
+#[test]
+fn test_plays_against_own_engine() {
+    let mut player = UciPlayer::new(env!("CARGO_BIN_EXE_ruschess-uci")).with_go(Go {
+        depth: Some(3),
+        ..Go::default()
+    });
+    let state = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let m = player.get_move(&state).played_move().unwrap();
+    assert_eq!(to_uci(&state, m), "a1a8");
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::EngineProcess;
+
+#[test]
+fn test_handshake() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("uci");
+    let lines = engine.expect("uciok");
+    assert!(lines[0].starts_with("id name ruschess"));
+    for option in ["Hash", "Threads", "MultiPV", "Skill Level", "UCI_LimitStrength", "UCI_Elo", "UCI_Chess960"] {
+        assert!(lines.iter().any(|line| line.starts_with(&format!("option name {} ", option))));
+    }
+    engine.send("isready");
+    engine.expect("readyok");
+    engine.quit();
+}
+
+#[test]
+fn test_go_depth() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("ucinewgame");
+    engine.send("position startpos moves e2e4 e7e5");
+    engine.send("go depth 3");
+    let lines = engine.expect("bestmove");
+    assert!(lines.iter().any(|line| line.starts_with("info depth 3 ")));
+    let bestmove = lines.last().unwrap();
+    assert!(bestmove.contains(" ponder "), "{}", bestmove);
+    engine.quit();
+}
+
+#[test]
+fn test_go_depth_zero() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos");
+    engine.send("go depth 0");
+    let lines = engine.expect("bestmove");
+    assert!(lines.iter().any(|line| line.starts_with("info depth 1 ")));
+    assert_ne!(lines.last().unwrap(), "bestmove 0000");
+    engine.send("isready");
+    engine.expect("readyok");
+    engine.quit();
+}
+
+#[test]
+fn test_finds_mate() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    engine.send("go depth 3");
+    let lines = engine.expect("bestmove");
+    assert!(lines.iter().any(|line| line.contains("score mate 1")));
+    assert_eq!(lines.last().unwrap(), "bestmove a1a8");
+    engine.quit();
+}
+
+#[test]
+fn test_go_nodes_and_movetime() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos");
+    engine.send("go nodes 2000");
+    engine.expect("bestmove");
+    engine.send("go movetime 100");
+    engine.expect("bestmove");
+    engine.quit();
+}
+
+#[test]
+fn test_go_clock() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos moves d2d4");
+    engine.send("go wtime 1000 btime 1000 winc 10 binc 10");
+    engine.expect("bestmove");
+    engine.quit();
+}
+
+#[test]
+fn test_go_infinite_waits_for_stop() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    engine.send("go infinite");
+    // the mate ends the search, but bestmove must wait for stop
+    engine.expect("info depth 1 ");
+    engine.expect_silence();
+    engine.send("isready");
+    engine.expect("readyok");
+    engine.send("stop");
+    assert_eq!(engine.expect("bestmove").last().unwrap(), "bestmove a1a8");
+    engine.quit();
+}
+
+#[test]
+fn test_ponderhit() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos moves e2e4");
+    engine.send("go ponder wtime 1000 btime 1000");
+    engine.expect("info depth 1 ");
+    engine.send("ponderhit");
+    engine.expect("bestmove");
+    engine.quit();
+}
+
+#[test]
+fn test_setoption_multi_pv_and_threads() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("setoption name Hash value 1");
+    engine.send("setoption name Threads value 2");
+    engine.send("setoption name MultiPV value 3");
+    engine.send("position startpos");
+    engine.send("go depth 2");
+    let lines = engine.expect("bestmove");
+    for multi_pv in 1..=3 {
+        let tag = format!(" multipv {} ", multi_pv);
+        assert!(lines.iter().any(|line| line.starts_with("info depth 2 ") && line.contains(&tag)));
+    }
+    engine.quit();
+}
+
+#[test]
+fn test_setoption_skill() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("setoption name Skill Level value 0");
+    engine.send("position startpos");
+    engine.send("go depth 8");
+    let lines = engine.expect("bestmove");
+    // the weakest level searches a single ply
+    assert!(lines.iter().all(|line| !line.starts_with("info depth 2 ")));
+
+    engine.send("setoption name UCI_LimitStrength value true");
+    engine.send("setoption name UCI_Elo value 1000");
+    engine.send("go depth 8");
+    let lines = engine.expect("bestmove");
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2 ")));
+    assert!(lines.iter().all(|line| !line.starts_with("info depth 3 ")));
+
+    engine.send("setoption name UCI_LimitStrength value maybe");
+    engine.expect("info string invalid value for UCI_LimitStrength: maybe");
+    engine.quit();
+}
+
+#[test]
+fn test_setoption_chess960() {
+    let mut engine = EngineProcess::spawn();
+    // with every move reported, castling shows up in one of the lines
+    engine.send("setoption name MultiPV value 20");
+    engine.send("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    engine.send("go depth 1");
+    let lines = engine.expect("bestmove");
+    assert!(lines.iter().any(|line| line.ends_with(" pv e1g1")));
+
+    engine.send("setoption name UCI_Chess960 value true");
+    engine.send("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    engine.send("go depth 1");
+    let lines = engine.expect("bestmove");
+    assert!(lines.iter().any(|line| line.ends_with(" pv e1h1")));
+    assert!(lines.iter().all(|line| !line.contains(" e1g1")));
+    engine.quit();
+}
+
+#[test]
+fn test_errors_are_reported() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos moves e2e5");
+    engine.expect("info string illegal move: e2e5");
+    engine.send("setoption name Colour value red");
+    engine.expect("info string unknown option: Colour");
+    engine.send("frobnicate");
+    engine.expect("info string unknown command: frobnicate");
+    engine.quit();
+}
+
+#[test]
+fn test_end_of_input_waits_for_search() {
+    let mut engine = EngineProcess::spawn();
+    engine.send("position startpos");
+    engine.send("go depth 2");
+    drop(engine.stdin);
+    let lines: Vec<String> = engine.lines.iter().collect();
+    assert!(lines.last().unwrap().starts_with("bestmove"));
+    assert!(engine.child.wait().unwrap().success());
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::EngineProcess;
+
+fn spawn_xboard() -> EngineProcess {
+    let mut engine = EngineProcess::spawn();
+    engine.send("xboard");
+    engine.send("protover 2");
+    let features = engine.expect("feature");
+    assert!(features.last().unwrap().ends_with("done=1"));
+    engine
+}
+
+/// Waits until every command sent so far has been handled.
+fn sync(engine: &mut EngineProcess) -> Vec<String> {
+    engine.send("ping 7");
+    engine.expect("pong 7")
+}
+
+#[test]
+fn test_handshake() {
+    let mut engine = spawn_xboard();
+    engine.send("ping 1");
+    assert_eq!(engine.expect("pong"), vec!["pong 1"]);
+    engine.quit();
+}
+
+#[test]
+fn test_engine_replies_as_black() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("level 0 1 0");
+    engine.send("post");
+    engine.send("time 600");
+    engine.send("otim 600");
+    engine.send("usermove e2e4");
+    let lines = engine.expect("move");
+    // thinking output comes first: depth, score, time, nodes and the variation
+    assert!(lines[0].starts_with("1 "));
+    assert!(lines[0].contains("1... "));
+    engine.quit();
+}
+
+#[test]
+fn test_go_plays_the_side_to_move() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("force");
+    engine.send("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    engine.send("sd 3");
+    engine.send("go");
+    assert_eq!(engine.expect("move"), vec!["move a1a8"]);
+    assert_eq!(engine.expect("1-0"), vec!["1-0 {White mates}"]);
+    engine.quit();
+}
+
+#[test]
+fn test_depth_zero() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("force");
+    engine.send("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    engine.send("sd 0");
+    engine.send("go");
+    assert_eq!(engine.expect("move"), vec!["move a1a8"]);
+    sync(&mut engine);
+    engine.quit();
+}
+
+#[test]
+fn test_move_time() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("st 0.1");
+    engine.send("go");
+    engine.expect("move");
+    engine.quit();
+}
+
+#[test]
+fn test_force_mode_and_undo() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("force");
+    engine.send("usermove e2e4");
+    engine.send("usermove e7e5");
+    engine.send("undo");
+    engine.send("usermove e7e5");
+    engine.send("remove");
+    engine.send("e2e4");
+    // with the moves taken back, only the last one is illegal
+    engine.send("usermove e7e5");
+    engine.send("usermove e7e5");
+    let lines = sync(&mut engine);
+    assert_eq!(lines, vec!["Illegal move: e7e5", "pong 7"]);
+    engine.quit();
+}
+
+#[test]
+fn test_errors() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("usermove e2e5");
+    engine.send("setboard nonsense");
+    engine.send("frobnicate");
+    let lines = sync(&mut engine);
+    assert_eq!(
+        lines,
+        vec![
+            "Illegal move: e2e5",
+            "tellusererror Illegal position",
+            "Error (unknown command): frobnicate",
+            "pong 7"
+        ]
+    );
+    engine.quit();
+}
+
+#[test]
+fn test_analyze() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("force");
+    engine.send("analyze");
+    engine.expect("2 ");
+    engine.send("usermove e2e4");
+    // the analysis restarts from the new position, with black to move
+    let lines = engine.expect("1 ");
+    assert!(lines.last().unwrap().contains("1... "));
+    engine.send("exit");
+    let lines = sync(&mut engine);
+    assert!(lines.iter().all(|line| !line.starts_with("move")));
+    engine.quit();
+}
+
+#[test]
+fn test_result_stops_thinking() {
+    let mut engine = spawn_xboard();
+    engine.send("new");
+    engine.send("level 0 60 0");
+    engine.send("go");
+    engine.send("result 1-0 {White resigns}");
+    let lines = sync(&mut engine);
+    assert!(lines.iter().all(|line| !line.starts_with("move")));
+    engine.expect_silence();
+    engine.quit();
+}